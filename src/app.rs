@@ -1,7 +1,7 @@
 use std::{
     path::{Path, PathBuf},
     rc::Rc,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Instant,
 };
 
@@ -19,16 +19,37 @@ use winit::platform::wayland::WindowAttributesExtWayland;
 
 use crate::{
     asset_manager::AssetManager,
+    assets::{
+        AssetLoader,
+        background::{BackgroundLoader, LoadEvent, LoadProgress},
+        disk_cache::DiskCache,
+        hot_reload::AssetChange,
+        level,
+    },
     renderer::{RenderContext, Renderer, camera::Camera},
     scene::Scene,
+    vfs::{self, OverlayVfs, Vfs},
 };
 
 pub struct App {
-    magicka_path: PathBuf,
+    vfs: Arc<dyn Vfs>,
+    disk_cache: Option<DiskCache>,
 
     asset_manager: Option<AssetManager>,
+    background_loader: Option<BackgroundLoader>,
     renderer: Option<Renderer>,
     scene: Option<Scene>,
+    /// loaded once the window exists
+    start_level: PathBuf,
+    /// the level `scene` was built from, as it was requested from the background loader
+    loaded_level: Option<PathBuf>,
+    /// the level requested from the background loader that hasn't finished loading yet
+    pending_level: Option<PathBuf>,
+    /// the level xmls of the install, listed the first time the level is switched. files that
+    /// turn out not to be levels or fail to load are taken out
+    levels: Option<Vec<PathBuf>>,
+    /// the level path being typed, see `handle_level_input`
+    level_input: Option<String>,
 
     last_time: Instant,
     camera_input_state: InputState,
@@ -37,13 +58,39 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(magicka_path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+    /// `overlays` are directories or zip archives layered over the install in order, so files
+    /// in later ones replace files of the same path in earlier ones. `level` is loaded first,
+    /// `DEFAULT_LEVEL` if there is none
+    pub fn new(
+        magicka_path: &Path,
+        overlays: &[PathBuf],
+        disk_cache: Option<DiskCache>,
+        level: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let base = vfs::open(magicka_path)?;
+        let vfs: Arc<dyn Vfs> = if overlays.is_empty() {
+            base.into()
+        } else {
+            let mut layers = vec![base];
+            for overlay in overlays {
+                layers.push(vfs::open(overlay)?);
+            }
+            Arc::new(OverlayVfs::new(layers)?)
+        };
+
         let app = App {
-            magicka_path: magicka_path.into(),
+            vfs,
+            disk_cache,
 
             asset_manager: None,
+            background_loader: None,
             renderer: None,
             scene: None,
+            start_level: level.unwrap_or_else(|| DEFAULT_LEVEL.into()),
+            loaded_level: None,
+            pending_level: None,
+            levels: None,
+            level_input: None,
 
             last_time: Instant::now(),
             camera_input_state: InputState::default(),
@@ -53,6 +100,214 @@ impl App {
         Ok(app)
     }
 
+    /// applies whatever the background loader finished since the last frame
+    fn poll_background_loader(&mut self) {
+        let Some(background_loader) = &self.background_loader else {
+            return;
+        };
+
+        let mut progress = None;
+        for event in background_loader.poll() {
+            match event {
+                LoadEvent::Progress(p) => progress = Some(p),
+                LoadEvent::Level {
+                    path,
+                    result,
+                    evicted,
+                } => {
+                    log::info!(
+                        "evicted {} cpu assets ({:.1} MiB)",
+                        evicted.assets,
+                        evicted.bytes as f64 / MIB
+                    );
+                    progress = None;
+                    if self.pending_level.as_ref() == Some(&path) {
+                        self.pending_level = None;
+                    }
+                    let asset_manager = self.asset_manager.as_mut().unwrap();
+                    asset_manager.begin_generation();
+                    match result.and_then(|level| Scene::from_level(&level, asset_manager)) {
+                        Ok(mut scene) => {
+                            log::info!("loaded level {}", path.display());
+                            // a reload of the same level keeps looking at the same spot
+                            if self.loaded_level.as_ref() == Some(&path)
+                                && let Some(previous) = &mut self.scene
+                            {
+                                std::mem::swap(&mut scene.camera, &mut previous.camera);
+                            }
+                            // drop the previous level before evicting, so its assets are unreferenced
+                            self.scene = Some(scene);
+                            self.loaded_level = Some(path);
+                            let evicted = asset_manager.evict_unused();
+                            log::info!(
+                                "evicted {} gpu assets ({:.1} MiB)",
+                                evicted.assets,
+                                evicted.bytes as f64 / MIB
+                            );
+                        }
+                        Err(e) => {
+                            log::error!("failed to load level {}: {e:#}", path.display());
+                            // so stepping through levels doesn't keep landing on it
+                            if let Some(levels) = &mut self.levels {
+                                levels.retain(|level| level != &path);
+                            }
+                        }
+                    }
+                    self.update_title(None);
+                }
+            }
+        }
+
+        if progress.is_some() {
+            self.update_title(progress);
+        }
+    }
+
+    /// shows the level being typed, or else how far along loading is, or else the loaded level
+    fn update_title(&self, progress: Option<LoadProgress>) {
+        let title = match (&self.level_input, progress, &self.loaded_level) {
+            (Some(input), _, _) => format!("{TITLE} - level: {input}_"),
+            (None, Some(progress), _) => format!("{TITLE} - {}", format_progress(progress)),
+            (None, None, Some(level)) => format!("{TITLE} - {}", level.display()),
+            (None, None, None) => TITLE.to_owned(),
+        };
+        self.renderer.as_ref().unwrap().window.set_title(&title);
+    }
+
+    /// the previous scene stays up until the new one is ready, it is torn down once replaced
+    fn load_level(&mut self, path: PathBuf) {
+        log::info!("loading level {}", path.display());
+        if let Some(background_loader) = &self.background_loader {
+            background_loader.load_level(path.clone());
+        }
+        self.pending_level = Some(path);
+    }
+
+    fn levels(&mut self) -> &mut Vec<PathBuf> {
+        self.levels.get_or_insert_with(|| {
+            let levels = level::find_levels(self.vfs.as_ref()).unwrap_or_else(|e| {
+                log::error!("failed to find levels: {e:#}");
+                Vec::new()
+            });
+            log::info!("found {} level xmls", levels.len());
+            levels
+        })
+    }
+
+    /// loads the level `step` places after the current one in `levels`, wrapping around. the
+    /// current one is the level still loading if there is one, so paging quickly goes on from
+    /// the last level asked for. only the xml being stepped to is read, to skip files that
+    /// aren't levels
+    fn step_level(&mut self, step: isize) {
+        let current = self
+            .pending_level
+            .as_ref()
+            .or(self.loaded_level.as_ref())
+            .map(|level| level.to_string_lossy().to_ascii_lowercase());
+        let vfs = self.vfs.clone();
+        let levels = self.levels();
+
+        let mut next = match levels
+            .iter()
+            .position(|level| Some(level.to_string_lossy().to_ascii_lowercase()) == current)
+        {
+            Some(i) => i as isize + step,
+            // not one of the listed levels, start from either end
+            None if step > 0 => 0,
+            None => -1,
+        };
+        while !levels.is_empty() {
+            let i = next.rem_euclid(levels.len() as isize) as usize;
+            let is_level = vfs
+                .read_to_string(&levels[i])
+                .is_ok_and(|xml| level::is_level_xml(&xml));
+            if is_level {
+                let level = levels[i].clone();
+                self.load_level(level);
+                return;
+            }
+
+            levels.remove(i);
+            // the following levels moved down into `i`
+            next = if step < 0 { i as isize - 1 } else { i as isize };
+        }
+        log::warn!("no levels to switch to");
+    }
+
+    /// a typed path is loaded as is, unless it names a discovered level by its path or file
+    /// name, ignoring case and the extension
+    fn resolve_level_input(&mut self, input: &str) -> PathBuf {
+        let typed = input.trim().replace('\\', "/").to_ascii_lowercase();
+        let typed = typed.strip_suffix(".xml").unwrap_or(&typed);
+        let matches = |level: &PathBuf| {
+            let path = level
+                .to_string_lossy()
+                .replace('\\', "/")
+                .to_ascii_lowercase();
+            let stem = level
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_ascii_lowercase());
+            path.strip_suffix(".xml") == Some(typed) || stem.as_deref() == Some(typed)
+        };
+
+        match self.levels().iter().find(|level| matches(level)) {
+            Some(level) => level.clone(),
+            None => PathBuf::from(input.trim()),
+        }
+    }
+
+    /// takes all key presses while a level path is being typed. enter loads it, escape cancels
+    fn handle_level_input(&mut self, code: KeyCode, state: ElementState, text: Option<&str>) {
+        if state != ElementState::Pressed {
+            return;
+        }
+        let Some(input) = &mut self.level_input else {
+            return;
+        };
+
+        match code {
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                let input = self.level_input.take().unwrap();
+                if !input.trim().is_empty() {
+                    let path = self.resolve_level_input(&input);
+                    self.load_level(path);
+                }
+            }
+            KeyCode::Escape => self.level_input = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            _ => {
+                if let Some(text) = text {
+                    input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+        self.update_title(None);
+    }
+
+    /// applies effects and level xml that were edited while running
+    fn poll_asset_changes(&mut self) {
+        let asset_manager = self.asset_manager.as_mut().unwrap();
+        for change in asset_manager.poll_changes() {
+            match change {
+                AssetChange::VisualEffect { old, new } => {
+                    if let Some(scene) = &mut self.scene {
+                        let replaced = scene.replace_visual_effect(&old, &new);
+                        log::debug!("updated {replaced} visual effect nodes");
+                    }
+                }
+                AssetChange::Level(_) => {
+                    if let (Some(background_loader), Some(level)) =
+                        (&self.background_loader, &self.loaded_level)
+                    {
+                        background_loader.load_level(level.clone());
+                    }
+                }
+            }
+        }
+    }
+
     fn update(&mut self, dt: f32) {
         let scene = self.scene.as_mut().unwrap();
 
@@ -98,10 +353,30 @@ impl App {
         &mut self,
         code: KeyCode,
         state: ElementState,
+        text: Option<&str>,
         _event_loop: &ActiveEventLoop,
     ) {
+        if self.level_input.is_some() {
+            self.handle_level_input(code, state, text);
+            return;
+        }
+
         match (code, state) {
             (KeyCode::Escape, ElementState::Pressed) => self.grab_cursor(false).unwrap(),
+            (KeyCode::F2, ElementState::Pressed) => {
+                self.level_input = Some(String::new());
+                // releases while typing aren't seen, so stop moving now
+                self.camera_input_state = InputState::default();
+                self.update_title(None);
+            }
+            (KeyCode::F3, ElementState::Pressed) => self.log_asset_stats(),
+            (KeyCode::PageUp, ElementState::Pressed) => self.step_level(-1),
+            (KeyCode::PageDown, ElementState::Pressed) => self.step_level(1),
+            (KeyCode::Home, ElementState::Pressed) => {
+                if let Some(scene) = &mut self.scene {
+                    scene.reset_camera();
+                }
+            }
             (KeyCode::KeyW, ElementState::Pressed) => self.camera_input_state.forward = true,
             (KeyCode::KeyW, ElementState::Released) => self.camera_input_state.forward = false,
             (KeyCode::KeyS, ElementState::Pressed) => self.camera_input_state.backward = true,
@@ -120,6 +395,16 @@ impl App {
         }
     }
 
+    /// the same report as `dev stats`, for everything uploaded that's still alive
+    fn log_asset_stats(&mut self) {
+        let Some(asset_manager) = &mut self.asset_manager else {
+            return;
+        };
+        for line in asset_manager.stats().report(ASSET_STATS_HEAVIEST) {
+            log::info!("{line}");
+        }
+    }
+
     fn handle_mouse_input(&mut self, button: MouseButton, state: ElementState) {
         match (button, state) {
             (MouseButton::Left, ElementState::Pressed) => self.grab_cursor(true).unwrap(),
@@ -162,7 +447,7 @@ impl App {
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window_attributes = WindowAttributes::default().with_title("Aldrheim");
+        let window_attributes = WindowAttributes::default().with_title(TITLE);
 
         #[cfg(target_os = "linux")]
         let window_attributes = window_attributes.with_name("cndofx.Aldrheim", "");
@@ -172,8 +457,10 @@ impl ApplicationHandler for App {
         let (render_context, surface, surface_config) =
             pollster::block_on(RenderContext::new(window.clone())).unwrap();
         let render_context = Rc::new(render_context);
-        let mut asset_manager =
-            AssetManager::new(&self.magicka_path, render_context.clone()).unwrap();
+        let mut loader = AssetLoader::new(self.vfs.clone());
+        loader.set_disk_cache(self.disk_cache.clone());
+        let loader = Arc::new(Mutex::new(loader));
+        let mut asset_manager = AssetManager::new(loader.clone(), render_context.clone());
         let renderer = Renderer::new(
             render_context,
             window,
@@ -187,9 +474,11 @@ impl ApplicationHandler for App {
         self.asset_manager = Some(asset_manager);
 
         if self.scene.is_none() {
-            let asset_manager = self.asset_manager.as_mut().unwrap();
-            let scene = load_scene(asset_manager).unwrap();
-            self.scene = Some(scene);
+            // render an empty scene until the level is ready
+            self.scene = Some(Scene::new());
+            let background_loader = BackgroundLoader::spawn(loader).unwrap();
+            self.background_loader = Some(background_loader);
+            self.load_level(self.start_level.clone());
         }
 
         self.last_time = Instant::now();
@@ -208,6 +497,8 @@ impl ApplicationHandler for App {
                 let current_time = Instant::now();
                 let dt = (current_time - self.last_time).as_secs_f32();
                 self.last_time = current_time;
+                self.poll_background_loader();
+                self.poll_asset_changes();
                 self.update(dt);
 
                 let renderer = self.renderer.as_mut().unwrap();
@@ -229,10 +520,11 @@ impl ApplicationHandler for App {
                     KeyEvent {
                         physical_key: PhysicalKey::Code(code),
                         state,
+                        text,
                         ..
                     },
                 ..
-            } => self.handle_key_input(code, state, event_loop),
+            } => self.handle_key_input(code, state, text.as_deref(), event_loop),
             WindowEvent::MouseInput { state, button, .. } => self.handle_mouse_input(button, state),
             _ => {}
         }
@@ -257,15 +549,22 @@ struct InputState {
     fast: bool,
 }
 
-fn load_scene(asset_manager: &mut AssetManager) -> anyhow::Result<Scene> {
-    let level_path = Path::new("Content/Levels/WizardCastle/wc_s1.xml");
-    // let level_path = Path::new("Content/Levels/WizardCastle/wc_s2.xml");
-    // let level_path = Path::new("Content/Levels/WizardCastle/wc_s4.xml");
-    // let level_path = Path::new("Content/Levels/Challenges/chs_havindr_arena.xml");
-    // let level_path = Path::new("Content/Levels/Tsar/Tsar_WizardCastle/twc_s3BF.xml");
-    // let level_path = Path::new("Content/Levels/EndofWorld/ew_s1.xml");
+/// how many of the heaviest assets the stats logged with F3 list
+const ASSET_STATS_HEAVIEST: usize = 20;
+
+const TITLE: &str = "Aldrheim";
+
+/// loaded when no level is passed to `App::new`
+const DEFAULT_LEVEL: &str = "Content/Levels/WizardCastle/wc_s1.xml";
 
-    let scene = Scene::load_level(level_path, None, asset_manager)?;
+const MIB: f64 = 1024.0 * 1024.0;
 
-    Ok(scene)
+fn format_progress(progress: LoadProgress) -> String {
+    format!(
+        "loading {}/{} files ({:.1}/{:.1} MiB)",
+        progress.files_done,
+        progress.files_total,
+        progress.bytes_done as f64 / MIB,
+        progress.bytes_total as f64 / MIB
+    )
 }
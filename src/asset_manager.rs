@@ -1,183 +1,178 @@
+//! the gpu half of asset loading, uploads what `assets::AssetLoader` produces
+
 use std::{
-    collections::HashMap,
-    io::BufReader,
-    path::{Path, PathBuf},
+    path::Path,
     rc::Rc,
+    sync::{Arc, Mutex, MutexGuard, TryLockError},
+    time::{Duration, Instant},
 };
 
-use anyhow::Context;
-use glam::{Mat4, Quat, Vec3};
 use wgpu::util::DeviceExt;
 
 use crate::{
-    asset_manager::vfx::VisualEffectAsset,
-    renderer::{
-        RenderContext,
-        pipelines::{render_deferred_effect::RenderDeferredEffectUniform, skymap::SkymapUniform},
+    assets::{
+        AssetLoader, CpuBiTree, CpuTexture, MaterialParams, TextureDimension,
+        cache::{AssetCache, EvictionStats},
+        hot_reload::AssetChange,
+        model::{CpuBone, CpuModel},
+        stats::{AssetRecord, AssetStats},
+    },
+    renderer::{RenderContext, pipelines::render_deferred_effect::RenderDeferredEffectUniform},
+    xnb::asset::{
+        index_buffer::IndexBuffer, model::BoundingSphere, texture_2d::ColorSpace,
+        vertex_buffer::VertexBuffer,
     },
-    scene::{self, SceneNode, SceneNodeKind, vfx::VisualEffectNode},
-    xnb::{self, BiTreeNode, Xnb, XnbContent, asset::XnbAsset},
 };
 
-pub mod vfx;
-
 pub struct AssetManager {
-    magicka_path: PathBuf,
+    loader: Arc<Mutex<AssetLoader>>,
     render_context: Rc<RenderContext>,
 
     // using `Rc` instead of `Weak` so that resources arent immediately dropped
     // when no longer used. if all the "goblin" enemies died, the goblin mesh
     // would disappear, even though the game is likely to need the goblin mesh
-    // again. i'm thinking all meshes should be loaded during a loading screen,
-    // and all unneeded meshes are dropped during that same loading screen
-    textures: HashMap<PathBuf, Rc<TextureAsset>>,
-    models: HashMap<PathBuf, Rc<ModelAsset>>,
-
-    // visual effects are keyed by filename strings instead of full paths
-    // because they are referenced by filename (unique, without extension)
-    // in other data such as levels, rather than by path like other assets
-    //
-    // they are also preloaded up front as they can located in arbitrary subdirectories,
-    // so locating the file would require a recursive search of the entire Content/Effect directory
-    visual_effects: HashMap<String, Rc<VisualEffectAsset>>,
+    // again. instead everything is kept until the next level transition, where
+    // whatever the new level didn't ask for is dropped with `evict_unused`
+    textures: AssetCache<Rc<TextureAsset>>,
+    models: AssetCache<Rc<ModelAsset>>,
+    /// everything uploaded that's still alive, cached or not
+    tracked: Vec<TrackedAsset>,
+
+    last_change_poll: Instant,
+}
+
+/// the stats of an uploaded asset, kept as long as the asset is
+struct TrackedAsset {
+    records: Vec<AssetRecord>,
+    /// references to the asset outside of `AssetManager`'s caches, `None` once it's dropped
+    refs: Box<dyn Fn() -> Option<usize>>,
 }
 
+/// how often `poll_changes` actually checks the watched files
+const CHANGE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 impl AssetManager {
-    pub fn new(
-        magicka_path: impl Into<PathBuf>,
-        render_context: Rc<RenderContext>,
-    ) -> anyhow::Result<Self> {
-        let magicka_path = magicka_path.into();
-        let visual_effects = preload_visual_effects(&magicka_path)?;
-
-        Ok(AssetManager {
-            magicka_path,
+    pub fn new(loader: Arc<Mutex<AssetLoader>>, render_context: Rc<RenderContext>) -> Self {
+        AssetManager {
+            loader,
             render_context,
-            visual_effects,
-            textures: HashMap::new(),
-            models: HashMap::new(),
-        })
+            textures: AssetCache::default(),
+            models: AssetCache::default(),
+            tracked: Vec::new(),
+            last_change_poll: Instant::now(),
+        }
     }
 
-    pub fn read_to_string(&self, path: &Path, base: Option<&Path>) -> anyhow::Result<String> {
-        let path = self.resolve_path(path, base, None)?;
-        let string = std::fs::read_to_string(&path)?;
-        log::debug!("loaded string data from file {}", path.display());
-        Ok(string)
+    /// blocks while a `BackgroundLoader` sharing the loader is busy
+    pub fn loader(&self) -> MutexGuard<'_, AssetLoader> {
+        self.loader.lock().unwrap()
     }
 
-    pub fn load_texture(
-        &mut self,
-        path: &Path,
-        base: Option<&Path>,
-    ) -> anyhow::Result<Rc<TextureAsset>> {
-        let path = self.resolve_path(path, base, Some("xnb"))?;
-        if let Some(texture) = self.textures.get(&path) {
-            return Ok(texture.clone());
+    /// the effects and level xml that changed on disk, see `AssetLoader::poll_changes`. meant to
+    /// be called every frame, but only checks every `CHANGE_POLL_INTERVAL`, and not at all while
+    /// the loader is busy with something else
+    pub fn poll_changes(&mut self) -> Vec<AssetChange> {
+        if self.last_change_poll.elapsed() < CHANGE_POLL_INTERVAL {
+            return Vec::new();
         }
+        self.last_change_poll = Instant::now();
 
-        let content = self.load_xnb_content(&path)?;
-        let texture = match &content.primary_asset {
-            XnbAsset::Texture2D(texture) => {
-                let texture = self.load_texture_inner_2d(texture)?;
-                log::debug!("loaded Texture2D from file {}", path.display());
-                Rc::new(texture)
-            }
-            XnbAsset::Texture3D(texture) => {
-                let texture = self.load_texture_inner_3d(texture)?;
-                log::debug!("loaded Texture3D from file {}", path.display());
-                Rc::new(texture)
-            }
-            _ => {
-                anyhow::bail!("expected Texture2D or Texture3D at path {}", path.display());
-            }
+        let mut loader = match self.loader.try_lock() {
+            Ok(loader) => loader,
+            Err(TryLockError::WouldBlock) => return Vec::new(),
+            Err(TryLockError::Poisoned(e)) => panic!("{e}"),
         };
-
-        self.textures.insert(path, texture.clone());
-
-        Ok(texture)
+        loader.poll_changes()
     }
 
-    fn load_texture_inner_2d(&self, texture: &xnb::Texture2D) -> anyhow::Result<TextureAsset> {
-        let texture_format = texture.format.to_wgpu();
-
-        let texture_size = wgpu::Extent3d {
-            width: texture.width,
-            height: texture.height,
-            depth_or_array_layers: 1,
-        };
+    /// call before uploading a new level, so the assets it uses are marked as used
+    pub fn begin_generation(&mut self) {
+        self.textures.begin_generation();
+        self.models.begin_generation();
+    }
 
-        let wgpu_texture = self
-            .render_context
-            .device
-            .create_texture(&wgpu::TextureDescriptor {
-                label: Some("Texture 2D"),
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                size: texture_size,
-                format: texture_format,
-                dimension: wgpu::TextureDimension::D2,
-                mip_level_count: texture.mips.len() as u32,
-                sample_count: 1,
-                view_formats: &[],
-            });
+    /// drops the gpu assets nothing used since `begin_generation`. call this after the previous
+    /// level's scene is gone, otherwise its assets are still referenced and are kept. the cpu
+    /// side is evicted by whoever loads the next level, so this never waits on the loader
+    pub fn evict_unused(&mut self) -> EvictionStats {
+        let mut gpu = self.textures.evict_unused();
+        gpu += self.models.evict_unused();
+        // otherwise the records of every asset ever uploaded pile up until `stats` is called
+        self.tracked.retain(|tracked| (tracked.refs)().is_some());
+        gpu
+    }
 
-        for (i, mip) in texture.mips.iter().enumerate() {
-            // TODO: is this the correct thing to do here?
-            // wgpu validation doesnt like copying 2x2 pixel mips with 4x4 block size
-            let mip_size = wgpu::Extent3d {
-                width: (texture.width / 2u32.pow(i as u32)).max(texture.format.block_dim()),
-                height: (texture.height / 2u32.pow(i as u32)).max(texture.format.block_dim()),
-                depth_or_array_layers: 1,
+    /// what every uploaded asset that's still alive costs, see `AssetStats::report`
+    pub fn stats(&mut self) -> AssetStats {
+        let mut stats = AssetStats::default();
+        self.tracked.retain(|tracked| {
+            let Some(refs) = (tracked.refs)() else {
+                return false;
             };
+            stats
+                .records
+                .extend(tracked.records.iter().map(|record| AssetRecord {
+                    refs,
+                    ..record.clone()
+                }));
+            true
+        });
+        stats
+    }
 
-            self.render_context.queue.write_texture(
-                wgpu::TexelCopyTextureInfo {
-                    texture: &wgpu_texture,
-                    mip_level: i as u32,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                mip,
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(texture.bytes_per_row(i)?),
-                    rows_per_image: Some(texture.rows_per_image(i)?),
-                },
-                mip_size,
-            );
-        }
-
-        let view = wgpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let bind_group = self
-            .render_context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Texture2D Bind Group"),
-                layout: &self.render_context.texture_2d_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(
-                            &self.render_context.linear_sampler,
-                        ),
-                    },
-                ],
-            });
+    /// `cached` is whether one of the caches holds on to `asset`, that reference isn't counted
+    fn track<T: 'static>(&mut self, asset: &Rc<T>, cached: bool, records: Vec<AssetRecord>) {
+        let asset = Rc::downgrade(asset);
+        self.tracked.push(TrackedAsset {
+            records,
+            refs: Box::new(move || match asset.strong_count() {
+                0 => None,
+                count => Some(count - cached as usize),
+            }),
+        });
+    }
 
-        Ok(TextureAsset {
-            texture: wgpu_texture,
-            view,
-            bind_group,
-        })
+    /// loads a texture and uploads it in one step
+    pub fn load_texture(
+        &mut self,
+        path: &Path,
+        base: Option<&Path>,
+    ) -> anyhow::Result<Rc<TextureAsset>> {
+        let texture = self.loader().load_texture(path, base)?;
+        Ok(self.upload_texture(&texture))
     }
 
-    fn load_texture_inner_3d(&self, texture: &xnb::Texture3D) -> anyhow::Result<TextureAsset> {
-        let texture_format = texture.format.to_wgpu();
+    pub fn upload_texture(&mut self, texture: &CpuTexture) -> Rc<TextureAsset> {
+        if let Some(asset) = self.textures.get(&texture.path) {
+            return asset;
+        }
+        let start = Instant::now();
+
+        let (dimension, view_dimension, layout, label) = match texture.dimension {
+            TextureDimension::D2 => (
+                wgpu::TextureDimension::D2,
+                wgpu::TextureViewDimension::D2,
+                &self.render_context.texture_2d_bind_group_layout,
+                "Texture 2D",
+            ),
+            TextureDimension::D3 => (
+                wgpu::TextureDimension::D3,
+                wgpu::TextureViewDimension::D3,
+                &self.render_context.texture_3d_bind_group_layout,
+                "Texture 3D",
+            ),
+            TextureDimension::Cube => (
+                wgpu::TextureDimension::D2,
+                wgpu::TextureViewDimension::Cube,
+                &self.render_context.texture_cube_bind_group_layout,
+                "Texture Cube",
+            ),
+        };
+
+        // the color space depends on what the texture ends up bound as, so it can be viewed as
+        // either. see `ColorSpace`
+        let srgb_format = texture.format.to_wgpu(ColorSpace::Srgb);
+        let linear_format = texture.format.to_wgpu(ColorSpace::Linear);
 
         let texture_size = wgpu::Extent3d {
             width: texture.width,
@@ -189,25 +184,17 @@ impl AssetManager {
             .render_context
             .device
             .create_texture(&wgpu::TextureDescriptor {
-                label: Some("Texture 3D"),
+                label: Some(label),
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
                 size: texture_size,
-                format: texture_format,
-                dimension: wgpu::TextureDimension::D3,
+                format: srgb_format,
+                dimension,
                 mip_level_count: texture.mips.len() as u32,
                 sample_count: 1,
-                view_formats: &[],
+                view_formats: &[linear_format],
             });
 
         for (i, mip) in texture.mips.iter().enumerate() {
-            // TODO: is this the correct thing to do here?
-            // wgpu validation doesnt like copying 2x2 pixel mips with 4x4 block size
-            let mip_size = wgpu::Extent3d {
-                width: (texture.width / 2u32.pow(i as u32)).max(texture.format.block_dim()),
-                height: (texture.height / 2u32.pow(i as u32)).max(texture.format.block_dim()),
-                depth_or_array_layers: (texture.depth / 2u32.pow(i as u32)).max(1),
-            };
-
             self.render_context.queue.write_texture(
                 wgpu::TexelCopyTextureInfo {
                     texture: &wgpu_texture,
@@ -215,24 +202,36 @@ impl AssetManager {
                     origin: wgpu::Origin3d::ZERO,
                     aspect: wgpu::TextureAspect::All,
                 },
-                mip,
+                &mip.data,
                 wgpu::TexelCopyBufferLayout {
                     offset: 0,
-                    bytes_per_row: Some(texture.bytes_per_row(i)?),
-                    rows_per_image: Some(texture.rows_per_image(i)?),
+                    bytes_per_row: Some(mip.bytes_per_row),
+                    rows_per_image: Some(mip.rows_per_image),
+                },
+                wgpu::Extent3d {
+                    width: mip.width,
+                    height: mip.height,
+                    depth_or_array_layers: mip.depth,
                 },
-                mip_size,
             );
         }
 
-        let view = wgpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let view = wgpu_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        let linear_view = wgpu_texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(linear_format),
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
 
         let bind_group = self
             .render_context
             .device
             .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Texture2D Bind Group"),
-                layout: &self.render_context.texture_3d_bind_group_layout,
+                label: Some("Texture Bind Group"),
+                layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
@@ -247,221 +246,286 @@ impl AssetManager {
                 ],
             });
 
-        Ok(TextureAsset {
+        log::debug!("uploaded texture {}", texture.path.display());
+
+        let asset = Rc::new(TextureAsset {
+            dimension: texture.dimension,
             texture: wgpu_texture,
             view,
+            linear_view,
             bind_group,
-        })
+        });
+        self.textures
+            .insert(texture.path.clone(), asset.clone(), texture.size_bytes());
+
+        let record = AssetRecord::texture(texture);
+        let record = AssetRecord {
+            load_time: record.load_time + start.elapsed(),
+            ..record
+        };
+        self.track(&asset, true, vec![record]);
+
+        asset
     }
 
+    /// loads a standalone model and uploads it in one step
     pub fn load_model(
         &mut self,
         path: &Path,
         base: Option<&Path>,
     ) -> anyhow::Result<Rc<ModelAsset>> {
-        todo!("load model");
-        // let path = self.resolve_path(path, base, Some("xnb"))?;
-        // if let Some(model) = self.models.get(&path) {
-        //     return Ok(model.clone());
-        // }
-
-        // let model_content = self.load_xnb_content(&path)?;
-        // let XnbAsset::Model(model) = &model_content.primary_asset else {
-        //     anyhow::bail!("expected Model at path {}", path.display());
-        // };
-        // let XnbAsset::RenderDeferredEffect(effect) = &model_content.shared_assets[0] else {
-        //     anyhow::bail!(
-        //         "expected RenderDeferredEffect at shared assets 0 at path {}",
-        //         path.display()
-        //     );
-        // };
-
-        // let texture = self.load_texture(
-        //     &fix_xnb_path(&effect.material_0.diffuse_texture),
-        //     Some(&path),
-        // )?;
-
-        // let model = renderer.load_model(model, texture)?;
-        // let model = Rc::new(model);
-
-        // log::debug!("loaded Model from file {}", path.display());
-
-        // self.models.insert(path, model.clone());
-
-        // Ok(model)
+        let path = self.loader().resolve_path(path, base, Some("xnb"))?;
+        if let Some(model) = self.models.get(&path) {
+            return Ok(model);
+        }
+
+        let model = self.loader().load_model(&path, None)?;
+        let size = model
+            .meshes
+            .iter()
+            .map(|mesh| (mesh.vertex_buffer.data.len() + mesh.index_buffer.data.len()) as u64)
+            .sum();
+        let asset = self.upload_model_inner(&path.display().to_string(), &model, true)?;
+        self.models.insert(path, asset.clone(), size);
+
+        Ok(asset)
     }
 
-    fn load_model_inner(
-        &self,
-        model: &xnb::Model,
-        texture: Rc<TextureAsset>,
-    ) -> anyhow::Result<ModelAsset> {
-        todo!()
-
-        // let mesh0 = &model.meshes[0];
-        // let part0 = &mesh0.parts[0];
-        // let vertex_decl = &model.vertex_decls[part0.vertex_decl_index as usize];
-        // let index_format = mesh0.index_buffer.wgpu_format();
-        // let index_count = part0.primitive_count * 3;
-        // let start_index = part0.start_index;
-        // let base_vertex = part0.base_vertex;
-
-        // let vertex_layout_uniform = VertexLayoutUniform::from_xnb_decl(vertex_decl)?;
-        // let vertex_layout_uniform_buffer =
-        //     self.device
-        //         .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        //             label: Some("Vertex Layout Uniform Buffer"),
-        //             contents: bytemuck::cast_slice(&[vertex_layout_uniform]),
-        //             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        //         });
-        // let vertex_layout_uniform_bind_group =
-        //     self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-        //         label: Some("Vertex Layout Uniform Bind Group"),
-        //         layout: &self
-        //             .render_deferred_effect_pipeline
-        //             .vertex_layout_uniform_bind_group_layout,
-        //         entries: &[wgpu::BindGroupEntry {
-        //             binding: 0,
-        //             resource: wgpu::BindingResource::Buffer(
-        //                 vertex_layout_uniform_buffer.as_entire_buffer_binding(),
-        //             ),
-        //         }],
-        //     });
-
-        // let vertex_buffer = self
-        //     .device
-        //     .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        //         label: Some("Vertex Buffer"),
-        //         contents: &mesh0.vertex_buffer.data,
-        //         usage: wgpu::BufferUsages::STORAGE,
-        //     });
-
-        // let index_buffer = self
-        //     .device
-        //     .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        //         label: Some("Index Buffer"),
-        //         contents: &mesh0.index_buffer.data,
-        //         usage: wgpu::BufferUsages::INDEX,
-        //     });
-
-        // let vertex_buffer_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-        //     label: Some("Vertex Buffer Bind Group"),
-        //     layout: &self
-        //         .render_deferred_effect_pipeline
-        //         .vertex_buffer_bind_group_layout,
-        //     entries: &[wgpu::BindGroupEntry {
-        //         binding: 0,
-        //         resource: wgpu::BindingResource::Buffer(vertex_buffer.as_entire_buffer_binding()),
-        //     }],
-        // });
-
-        // Ok(ModelAsset {
-        //     pipeline: self.render_deferred_effect_pipeline.pipeline.clone(),
-        //     vertex_buffer,
-        //     vertex_buffer_bind_group,
-        //     vertex_layout_uniform_buffer,
-        //     vertex_layout_uniform_bind_group,
-        //     index_buffer,
-        //     index_format,
-        //     index_count,
-        //     start_index,
-        //     base_vertex,
-        //     texture,
-        // })
+    /// `name` is what the model's assets are reported as in `stats`
+    pub fn upload_model(&mut self, name: &str, model: &CpuModel) -> anyhow::Result<Rc<ModelAsset>> {
+        self.upload_model_inner(name, model, false)
     }
 
-    pub fn load_level_model(
+    fn upload_model_inner(
         &mut self,
-        path: &Path,
-        base: Option<&Path>,
-    ) -> anyhow::Result<SceneNode> {
-        let path = self.resolve_path(path, base, Some("xnb"))?;
-
-        let model_content = self.load_xnb_content(&path)?;
-        let XnbAsset::LevelModel(level_model) = &model_content.primary_asset else {
-            anyhow::bail!("expected LevelModel at path {}", path.display());
-        };
-
-        let mut scene_node = SceneNode {
-            name: "Level Model".into(),
-            visible: true,
-            transform: Mat4::IDENTITY,
-            children: Vec::new(),
-            kind: SceneNodeKind::Empty,
-        };
-
-        for tree in &level_model.model.trees {
-            debug_assert_eq!(tree.vertex_stride as usize, tree.vertex_decl.stride());
-
-            let XnbAsset::RenderDeferredEffect(effect) = &tree.effect else {
-                if let XnbAsset::AdditiveEffect(_) = &tree.effect {
-                    log::warn!("skipping unimplemented BiTree with AdditiveEffect");
-                    continue;
-                }
-
-                anyhow::bail!(
-                    "expected RenderDeferredEffect inside LevelModel BiTree, got {}",
-                    tree.effect.as_ref()
-                );
-            };
-
-            let asset = self.load_bitree(tree, effect, &path)?;
-            load_level_model_bitree_node_recursive(&mut scene_node, &tree.node, Rc::new(asset))?;
-        }
+        name: &str,
+        model: &CpuModel,
+        cached: bool,
+    ) -> anyhow::Result<Rc<ModelAsset>> {
+        let mut records = Vec::new();
+        let materials = model
+            .materials
+            .iter()
+            .map(|material| self.upload_material(material))
+            .collect();
+
+        let mut meshes = Vec::with_capacity(model.meshes.len());
+        for mesh in &model.meshes {
+            let mesh_name = format!("{name}/{}", mesh.name);
+            let start = Instant::now();
+            let (vertex_buffer, vertex_buffer_bind_group) =
+                self.create_vertex_buffer(&mesh.vertex_buffer.data);
+            let index_buffer = self.create_index_buffer(&mesh.index_buffer.data);
+            records.extend(self.mesh_records(
+                mesh_name.clone(),
+                &mesh.vertex_buffer,
+                &mesh.index_buffer,
+                &vertex_buffer,
+                &index_buffer,
+                start,
+            ));
+
+            let mut parts = Vec::with_capacity(mesh.parts.len());
+            for (i, part) in mesh.parts.iter().enumerate() {
+                let effect = &model.materials[part.material].effect;
+                let effect_uniform = RenderDeferredEffectUniform::new(effect, &part.vertex_decl)?;
+                let (vertex_layout_uniform_buffer, vertex_layout_uniform_bind_group) =
+                    self.create_effect_uniform(effect_uniform);
+                records.push(AssetRecord {
+                    gpu_bytes: vertex_layout_uniform_buffer.size(),
+                    ..AssetRecord::effect(format!("{mesh_name} part {i}"), effect)
+                });
 
-        for effect_storage in &level_model.effect_storages {
-            dbg!(&effect_storage.effect);
-            let effect = self.load_visual_effect(&effect_storage.effect)?;
-
-            let effect_node = SceneNode {
-                name: effect_storage.name.clone(),
-                visible: true,
-                transform: Mat4::from_rotation_translation(
-                    Quat::look_to_rh(effect_storage.forward, Vec3::Y),
-                    effect_storage.position,
-                ),
-                children: Vec::new(),
-                kind: SceneNodeKind::VisualEffect(VisualEffectNode::new(effect)),
-            };
+                parts.push(ModelPartAsset {
+                    vertex_layout_uniform_buffer,
+                    vertex_layout_uniform_bind_group,
+                    material: part.material,
+                    start_index: part.start_index,
+                    index_count: part.index_count,
+                    base_vertex: part.base_vertex,
+                });
+            }
 
-            scene_node.children.push(effect_node);
+            meshes.push(ModelMeshAsset {
+                name: mesh.name.clone(),
+                bone: mesh.bone,
+                bounds: mesh.bounds.clone(),
+                vertex_buffer,
+                vertex_buffer_bind_group,
+                index_buffer,
+                index_format: mesh.index_buffer.wgpu_format(),
+                parts,
+            });
         }
 
-        log::debug!("loaded LevelModel from file {}", path.display());
-
-        Ok(scene_node)
+        let asset = Rc::new(ModelAsset {
+            bones: model.bones.clone(),
+            meshes,
+            materials,
+        });
+        self.track(&asset, cached, records);
+        Ok(asset)
     }
 
-    fn load_bitree(
+    /// `name` is what the tree's assets are reported as in `stats`
+    pub fn upload_bitree(
         &mut self,
-        tree: &xnb::BiTree,
-        effect: &xnb::RenderDeferredEffect,
-        path: &Path,
-    ) -> anyhow::Result<BiTreeAsset> {
-        let diffuse_texture_0 = if !effect.material_0.diffuse_texture.is_empty() {
-            Some(self.load_texture(
-                &fix_xnb_path(&effect.material_0.diffuse_texture),
-                Some(path),
-            )?)
-        } else {
-            None
-        };
+        name: &str,
+        tree: &CpuBiTree,
+    ) -> anyhow::Result<Rc<BiTreeAsset>> {
+        let material = self.upload_material(&tree.material);
+
+        let effect_uniform =
+            RenderDeferredEffectUniform::new(&tree.material.effect, &tree.mesh.vertex_decl)?;
+        let (vertex_layout_uniform_buffer, vertex_layout_uniform_bind_group) =
+            self.create_effect_uniform(effect_uniform);
+
+        let start = Instant::now();
+        let (vertex_buffer, vertex_buffer_bind_group) =
+            self.create_vertex_buffer(&tree.mesh.vertex_buffer.data);
+        let index_buffer = self.create_index_buffer(&tree.mesh.index_buffer.data);
+        let mut records = self.mesh_records(
+            name.to_owned(),
+            &tree.mesh.vertex_buffer,
+            &tree.mesh.index_buffer,
+            &vertex_buffer,
+            &index_buffer,
+            start,
+        );
+        records.push(AssetRecord {
+            gpu_bytes: vertex_layout_uniform_buffer.size(),
+            ..AssetRecord::effect(name.to_owned(), &tree.material.effect)
+        });
+
+        let asset = Rc::new(BiTreeAsset {
+            visible: tree.visible,
+            vertex_buffer,
+            vertex_buffer_bind_group,
+            vertex_layout_uniform_buffer,
+            vertex_layout_uniform_bind_group,
+            index_buffer,
+            index_format: tree.mesh.index_buffer.wgpu_format(),
+            material,
+        });
+        self.track(&asset, false, records);
+        Ok(asset)
+    }
+
+    /// `upload_start` is when creating the buffers started. meshes are uploaded as they're stored
+    /// in the xnb, so that's all the time they take
+    fn mesh_records(
+        &self,
+        name: String,
+        vertex_data: &VertexBuffer,
+        index_data: &IndexBuffer,
+        vertex_buffer: &wgpu::Buffer,
+        index_buffer: &wgpu::Buffer,
+        upload_start: Instant,
+    ) -> Vec<AssetRecord> {
+        vec![
+            AssetRecord {
+                gpu_bytes: vertex_buffer.size(),
+                load_time: upload_start.elapsed(),
+                ..AssetRecord::vertex_buffer(name.clone(), vertex_data)
+            },
+            AssetRecord {
+                gpu_bytes: index_buffer.size(),
+                ..AssetRecord::index_buffer(name, index_data)
+            },
+        ]
+    }
 
-        let diffuse_texture_1 = if let Some(material_1) = &effect.material_1 {
-            if !material_1.diffuse_texture.is_empty() {
-                Some(self.load_texture(&fix_xnb_path(&material_1.diffuse_texture), Some(path))?)
-            } else {
-                None
+    fn upload_material(&mut self, material: &MaterialParams) -> MaterialAsset {
+        let mut textures = Vec::new();
+        // a texture of another dimension can't be bound to the slot, the fallback is used instead
+        let mut upload = |texture: &Option<Arc<CpuTexture>>, slot: &str, dimension| {
+            let texture = texture.as_ref()?;
+            if texture.dimension != dimension {
+                log::warn!(
+                    "ignoring {slot} {} that isn't a {} texture",
+                    texture.path.display(),
+                    match dimension {
+                        TextureDimension::D2 => "2d",
+                        TextureDimension::D3 => "3d",
+                        TextureDimension::Cube => "cube",
+                    }
+                );
+                return None;
             }
-        } else {
-            None
+            let asset = self.upload_texture(texture);
+            textures.push(asset.clone());
+            Some(asset)
         };
 
-        let effect_uniform = RenderDeferredEffectUniform::new(effect, &tree.vertex_decl)?;
+        let maps = [&material.material_0, &material.material_1].map(|textures| {
+            [
+                upload(&textures.diffuse, "diffuse map", TextureDimension::D2),
+                upload(&textures.material, "material map", TextureDimension::D2),
+                upload(&textures.normal, "normal map", TextureDimension::D2),
+            ]
+        });
+        let reflection_map = upload(
+            &material.reflection_map,
+            "reflection map",
+            TextureDimension::Cube,
+        );
+
+        let fallbacks = &self.render_context.fallback_textures;
+        let [diffuse_0, material_0, normal_0] = &maps[0];
+        let [diffuse_1, material_1, normal_1] = &maps[1];
+        let views = [
+            diffuse_0.as_ref().map_or(&fallbacks.white, |t| &t.view),
+            material_0
+                .as_ref()
+                .map_or(&fallbacks.white, |t| &t.linear_view),
+            normal_0
+                .as_ref()
+                .map_or(&fallbacks.flat_normal, |t| &t.linear_view),
+            diffuse_1.as_ref().map_or(&fallbacks.white, |t| &t.view),
+            material_1
+                .as_ref()
+                .map_or(&fallbacks.white, |t| &t.linear_view),
+            normal_1
+                .as_ref()
+                .map_or(&fallbacks.flat_normal, |t| &t.linear_view),
+            reflection_map
+                .as_ref()
+                .map_or(&fallbacks.black_cube, |t| &t.view),
+        ];
+
+        let mut entries: Vec<_> = views
+            .into_iter()
+            .enumerate()
+            .map(|(binding, view)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect();
+        entries.push(wgpu::BindGroupEntry {
+            binding: entries.len() as u32,
+            resource: wgpu::BindingResource::Sampler(&self.render_context.linear_sampler),
+        });
 
-        let index_format = tree.index_buffer.wgpu_format();
+        let texture_bind_group =
+            self.render_context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Material Bind Group"),
+                    layout: &self.render_context.material_bind_group_layout,
+                    entries: &entries,
+                });
 
-        let vertex_layout_uniform_buffer =
+        MaterialAsset {
+            texture_bind_group,
+            textures,
+        }
+    }
+
+    fn create_effect_uniform(
+        &self,
+        effect_uniform: RenderDeferredEffectUniform,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer =
             self.render_context
                 .device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -469,220 +533,97 @@ impl AssetManager {
                     contents: bytemuck::cast_slice(&[effect_uniform]),
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
-        let vertex_layout_uniform_bind_group =
-            self.render_context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Effect Uniform Bind Group"),
-                    layout: &self.render_context.uniform_buffer_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(
-                            vertex_layout_uniform_buffer.as_entire_buffer_binding(),
-                        ),
-                    }],
-                });
+        let bind_group = self
+            .render_context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Effect Uniform Bind Group"),
+                layout: &self.render_context.uniform_buffer_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+                }],
+            });
+        (buffer, bind_group)
+    }
 
-        let vertex_buffer =
+    /// vertices are read from a storage buffer by the shader, see `RenderDeferredEffectUniform`
+    fn create_vertex_buffer(&self, data: &[u8]) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer =
             self.render_context
                 .device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Vertex Buffer"),
-                    contents: &tree.vertex_buffer.data,
+                    contents: data,
                     usage: wgpu::BufferUsages::STORAGE,
                 });
-
-        let index_buffer =
-            self.render_context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Index Buffer"),
-                    contents: &tree.index_buffer.data,
-                    usage: wgpu::BufferUsages::INDEX,
-                });
-
-        let vertex_buffer_bind_group =
-            self.render_context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Vertex Buffer Bind Group"),
-                    layout: &self.render_context.vertex_storage_buffer_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(
-                            vertex_buffer.as_entire_buffer_binding(),
-                        ),
-                    }],
-                });
-
-        let texture_bind_group =
-            self.render_context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Texture Bind Group"),
-                    layout: &self.render_context.texture_2d_2x_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::TextureView(
-                                if let Some(diffuse_0) = &diffuse_texture_0 {
-                                    &diffuse_0.view
-                                } else {
-                                    &self.render_context.placeholder_texture_view
-                                },
-                            ),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::TextureView(
-                                if let Some(diffuse_1) = &diffuse_texture_1 {
-                                    &diffuse_1.view
-                                } else {
-                                    &self.render_context.placeholder_texture_view
-                                },
-                            ),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 2,
-                            resource: wgpu::BindingResource::Sampler(
-                                &self.render_context.linear_sampler,
-                            ),
-                        },
-                    ],
-                });
-
-        Ok(BiTreeAsset {
-            visible: tree.visible,
-            vertex_buffer,
-            vertex_buffer_bind_group,
-            vertex_layout_uniform_buffer,
-            vertex_layout_uniform_bind_group,
-            index_buffer,
-            index_format,
-            texture_bind_group,
-            diffuse_texture_0,
-            diffuse_texture_1,
-        })
-    }
-
-    pub fn load_visual_effect(&self, name: &str) -> anyhow::Result<Rc<VisualEffectAsset>> {
-        if let Some(effect) = self.visual_effects.get(name) {
-            Ok(effect.clone())
-        } else {
-            anyhow::bail!("visual effect '{name}' not found");
-        }
-    }
-
-    fn load_xnb_content(&self, path: &Path) -> anyhow::Result<XnbContent> {
-        let file = std::fs::File::open(path)
-            .with_context(|| format!("failed to open file {}", path.display()))?;
-        let mut reader = BufReader::new(file);
-        let xnb = Xnb::read(&mut reader)?;
-        let content = xnb
-            .parse_content()
-            .with_context(|| format!("failed to parse content from file {}", path.display()))?;
-        Ok(content)
+        let bind_group = self
+            .render_context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Vertex Buffer Bind Group"),
+                layout: &self.render_context.vertex_storage_buffer_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+                }],
+            });
+        (buffer, bind_group)
     }
 
-    /// - `path` is a file path relative the magicka installation root.
-    ///   the casing needn't match the filesystem, and an `xnb` extension will be added if not present.
-    /// - `base` is the directory `path` is relative to. this path must exist on case sensitive filesystems.
-    ///   - if `base` is `None`, the root Magicka installation directory is assumed.
-    ///   - if `base` is a relative path, it is appended to the root Magicka installation directory.
-    ///   - if `base` is a file path, the parent directory will be used.
-    fn resolve_path(
-        &self,
-        path: &Path,
-        base: Option<&Path>,
-        ensure_extension: Option<&str>,
-    ) -> anyhow::Result<PathBuf> {
-        // default to magicka install dir
-        let mut base = base
-            .map(|b| b.to_owned())
-            .unwrap_or(self.magicka_path.clone());
-
-        // make base path absolute
-        if !base.has_root() {
-            base = self.magicka_path.join(base);
-        }
-
-        // make base path a directory
-        if !base.is_dir() {
-            base.pop();
-        }
-
-        // ensure path has an extension (relative paths stored inside XNBs dont have .xnb extensions)
-        let path = if let Some(extension) = ensure_extension
-            && path.extension().is_none()
-        {
-            path.with_extension(extension)
-        } else {
-            path.to_owned()
-        };
-
-        // short circuit if the casing is already correct
-        let full_path = base.join(&path);
-        if full_path.exists() {
-            // canonicalize might be unnecessary but we're hashing paths
-            return Ok(full_path.canonicalize()?);
-        }
-
-        // recursively match each component of the relative path case-insensitively
-        let mut current_path = base;
-        for component in path.components() {
-            match component {
-                std::path::Component::CurDir => {}
-                std::path::Component::ParentDir => {
-                    current_path.pop();
-                }
-                std::path::Component::Normal(insensitive_component) => {
-                    let lower_component = insensitive_component.to_ascii_lowercase();
-
-                    let mut found: Option<std::ffi::OsString> = None;
-                    for entry in std::fs::read_dir(&current_path)? {
-                        let entry_name = entry?.file_name();
-                        let lower_entry_name = entry_name.to_ascii_lowercase();
-
-                        if lower_entry_name == lower_component {
-                            found = Some(entry_name);
-                            break;
-                        }
-                    }
-
-                    if let Some(found) = found {
-                        current_path.push(found);
-                    } else {
-                        current_path.push(insensitive_component);
-                        anyhow::bail!("unable to find path {}", current_path.display());
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        // canonicalize might be unnecessary but we're hashing paths
-        Ok(current_path.canonicalize()?)
+    fn create_index_buffer(&self, data: &[u8]) -> wgpu::Buffer {
+        self.render_context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: data,
+                usage: wgpu::BufferUsages::INDEX,
+            })
     }
 }
 
 pub struct TextureAsset {
+    pub dimension: TextureDimension,
     pub texture: wgpu::Texture,
+    /// reads the texture as srgb, `bind_group` uses this one
     pub view: wgpu::TextureView,
+    pub linear_view: wgpu::TextureView,
     pub bind_group: wgpu::BindGroup,
 }
 
+/// a model's meshes, drawn by the scene nodes `SceneNode::from_model` builds
 pub struct ModelAsset {
-    pub pipeline: wgpu::RenderPipeline,
+    pub bones: Vec<CpuBone>,
+    pub meshes: Vec<ModelMeshAsset>,
+    /// indexed by `ModelPartAsset::material`
+    pub materials: Vec<MaterialAsset>,
+}
+
+pub struct ModelMeshAsset {
+    pub name: String,
+    /// index into `ModelAsset::bones`
+    pub bone: Option<usize>,
+    pub bounds: BoundingSphere,
     pub vertex_buffer: wgpu::Buffer,
     pub vertex_buffer_bind_group: wgpu::BindGroup,
-    pub vertex_layout_uniform_buffer: wgpu::Buffer,
-    pub vertex_layout_uniform_bind_group: wgpu::BindGroup,
     pub index_buffer: wgpu::Buffer,
     pub index_format: wgpu::IndexFormat,
-    pub index_count: u32,
+    pub parts: Vec<ModelPartAsset>,
+}
+
+pub struct ModelPartAsset {
+    pub vertex_layout_uniform_buffer: wgpu::Buffer,
+    pub vertex_layout_uniform_bind_group: wgpu::BindGroup,
+    pub material: usize,
     pub start_index: u32,
-    pub base_vertex: u32,
-    pub texture: Rc<TextureAsset>,
+    pub index_count: u32,
+    pub base_vertex: i32,
+}
+
+pub struct MaterialAsset {
+    pub texture_bind_group: wgpu::BindGroup,
+    /// the textures bound in `texture_bind_group`, fallbacks aside
+    pub textures: Vec<Rc<TextureAsset>>,
 }
 
 pub struct BiTreeAsset {
@@ -693,87 +634,5 @@ pub struct BiTreeAsset {
     pub vertex_layout_uniform_bind_group: wgpu::BindGroup,
     pub index_buffer: wgpu::Buffer,
     pub index_format: wgpu::IndexFormat,
-    pub texture_bind_group: wgpu::BindGroup,
-    pub diffuse_texture_0: Option<Rc<TextureAsset>>,
-    pub diffuse_texture_1: Option<Rc<TextureAsset>>,
-}
-
-fn load_level_model_bitree_node_recursive(
-    parent: &mut SceneNode,
-    bitree_node: &BiTreeNode,
-    bitree_asset: Rc<BiTreeAsset>,
-) -> anyhow::Result<()> {
-    let mut node = SceneNode {
-        name: "BiTree Node".into(),
-        visible: bitree_asset.visible,
-        transform: Mat4::IDENTITY,
-        children: Vec::new(),
-        kind: SceneNodeKind::BiTree(scene::BiTreeNode {
-            tree: bitree_asset.clone(),
-            start_index: bitree_node.start_index as u32,
-            index_count: bitree_node.primitive_count as u32 * 3,
-            bounding_box: bitree_node.bounding_box.clone(),
-        }),
-    };
-
-    for child in bitree_node.iter_children() {
-        load_level_model_bitree_node_recursive(&mut node, child, bitree_asset.clone())?;
-    }
-
-    parent.children.push(node);
-
-    Ok(())
-}
-
-fn fix_xnb_path(path: &str) -> PathBuf {
-    let path = path.replace('\\', "/");
-    PathBuf::from(path)
-}
-
-fn preload_visual_effects(base: &Path) -> anyhow::Result<HashMap<String, Rc<VisualEffectAsset>>> {
-    let path = base.join("Content/Effects");
-    let mut map = HashMap::new();
-
-    preload_visual_effects_inner(&path, &mut map)?;
-
-    Ok(map)
-}
-
-fn preload_visual_effects_inner(
-    path: &Path,
-    map: &mut HashMap<String, Rc<VisualEffectAsset>>,
-) -> anyhow::Result<()> {
-    for entry in std::fs::read_dir(path)? {
-        // cursed closure to allow catching all errors at once
-        // if one file failes to load, it will be logged and traversal will continue
-        if let Err(e) = (|| -> anyhow::Result<()> {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            let path = entry.path();
-
-            if metadata.is_file() {
-                let xml_string = std::fs::read_to_string(&path)?;
-                let effect = VisualEffectAsset::read_xml(&xml_string).with_context(|| {
-                    format!("failed to read visual effect at path {}", path.display())
-                })?;
-                let name = path
-                    .file_stem()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_ascii_lowercase();
-                map.insert(name, Rc::new(effect));
-            } else if metadata.is_dir() {
-                preload_visual_effects_inner(&path, map)?;
-            } else {
-                unreachable!("vfx entry is not a file or a directory");
-            }
-
-            Ok(())
-        })() {
-            log::error!("{e}");
-        }
-    }
-
-    Ok(())
+    pub material: MaterialAsset,
 }
@@ -0,0 +1,673 @@
+//! the cpu half of asset loading. everything here resolves, parses and caches content into
+//! plain data without touching a graphics device, so it can run headless. uploading it to the
+//! gpu is done by `asset_manager`
+
+use std::{
+    collections::HashSet,
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{
+    assets::{
+        background::ProgressTracker,
+        cache::{AssetCache, EvictionStats},
+        disk_cache::{CacheKind, DiskCache, SourceKey},
+        hot_reload::FileWatcher,
+    },
+    content::ContentIndex,
+    vfs::Vfs,
+    vfx::{VisualEffectAsset, VisualEffectIndex},
+    xnb::{
+        self, BiTreeNode, Xnb, XnbContent,
+        asset::{
+            XnbAsset, index_buffer::IndexBuffer,
+            render_deferred_effect::RenderDeferredEffectMaterial, texture_2d::PixelFormat,
+            texture_cube, vertex_buffer::VertexBuffer, vertex_decl::VertexDeclaration,
+        },
+    },
+};
+
+pub mod background;
+pub mod cache;
+pub mod disk_cache;
+pub mod hot_reload;
+pub mod level;
+pub mod model;
+pub mod stats;
+
+pub struct AssetLoader {
+    vfs: Arc<dyn Vfs>,
+    /// built the first time a path is resolved, `None` if that failed
+    index: OnceLock<Option<ContentIndex>>,
+
+    // kept after upload so a texture shared between levels isn't read again,
+    // see `AssetManager` for when entries are dropped
+    textures: AssetCache<Arc<CpuTexture>>,
+
+    // visual effects are referenced by filename (without extension) in other data such as
+    // levels, rather than by path like other assets, and can be located in arbitrary
+    // subdirectories. the index of their paths is built the first time an effect is needed
+    // rather than in `new`, so it can be done by the background loader, and each effect is only
+    // parsed once something asks for it
+    effect_index: Option<VisualEffectIndex>,
+    /// keyed by resolved path like the textures, the index maps names to paths
+    visual_effects: AssetCache<Arc<VisualEffectAsset>>,
+
+    progress: ProgressTracker,
+    disk_cache: Option<DiskCache>,
+
+    watcher: FileWatcher,
+    /// resolved paths of the effects in `visual_effects`
+    watched_effects: HashSet<PathBuf>,
+    level_xml: Option<PathBuf>,
+    /// the files `level_xml` includes, directly or through other includes
+    level_includes: Vec<PathBuf>,
+}
+
+impl AssetLoader {
+    /// all paths are resolved relative to the root of `vfs`
+    pub fn new(vfs: Arc<dyn Vfs>) -> Self {
+        AssetLoader {
+            vfs,
+            index: OnceLock::new(),
+            textures: AssetCache::default(),
+            effect_index: None,
+            visual_effects: AssetCache::default(),
+            progress: ProgressTracker::default(),
+            disk_cache: None,
+            watcher: FileWatcher::default(),
+            watched_effects: HashSet::new(),
+            level_xml: None,
+            level_includes: Vec::new(),
+        }
+    }
+
+    pub fn progress(&self) -> &ProgressTracker {
+        &self.progress
+    }
+
+    pub fn vfs(&self) -> &dyn Vfs {
+        self.vfs.as_ref()
+    }
+
+    pub fn progress_mut(&mut self) -> &mut ProgressTracker {
+        &mut self.progress
+    }
+
+    /// processed assets are looked up in `disk_cache` before being read from the vfs, and
+    /// stored in it after
+    pub fn set_disk_cache(&mut self, disk_cache: Option<DiskCache>) {
+        self.disk_cache = disk_cache;
+    }
+
+    pub fn read_to_string(&self, path: &Path, base: Option<&Path>) -> anyhow::Result<String> {
+        let path = self.resolve_path(path, base, None)?;
+        let string = self.vfs.read_to_string(&path)?;
+        self.progress.finish(&path, string.len() as u64);
+        log::debug!("loaded string data from file {}", path.display());
+        Ok(string)
+    }
+
+    pub fn load_texture(
+        &mut self,
+        path: &Path,
+        base: Option<&Path>,
+    ) -> anyhow::Result<Arc<CpuTexture>> {
+        let path = self.resolve_path(path, base, Some("xnb"))?;
+        if let Some(texture) = self.textures.get(&path) {
+            return Ok(texture);
+        }
+
+        let texture = Arc::new(self.read_texture(&path)?);
+        self.textures
+            .insert(path, texture.clone(), texture.size_bytes());
+
+        Ok(texture)
+    }
+
+    /// loads every texture in `paths` that isn't cached yet, spread over one thread per core.
+    /// the paths must already be resolved
+    pub fn prefetch_textures(&mut self, paths: &[PathBuf]) -> anyhow::Result<()> {
+        let mut missing: Vec<&PathBuf> = paths
+            .iter()
+            .filter(|path| !self.textures.contains(path))
+            .collect();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for path in &missing {
+            self.queue(path);
+        }
+
+        let num_threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(missing.len());
+        let next = AtomicUsize::new(0);
+
+        let this = &*self;
+        let mut results = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..num_threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(path) = missing.get(i) else {
+                                break;
+                            };
+                            results.push((i, this.read_texture(path)));
+                        }
+                        results
+                    })
+                })
+                .collect();
+
+            let mut results = Vec::with_capacity(missing.len());
+            for worker in workers {
+                let worker_results = worker
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("texture loading thread panicked"))?;
+                results.extend(worker_results);
+            }
+            anyhow::Ok(results)
+        })?;
+
+        results.sort_by_key(|(i, _)| *i);
+        for (i, texture) in results {
+            let texture = texture?;
+            let size = texture.size_bytes();
+            self.textures
+                .insert(missing[i].clone(), Arc::new(texture), size);
+        }
+
+        Ok(())
+    }
+
+    fn read_texture(&self, path: &Path) -> anyhow::Result<CpuTexture> {
+        let start = Instant::now();
+        let mut texture = self.read_texture_data(path)?;
+        texture.load_time = start.elapsed();
+        Ok(texture)
+    }
+
+    fn read_texture_data(&self, path: &Path) -> anyhow::Result<CpuTexture> {
+        let source = self.source_key(path);
+        if let Some(data) = self.read_cached(CacheKind::Texture, source.as_ref()) {
+            match CpuTexture::read_cached(path.to_owned(), &mut Cursor::new(data)) {
+                Ok(texture) => {
+                    self.progress.finish(path, source.map_or(0, |s| s.size));
+                    log::debug!("loaded cached texture for file {}", path.display());
+                    return Ok(texture);
+                }
+                Err(e) => log::warn!("ignoring cached texture for {}: {e:#}", path.display()),
+            }
+        }
+
+        // the texture is cached as a whole, so the xnb it came from doesn't need to be
+        let content = self.read_xnb_content(path, source.as_ref(), false)?;
+        let texture = match content.primary_asset {
+            XnbAsset::Texture2D(texture) => {
+                log::debug!("loaded Texture2D from file {}", path.display());
+                CpuTexture::from_2d(path.to_owned(), texture)?
+            }
+            XnbAsset::Texture3D(texture) => {
+                log::debug!("loaded Texture3D from file {}", path.display());
+                CpuTexture::from_3d(path.to_owned(), texture)?
+            }
+            XnbAsset::TextureCube(texture) => {
+                log::debug!("loaded TextureCube from file {}", path.display());
+                CpuTexture::from_cube(path.to_owned(), texture)?
+            }
+            _ => {
+                anyhow::bail!(
+                    "expected Texture2D, Texture3D or TextureCube at path {}",
+                    path.display()
+                );
+            }
+        };
+
+        if let (Some(disk_cache), Some(source)) = (&self.disk_cache, &source) {
+            let mut data = Vec::with_capacity(texture.size_bytes() as usize);
+            texture.write_cached(&mut data)?;
+            if let Err(e) = disk_cache.put(CacheKind::Texture, source, &data) {
+                log::warn!("{e:#}");
+            }
+        }
+
+        Ok(texture)
+    }
+
+    /// loads the textures a `RenderDeferredEffect` refers to. `path` is the file the effect
+    /// was read from, texture paths are relative to it
+    pub fn load_material(
+        &mut self,
+        effect: xnb::RenderDeferredEffect,
+        path: &Path,
+    ) -> anyhow::Result<MaterialParams> {
+        let material_0 = self.load_material_textures(&effect.material_0, path)?;
+        let material_1 = match &effect.material_1 {
+            Some(material_1) => self.load_material_textures(material_1, path)?,
+            None => MaterialTextures::default(),
+        };
+        let reflection_map = self.load_material_texture(&effect.reflection_map, path)?;
+
+        Ok(MaterialParams {
+            effect,
+            material_0,
+            material_1,
+            reflection_map,
+        })
+    }
+
+    fn load_material_textures(
+        &mut self,
+        material: &RenderDeferredEffectMaterial,
+        path: &Path,
+    ) -> anyhow::Result<MaterialTextures> {
+        Ok(MaterialTextures {
+            diffuse: self.load_material_texture(&material.diffuse_texture, path)?,
+            material: self.load_material_texture(&material.material_texture, path)?,
+            normal: self.load_material_texture(&material.normal_texture, path)?,
+        })
+    }
+
+    /// `None` for an empty path, which materials use for textures they don't have
+    fn load_material_texture(
+        &mut self,
+        texture: &str,
+        path: &Path,
+    ) -> anyhow::Result<Option<Arc<CpuTexture>>> {
+        if texture.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.load_texture(Path::new(texture), Some(path))?))
+    }
+
+    /// the textures `load_material` will load for `effect`, resolved relative to `path`
+    pub fn material_texture_paths(
+        &self,
+        effect: &xnb::RenderDeferredEffect,
+        path: &Path,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        std::iter::once(&effect.material_0)
+            .chain(&effect.material_1)
+            .flat_map(|material| {
+                [
+                    &material.diffuse_texture,
+                    &material.material_texture,
+                    &material.normal_texture,
+                ]
+            })
+            .chain([&effect.reflection_map])
+            .filter(|texture| !texture.is_empty())
+            .map(|texture| self.resolve_path(Path::new(texture), Some(path), Some("xnb")))
+            .collect()
+    }
+
+    /// starts tracking which cached assets the next level uses, see `AssetCache`
+    pub fn begin_generation(&mut self) {
+        self.textures.begin_generation();
+        self.visual_effects.begin_generation();
+    }
+
+    /// drops the cached textures and visual effects nothing used in the current generation.
+    /// `BackgroundLoader` does this before starting the next one
+    pub fn evict_unused(&mut self) -> EvictionStats {
+        let mut stats = self.textures.evict_unused();
+        stats += self.visual_effects.evict_unused();
+        self.unwatch_evicted_effects();
+        stats
+    }
+
+    pub fn load_visual_effect(&mut self, name: &str) -> anyhow::Result<Arc<VisualEffectAsset>> {
+        // references to effects aren't consistently cased
+        let key = name.to_ascii_lowercase();
+        let index = match &mut self.effect_index {
+            Some(index) => index,
+            index => index.insert(VisualEffectIndex::build(self.vfs.as_ref())?),
+        };
+        let Some(path) = index.get(&key).map(Path::to_owned) else {
+            anyhow::bail!("visual effect '{name}' not found");
+        };
+        if let Some(effect) = self.visual_effects.get(&path) {
+            return Ok(effect);
+        }
+
+        let effect = Arc::new(self.read_visual_effect(&path)?);
+        let size = self.vfs.size(&path).unwrap_or(0);
+        self.progress.finish(&path, size);
+        log::debug!("loaded visual effect from file {}", path.display());
+
+        self.watch_visual_effect(&path);
+        self.visual_effects.insert(path, effect.clone(), size);
+        Ok(effect)
+    }
+
+    fn read_visual_effect(&self, path: &Path) -> anyhow::Result<VisualEffectAsset> {
+        let xml = self.vfs.read_to_string(path)?;
+        VisualEffectAsset::read_xml(&xml)
+            .with_context(|| format!("failed to read visual effect at path {}", path.display()))
+    }
+
+    pub fn load_xnb_content(&self, path: &Path) -> anyhow::Result<XnbContent> {
+        let source = self.source_key(path);
+        self.read_xnb_content(path, source.as_ref(), true)
+    }
+
+    /// `cache_payload` stores the decompressed content stream in the disk cache on a miss
+    fn read_xnb_content(
+        &self,
+        path: &Path,
+        source: Option<&SourceKey>,
+        cache_payload: bool,
+    ) -> anyhow::Result<XnbContent> {
+        let (decompressed, size) = match self.read_cached(CacheKind::Xnb, source) {
+            Some(decompressed) => (decompressed, source.map_or(0, |s| s.size)),
+            None => {
+                let data = self.vfs.read(path)?;
+                let xnb = Xnb::read(&mut Cursor::new(&data))?;
+                let decompressed = xnb.decompress()?.into_owned();
+                if let (true, Some(disk_cache), Some(source)) =
+                    (cache_payload, &self.disk_cache, source)
+                    && let Err(e) = disk_cache.put(CacheKind::Xnb, source, &decompressed)
+                {
+                    log::warn!("{e:#}");
+                }
+                (decompressed, data.len() as u64)
+            }
+        };
+
+        let content = Xnb::parse_content_from(&decompressed)
+            .with_context(|| format!("failed to parse content from file {}", path.display()))?;
+        self.progress.finish(path, size);
+        Ok(content)
+    }
+
+    /// `None` if there's no disk cache, or the file can't be cached
+    fn source_key(&self, path: &Path) -> Option<SourceKey> {
+        self.disk_cache.as_ref()?;
+        SourceKey::new(self.vfs.as_ref(), path).ok().flatten()
+    }
+
+    fn read_cached(&self, kind: CacheKind, source: Option<&SourceKey>) -> Option<Vec<u8>> {
+        self.disk_cache.as_ref()?.get(kind, source?)
+    }
+
+    pub fn resolve_path(
+        &self,
+        path: &Path,
+        base: Option<&Path>,
+        ensure_extension: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        let index = self
+            .content_index()
+            .ok_or_else(|| anyhow::anyhow!("content could not be indexed"))?;
+        index
+            .resolve(path, base, ensure_extension)
+            .ok_or_else(|| anyhow::anyhow!("unable to find path {}", path.display()))
+    }
+
+    fn content_index(&self) -> Option<&ContentIndex> {
+        self.index
+            .get_or_init(|| match ContentIndex::build(self.vfs.as_ref()) {
+                Ok(index) => Some(index),
+                Err(e) => {
+                    log::error!("failed to index content: {e:#}");
+                    None
+                }
+            })
+            .as_ref()
+    }
+
+    /// counts a resolved path towards the progress totals before it is read
+    fn queue(&self, path: &Path) {
+        let size = self.vfs.size(path).unwrap_or(0);
+        self.progress.queue(path, size);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureDimension {
+    D2,
+    D3,
+    Cube,
+}
+
+/// texture data ready to be copied to the gpu as is, mips are still block compressed
+pub struct CpuTexture {
+    /// the resolved path the texture was loaded from, also used to key gpu uploads
+    pub path: PathBuf,
+    pub dimension: TextureDimension,
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    /// 1 for 2d textures, the number of faces for cube textures
+    pub depth: u32,
+    pub mips: Vec<CpuMip>,
+    /// spent reading and decoding the texture, or reading it from the disk cache
+    pub load_time: Duration,
+}
+
+pub struct CpuMip {
+    pub data: Vec<u8>,
+    /// size of the copy in pixels, never smaller than a single block
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub bytes_per_row: u32,
+    pub rows_per_image: u32,
+}
+
+impl CpuTexture {
+    pub fn size_bytes(&self) -> u64 {
+        self.mips.iter().map(|mip| mip.data.len() as u64).sum()
+    }
+
+    fn from_2d(path: PathBuf, texture: xnb::Texture2D) -> anyhow::Result<Self> {
+        let mips = Self::mips(
+            texture.mips,
+            TextureDimension::D2,
+            texture.width,
+            texture.height,
+            1,
+            texture.format,
+        )?;
+        Ok(CpuTexture {
+            path,
+            dimension: TextureDimension::D2,
+            format: texture.format,
+            width: texture.width,
+            height: texture.height,
+            depth: 1,
+            mips,
+            load_time: Duration::ZERO,
+        })
+    }
+
+    fn from_3d(path: PathBuf, texture: xnb::Texture3D) -> anyhow::Result<Self> {
+        let mips = Self::mips(
+            texture.mips,
+            TextureDimension::D3,
+            texture.width,
+            texture.height,
+            texture.depth,
+            texture.format,
+        )?;
+        Ok(CpuTexture {
+            path,
+            dimension: TextureDimension::D3,
+            format: texture.format,
+            width: texture.width,
+            height: texture.height,
+            depth: texture.depth,
+            mips,
+            load_time: Duration::ZERO,
+        })
+    }
+
+    fn from_cube(path: PathBuf, texture: xnb::TextureCube) -> anyhow::Result<Self> {
+        let depth = texture_cube::FACE_COUNT as u32;
+        let mips = Self::mips(
+            texture.mips,
+            TextureDimension::Cube,
+            texture.size,
+            texture.size,
+            depth,
+            texture.format,
+        )?;
+        Ok(CpuTexture {
+            path,
+            dimension: TextureDimension::Cube,
+            format: texture.format,
+            width: texture.size,
+            height: texture.size,
+            depth,
+            mips,
+            load_time: Duration::ZERO,
+        })
+    }
+
+    /// the inverse of `read_cached`
+    fn write_cached(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u8(match self.dimension {
+            TextureDimension::D2 => 2,
+            TextureDimension::D3 => 3,
+            TextureDimension::Cube => 6,
+        })?;
+        writer.write_u32::<LittleEndian>(self.format as u32)?;
+        writer.write_u32::<LittleEndian>(self.width)?;
+        writer.write_u32::<LittleEndian>(self.height)?;
+        writer.write_u32::<LittleEndian>(self.depth)?;
+        writer.write_u32::<LittleEndian>(self.mips.len() as u32)?;
+        for mip in &self.mips {
+            writer.write_u32::<LittleEndian>(mip.width)?;
+            writer.write_u32::<LittleEndian>(mip.height)?;
+            writer.write_u32::<LittleEndian>(mip.depth)?;
+            writer.write_u32::<LittleEndian>(mip.bytes_per_row)?;
+            writer.write_u32::<LittleEndian>(mip.rows_per_image)?;
+            writer.write_u32::<LittleEndian>(mip.data.len() as u32)?;
+            writer.write_all(&mip.data)?;
+        }
+        Ok(())
+    }
+
+    fn read_cached(path: PathBuf, reader: &mut impl Read) -> anyhow::Result<Self> {
+        let dimension = match reader.read_u8()? {
+            2 => TextureDimension::D2,
+            3 => TextureDimension::D3,
+            6 => TextureDimension::Cube,
+            v => anyhow::bail!("unknown texture dimension: {v}"),
+        };
+        let format = reader.read_u32::<LittleEndian>()?;
+        let format = PixelFormat::from_repr(format)
+            .ok_or_else(|| anyhow::anyhow!("unknown pixel format: {format}"))?;
+        let width = reader.read_u32::<LittleEndian>()?;
+        let height = reader.read_u32::<LittleEndian>()?;
+        let depth = reader.read_u32::<LittleEndian>()?;
+
+        let mip_count = reader.read_u32::<LittleEndian>()?;
+        let mut mips = Vec::with_capacity(mip_count as usize);
+        for _ in 0..mip_count {
+            let width = reader.read_u32::<LittleEndian>()?;
+            let height = reader.read_u32::<LittleEndian>()?;
+            let depth = reader.read_u32::<LittleEndian>()?;
+            let bytes_per_row = reader.read_u32::<LittleEndian>()?;
+            let rows_per_image = reader.read_u32::<LittleEndian>()?;
+            let len = reader.read_u32::<LittleEndian>()?;
+            let mut data = vec![0u8; len as usize];
+            reader.read_exact(&mut data)?;
+            mips.push(CpuMip {
+                data,
+                width,
+                height,
+                depth,
+                bytes_per_row,
+                rows_per_image,
+            });
+        }
+
+        Ok(CpuTexture {
+            path,
+            dimension,
+            format,
+            width,
+            height,
+            depth,
+            mips,
+            load_time: Duration::ZERO,
+        })
+    }
+
+    fn mips(
+        mips: Vec<Vec<u8>>,
+        dimension: TextureDimension,
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: PixelFormat,
+    ) -> anyhow::Result<Vec<CpuMip>> {
+        mips.into_iter()
+            .enumerate()
+            .map(|(i, data)| {
+                // TODO: is this the correct thing to do here?
+                // wgpu validation doesnt like copying 2x2 pixel mips with 4x4 block size
+                Ok(CpuMip {
+                    data,
+                    width: (width / 2u32.pow(i as u32)).max(format.block_dim()),
+                    height: (height / 2u32.pow(i as u32)).max(format.block_dim()),
+                    // only volumes shrink in depth, cube faces are layers
+                    depth: match dimension {
+                        TextureDimension::D3 => (depth / 2u32.pow(i as u32)).max(1),
+                        TextureDimension::D2 | TextureDimension::Cube => depth,
+                    },
+                    bytes_per_row: xnb::asset::texture_2d::bytes_per_row(width, i, format)?,
+                    rows_per_image: xnb::asset::texture_2d::rows_per_image(height, i, format)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// vertex and index data in the layout they're stored in the xnb
+pub struct CpuMesh {
+    pub vertex_decl: VertexDeclaration,
+    pub vertex_buffer: VertexBuffer,
+    pub index_buffer: IndexBuffer,
+}
+
+/// a `RenderDeferredEffect` with its textures loaded
+pub struct MaterialParams {
+    pub effect: xnb::RenderDeferredEffect,
+    pub material_0: MaterialTextures,
+    /// all `None` if the effect only has one material
+    pub material_1: MaterialTextures,
+    pub reflection_map: Option<Arc<CpuTexture>>,
+}
+
+/// the textures of a `RenderDeferredEffectMaterial`, `None` where its path is empty
+#[derive(Default)]
+pub struct MaterialTextures {
+    pub diffuse: Option<Arc<CpuTexture>>,
+    pub material: Option<Arc<CpuTexture>>,
+    pub normal: Option<Arc<CpuTexture>>,
+}
+
+pub struct CpuBiTree {
+    pub visible: bool,
+    pub mesh: CpuMesh,
+    pub material: MaterialParams,
+    pub node: BiTreeNode,
+}
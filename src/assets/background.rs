@@ -0,0 +1,176 @@
+//! runs `AssetLoader` on a worker thread so the window keeps responding while a level loads
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+use crate::assets::{AssetLoader, cache::EvictionStats, level::CpuLevel};
+
+/// how far along the current batch of loads is. the totals grow as files referencing other
+/// files are parsed, so they're only an estimate until the batch is done
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+pub enum LoadEvent {
+    Progress(LoadProgress),
+    Level {
+        path: PathBuf,
+        /// boxed so progress events stay small
+        result: anyhow::Result<Box<CpuLevel>>,
+        /// the cpu assets dropped before loading, see `AssetLoader::evict_unused`
+        evicted: EvictionStats,
+    },
+}
+
+/// counts the files `AssetLoader` reads and reports every change to an optional channel
+#[derive(Default)]
+pub struct ProgressTracker {
+    state: Mutex<ProgressState>,
+    sender: Option<Sender<LoadEvent>>,
+}
+
+#[derive(Default)]
+struct ProgressState {
+    progress: LoadProgress,
+    /// files counted in the totals that haven't been read yet
+    queued: HashSet<PathBuf>,
+}
+
+impl ProgressTracker {
+    pub fn set_sender(&mut self, sender: Option<Sender<LoadEvent>>) {
+        self.sender = sender;
+    }
+
+    /// starts a new batch
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = ProgressState::default();
+        self.send(LoadProgress::default());
+    }
+
+    /// counts a file of `size` bytes that is going to be read towards the totals
+    pub fn queue(&self, path: &Path, size: u64) {
+        let mut state = self.state.lock().unwrap();
+        if !state.queued.insert(path.to_owned()) {
+            return;
+        }
+
+        state.progress.files_total += 1;
+        state.progress.bytes_total += size;
+        let progress = state.progress;
+        drop(state);
+
+        self.send(progress);
+    }
+
+    /// marks a file as read. files that weren't queued are added to the totals as well
+    pub fn finish(&self, path: &Path, size: u64) {
+        let mut state = self.state.lock().unwrap();
+        if !state.queued.remove(path) {
+            state.progress.files_total += 1;
+            state.progress.bytes_total += size;
+        }
+        state.progress.files_done += 1;
+        state.progress.bytes_done += size;
+        // the file may have changed size since it was queued
+        state.progress.bytes_total = state.progress.bytes_total.max(state.progress.bytes_done);
+        let progress = state.progress;
+        drop(state);
+
+        self.send(progress);
+    }
+
+    fn send(&self, progress: LoadProgress) {
+        if let Some(sender) = &self.sender {
+            // nobody listening anymore is fine
+            let _ = sender.send(LoadEvent::Progress(progress));
+        }
+    }
+}
+
+/// loads levels on a worker thread. results and progress are polled with `poll` and are meant
+/// to be uploaded to the gpu on the main thread
+pub struct BackgroundLoader {
+    requests: Sender<PathBuf>,
+    events: Receiver<LoadEvent>,
+}
+
+impl BackgroundLoader {
+    /// `loader` stays usable from other threads, but it is locked for as long as a level is
+    /// loading
+    pub fn spawn(loader: Arc<Mutex<AssetLoader>>) -> anyhow::Result<Self> {
+        let (request_sender, request_receiver) = mpsc::channel::<PathBuf>();
+        let (event_sender, event_receiver) = mpsc::channel();
+
+        loader
+            .lock()
+            .unwrap()
+            .progress_mut()
+            .set_sender(Some(event_sender.clone()));
+
+        std::thread::Builder::new()
+            .name("asset loader".into())
+            .spawn(move || {
+                // ends once the `BackgroundLoader` is dropped
+                while let Ok(path) = request_receiver.recv() {
+                    // only the newest request matters, so flipping through levels doesn't load
+                    // every one in between
+                    let path = request_receiver.try_iter().last().unwrap_or(path);
+                    let (result, evicted) = {
+                        let mut loader = loader.lock().unwrap();
+                        loader.progress().reset();
+                        // the level being shown is still the current generation, so what it
+                        // used stays cached for the new level and only older assets go
+                        let evicted = loader.evict_unused();
+                        loader.begin_generation();
+                        // the parsers still assert on some unexpected data, don't let that
+                        // take the loader down with it
+                        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            loader.load_level(&path, None).map(Box::new)
+                        }))
+                        .unwrap_or_else(|_| {
+                            Err(anyhow::anyhow!("panicked while loading {}", path.display()))
+                        });
+                        (result, evicted)
+                    };
+
+                    if event_sender
+                        .send(LoadEvent::Level {
+                            path,
+                            result,
+                            evicted,
+                        })
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(BackgroundLoader {
+            requests: request_sender,
+            events: event_receiver,
+        })
+    }
+
+    /// queues a level, a `LoadEvent::Level` is sent when it is done. levels queued before it that
+    /// haven't started loading yet are skipped
+    pub fn load_level(&self, path: impl Into<PathBuf>) {
+        // the worker only exits once `self.requests` is dropped
+        let _ = self.requests.send(path.into());
+    }
+
+    /// every event received since the last call, without blocking
+    pub fn poll(&self) -> impl Iterator<Item = LoadEvent> + '_ {
+        self.events.try_iter()
+    }
+}
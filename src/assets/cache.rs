@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+/// assets keyed by resolved path, tagged with the generation they were last used in.
+///
+/// a generation is started for every level that gets loaded. everything the level asks for is
+/// marked with the new generation, and anything left over from older generations that nothing
+/// outside the cache holds on to anymore can then be evicted
+pub struct AssetCache<T> {
+    entries: HashMap<PathBuf, CacheEntry<T>>,
+    generation: u32,
+}
+
+struct CacheEntry<T> {
+    value: T,
+    generation: u32,
+    size: u64,
+}
+
+/// how many assets were dropped from a cache, and the size of their data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionStats {
+    pub assets: usize,
+    pub bytes: u64,
+}
+
+impl std::ops::AddAssign for EvictionStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.assets += rhs.assets;
+        self.bytes += rhs.bytes;
+    }
+}
+
+/// shared pointers the cache can check for outside references
+pub trait SharedAsset: Clone {
+    fn is_shared(&self) -> bool;
+}
+
+impl<T> SharedAsset for Rc<T> {
+    fn is_shared(&self) -> bool {
+        Rc::strong_count(self) > 1
+    }
+}
+
+impl<T> SharedAsset for Arc<T> {
+    fn is_shared(&self) -> bool {
+        Arc::strong_count(self) > 1
+    }
+}
+
+impl<T> Default for AssetCache<T> {
+    fn default() -> Self {
+        AssetCache {
+            entries: HashMap::new(),
+            generation: 0,
+        }
+    }
+}
+
+impl<T: SharedAsset> AssetCache<T> {
+    /// returns the cached asset and marks it as used by the current generation
+    pub fn get(&mut self, path: &Path) -> Option<T> {
+        let entry = self.entries.get_mut(path)?;
+        entry.generation = self.generation;
+        Some(entry.value.clone())
+    }
+
+    /// like `get`, without marking the asset as used
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// `size` is what evicting the asset is reported to free, in bytes
+    pub fn insert(&mut self, path: PathBuf, value: T, size: u64) {
+        let entry = CacheEntry {
+            value,
+            generation: self.generation,
+            size,
+        };
+        self.entries.insert(path, entry);
+    }
+
+    /// swaps in a new version of a cached asset, keeping the generation it was last used in.
+    /// returns the old version, `None` if the asset isn't cached
+    pub fn replace(&mut self, path: &Path, value: T, size: u64) -> Option<T> {
+        let entry = self.entries.get_mut(path)?;
+        entry.size = size;
+        Some(std::mem::replace(&mut entry.value, value))
+    }
+
+    pub fn begin_generation(&mut self) {
+        self.generation += 1;
+    }
+
+    /// drops every asset that wasn't used in the current generation and isn't referenced
+    /// anywhere else. assets that are still referenced are kept and checked again next time
+    pub fn evict_unused(&mut self) -> EvictionStats {
+        let mut stats = EvictionStats::default();
+        self.entries.retain(|path, entry| {
+            if entry.generation == self.generation || entry.value.is_shared() {
+                return true;
+            }
+
+            log::debug!("evicting {}", path.display());
+            stats.assets += 1;
+            stats.bytes += entry.size;
+            false
+        });
+        stats
+    }
+}
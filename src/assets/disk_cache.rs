@@ -0,0 +1,321 @@
+//! processed assets kept on disk between runs, so loading the same content again skips
+//! decompressing and parsing it. entries are only used while the file they were made from still
+//! has the same origin, path, size and modification time, and was written by the same
+//! `CACHE_VERSION`
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::vfs::Vfs;
+
+/// bump whenever the layout of an entry or of anything stored in one changes
+pub const CACHE_VERSION: u32 = 3;
+
+const MAGIC: &[u8; 4] = b"ALDC";
+const ENTRY_EXTENSION: &str = "bin";
+
+/// what kind of data an entry holds. each kind is kept in its own subdirectory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// the decompressed content stream of an xnb
+    Xnb,
+    /// a `CpuTexture`, see `CpuTexture::write_cached`
+    Texture,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 2] = [CacheKind::Xnb, CacheKind::Texture];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheKind::Xnb => "xnb",
+            CacheKind::Texture => "texture",
+        }
+    }
+}
+
+/// the source file an entry was made from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceKey {
+    /// see `Vfs::origin`
+    pub origin: String,
+    pub path: PathBuf,
+    /// nanoseconds since the unix epoch
+    pub modified: u64,
+    pub size: u64,
+}
+
+impl SourceKey {
+    /// `None` if `vfs` can't tell when the file was modified, such files can't be cached
+    pub fn new(vfs: &dyn Vfs, path: &Path) -> anyhow::Result<Option<Self>> {
+        let Some(modified) = vfs.modified(path)? else {
+            return Ok(None);
+        };
+        let modified = modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Ok(Some(SourceKey {
+            origin: vfs.origin(path)?,
+            path: path.to_owned(),
+            modified,
+            size: vfs.size(path)?,
+        }))
+    }
+}
+
+/// number of entries and their total size on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl std::ops::AddAssign for EntryStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.entries += rhs.entries;
+        self.bytes += rhs.bytes;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    /// keyed by `CacheKind::name`
+    pub kinds: BTreeMap<&'static str, EntryStats>,
+    /// entries written by another `CACHE_VERSION`, which are never read
+    pub outdated: EntryStats,
+}
+
+impl CacheStats {
+    pub fn total(&self) -> EntryStats {
+        let mut total = EntryStats::default();
+        for stats in self.kinds.values() {
+            total += *stats;
+        }
+        total
+    }
+}
+
+#[derive(Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn open(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create cache directory {}", dir.display()))?;
+        Ok(DiskCache { dir })
+    }
+
+    /// the per user cache directory of the platform, `None` if it can't be determined
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join("Library/Caches"))
+        } else {
+            std::env::var_os("XDG_CACHE_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        };
+        Some(base?.join("aldrheim"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// the cached data for `key`, `None` if there is no usable entry
+    pub fn get(&self, kind: CacheKind, key: &SourceKey) -> Option<Vec<u8>> {
+        let path = self.entry_path(kind, key);
+        let file = File::open(&path).ok()?;
+        match read_entry(&mut BufReader::new(file), key) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("ignoring unreadable cache entry {}: {e:#}", path.display());
+                None
+            }
+        }
+    }
+
+    /// replaces the entry for the same origin and path as `key`, if there is one
+    pub fn put(&self, kind: CacheKind, key: &SourceKey, data: &[u8]) -> anyhow::Result<()> {
+        let path = self.entry_path(kind, key);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+
+        // written next to the entry and renamed over it, so a reader never sees half an entry
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        let result = (|| -> anyhow::Result<()> {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            write_entry(&mut writer, key, data)?;
+            writer.into_inner()?;
+            std::fs::rename(&temp_path, &path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result.with_context(|| format!("failed to write cache entry {}", path.display()))
+    }
+
+    pub fn stats(&self) -> anyhow::Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for kind in CacheKind::ALL {
+            let kind_stats = stats.kinds.entry(kind.name()).or_default();
+            for (path, size) in self.entries(kind)? {
+                let entry = EntryStats {
+                    entries: 1,
+                    bytes: size,
+                };
+                if entry_version(&path).ok() == Some(CACHE_VERSION) {
+                    *kind_stats += entry;
+                } else {
+                    stats.outdated += entry;
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// deletes every entry, returns what was deleted
+    pub fn clear(&self) -> anyhow::Result<EntryStats> {
+        let mut removed = EntryStats::default();
+        for kind in CacheKind::ALL {
+            for (path, size) in self.entries(kind)? {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+                removed += EntryStats {
+                    entries: 1,
+                    bytes: size,
+                };
+            }
+        }
+        Ok(removed)
+    }
+
+    /// entries are named after a hash of the source origin and path, so a changed source
+    /// replaces its old entry instead of adding another one, while the same path in another
+    /// install or overlay gets an entry of its own
+    fn entry_path(&self, kind: CacheKind, key: &SourceKey) -> PathBuf {
+        let source_path = key
+            .path
+            .to_string_lossy()
+            .replace('\\', "/")
+            .to_ascii_lowercase();
+        let name = format!("{}\0{source_path}", key.origin);
+        self.dir
+            .join(kind.name())
+            .join(format!("{:016x}.{ENTRY_EXTENSION}", fnv1a(name.as_bytes())))
+    }
+
+    /// every entry file of `kind` with its size in bytes
+    fn entries(&self, kind: CacheKind) -> anyhow::Result<Vec<(PathBuf, u64)>> {
+        let dir = self.dir.join(kind.name());
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir)
+            .with_context(|| format!("failed to read cache directory {}", dir.display()))?
+        {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION) {
+                entries.push((path, entry.metadata()?.len()));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// stable between builds, unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn write_entry(writer: &mut impl Write, key: &SourceKey, data: &[u8]) -> anyhow::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(CACHE_VERSION)?;
+    write_string(writer, &key.origin)?;
+    write_string(writer, &key.path.to_string_lossy())?;
+    writer.write_u64::<LittleEndian>(key.modified)?;
+    writer.write_u64::<LittleEndian>(key.size)?;
+    writer.write_u64::<LittleEndian>(data.len() as u64)?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// `None` if the entry is outdated or belongs to another source
+fn read_entry(reader: &mut impl Read, key: &SourceKey) -> anyhow::Result<Option<Vec<u8>>> {
+    if read_version(reader)? != CACHE_VERSION {
+        return Ok(None);
+    }
+
+    let origin = read_string(reader)?;
+    let path = read_string(reader)?;
+    let modified = reader.read_u64::<LittleEndian>()?;
+    let size = reader.read_u64::<LittleEndian>()?;
+    if origin != key.origin
+        || Path::new(&path) != key.path
+        || modified != key.modified
+        || size != key.size
+    {
+        return Ok(None);
+    }
+
+    let len = reader.read_u64::<LittleEndian>()?;
+    let mut data = Vec::with_capacity(len as usize);
+    reader.take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        anyhow::bail!("entry is truncated");
+    }
+    Ok(Some(data))
+}
+
+/// utf-8 with a byte length in front. xnb strings are one byte per char and can't hold every
+/// path
+fn write_string(writer: &mut impl Write, value: &str) -> anyhow::Result<()> {
+    writer.write_u32::<LittleEndian>(value.len() as u32)?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_string(reader: &mut impl Read) -> anyhow::Result<String> {
+    let len = reader.read_u32::<LittleEndian>()?;
+    let mut bytes = Vec::with_capacity(len as usize);
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        anyhow::bail!("entry is truncated");
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn read_version(reader: &mut impl Read) -> anyhow::Result<u32> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        anyhow::bail!("not a cache entry");
+    }
+    Ok(reader.read_u32::<LittleEndian>()?)
+}
+
+fn entry_version(path: &Path) -> anyhow::Result<u32> {
+    read_version(&mut File::open(path)?)
+}
@@ -0,0 +1,157 @@
+//! reloads visual effects and level xml while the app is running, by polling the files they
+//! were read from
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use crate::{assets::AssetLoader, vfs::Vfs, vfx::VisualEffectAsset};
+
+/// a watched file that changed and was read again
+pub enum AssetChange {
+    /// anything still holding `old` should switch to `new`
+    VisualEffect {
+        old: Arc<VisualEffectAsset>,
+        new: Arc<VisualEffectAsset>,
+    },
+    /// the xml of the most recently loaded level or a file it includes changed, the level has
+    /// to be loaded again. holds the resolved path of the level xml
+    Level(PathBuf),
+}
+
+impl AssetLoader {
+    /// re-reads every loaded visual effect and the current level xml that changed since the
+    /// last call. an effect that fails to parse is logged and keeps its old version
+    pub fn poll_changes(&mut self) -> Vec<AssetChange> {
+        let mut changes = Vec::new();
+        let mut level_changed = false;
+        for path in self.watcher.poll(self.vfs.as_ref()) {
+            if self.level_xml.as_deref() == Some(path.as_path())
+                || self.level_includes.contains(&path)
+            {
+                log::info!("level xml {} changed", path.display());
+                level_changed = true;
+                continue;
+            }
+
+            if !self.watched_effects.contains(&path) {
+                continue;
+            }
+            match self.read_visual_effect(&path) {
+                Ok(effect) => {
+                    let new = Arc::new(effect);
+                    let size = self.vfs.size(&path).unwrap_or(0);
+                    let Some(old) = self.visual_effects.replace(&path, new.clone(), size) else {
+                        continue;
+                    };
+                    log::info!("reloaded visual effect {}", path.display());
+                    changes.push(AssetChange::VisualEffect { old, new });
+                }
+                Err(e) => {
+                    log::error!("failed to reload visual effect, keeping the old version: {e:#}")
+                }
+            }
+        }
+        // several of the level's files may change at once, it's only loaded again once
+        if level_changed && let Some(level_xml) = &self.level_xml {
+            changes.push(AssetChange::Level(level_xml.clone()));
+        }
+        changes
+    }
+
+    /// only the most recently loaded level is watched, along with its includes
+    pub(crate) fn watch_level(&mut self, xml_path: &Path) {
+        if let Some(previous) = self.level_xml.take() {
+            self.watcher.unwatch(&previous);
+        }
+        for previous in std::mem::take(&mut self.level_includes) {
+            self.watcher.unwatch(&previous);
+        }
+        self.watcher.watch(self.vfs.as_ref(), xml_path);
+        self.level_xml = Some(xml_path.to_owned());
+    }
+
+    /// watches a file included by the level last passed to `watch_level`
+    pub(crate) fn watch_level_include(&mut self, path: &Path) {
+        if self.level_xml.as_deref() == Some(path) || self.level_includes.iter().any(|p| p == path)
+        {
+            return;
+        }
+        self.watcher.watch(self.vfs.as_ref(), path);
+        self.level_includes.push(path.to_owned());
+    }
+
+    pub(crate) fn watch_visual_effect(&mut self, path: &Path) {
+        self.watcher.watch(self.vfs.as_ref(), path);
+        self.watched_effects.insert(path.to_owned());
+    }
+
+    /// stops watching the effects `evict_unused` dropped, they're read again if they're needed
+    pub(crate) fn unwatch_evicted_effects(&mut self) {
+        let visual_effects = &self.visual_effects;
+        let watcher = &mut self.watcher;
+        self.watched_effects.retain(|path| {
+            let cached = visual_effects.contains(path);
+            if !cached {
+                watcher.unwatch(path);
+            }
+            cached
+        });
+    }
+}
+
+/// what a file looked like when it was last checked. a file that can't be read has neither
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    size: Option<u64>,
+}
+
+impl FileStamp {
+    fn read(vfs: &dyn Vfs, path: &Path) -> Self {
+        FileStamp {
+            modified: vfs.modified(path).ok().flatten(),
+            size: vfs.size(path).ok(),
+        }
+    }
+}
+
+/// remembers the modification time and size of watched files, and reports the ones that
+/// differ each time it is polled
+#[derive(Default)]
+pub struct FileWatcher {
+    files: HashMap<PathBuf, FileStamp>,
+}
+
+impl FileWatcher {
+    /// starts watching `path` as it is now. watching an already watched path does nothing, so
+    /// changes since it was first watched are still reported
+    pub fn watch(&mut self, vfs: &dyn Vfs, path: &Path) {
+        if !self.files.contains_key(path) {
+            self.files
+                .insert(path.to_owned(), FileStamp::read(vfs, path));
+        }
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    /// every watched file that changed since the last poll. files that can't be read are
+    /// reported once when they disappear, and again when they come back
+    pub fn poll(&mut self, vfs: &dyn Vfs) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, stamp) in &mut self.files {
+            let current = FileStamp::read(vfs, path);
+            if current != *stamp {
+                *stamp = current;
+                changed.push(path.clone());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glam::{Mat4, Quat, Vec3};
+use roxmltree::Document;
+
+use crate::{
+    assets::{
+        AssetLoader, CpuBiTree, CpuMesh, CpuTexture, level::definition::LevelDefinition,
+        model::CpuModel,
+    },
+    vfs::Vfs,
+    vfx::VisualEffectAsset,
+    xnb::asset::{XnbAsset, color::Color, level_model::AnimatedLevelPart},
+};
+
+pub mod definition;
+pub mod trigger;
+
+/// everything needed to build a level's scene, without any gpu resources
+pub struct CpuLevel {
+    pub model: CpuLevelModel,
+    pub skymap: Option<CpuSkymap>,
+    pub indoors: bool,
+    /// everything the level xml describes, including what isn't used yet
+    pub definition: LevelDefinition,
+}
+
+pub struct CpuSkymap {
+    pub texture: Arc<CpuTexture>,
+    pub color: Color,
+}
+
+pub struct CpuLevelModel {
+    pub trees: Vec<CpuBiTree>,
+    pub animated_parts: Vec<CpuAnimatedPart>,
+    pub visual_effects: Vec<CpuVisualEffectPlacement>,
+    pub locators: Vec<CpuLocator>,
+}
+
+/// a named point in a level, such as where players spawn
+pub struct CpuLocator {
+    pub name: String,
+    /// in the same convention as scene node transforms
+    pub transform: Mat4,
+}
+
+impl CpuLevelModel {
+    /// where the camera starts out: the first locator named like a start or spawn point, or
+    /// failing that the first locator
+    pub fn spawn_locator(&self) -> Option<&CpuLocator> {
+        self.locators
+            .iter()
+            .find(|locator| {
+                let name = locator.name.to_ascii_lowercase();
+                name.contains("start") || name.contains("spawn")
+            })
+            .or_else(|| self.locators.first())
+    }
+}
+
+/// a movable piece of a level such as a door or a bridge, only shown in its bind pose for now
+pub struct CpuAnimatedPart {
+    pub name: String,
+    pub model: CpuModel,
+    pub children: Vec<CpuAnimatedPart>,
+}
+
+pub struct CpuVisualEffectPlacement {
+    pub name: String,
+    pub transform: Mat4,
+    pub effect: Arc<VisualEffectAsset>,
+}
+
+impl AssetLoader {
+    pub fn load_level(
+        &mut self,
+        xml_path: &Path,
+        base_path: Option<&Path>,
+    ) -> anyhow::Result<CpuLevel> {
+        let xml_path = self.resolve_path(xml_path, base_path, None)?;
+        let xml = self.read_to_string(&xml_path, None)?;
+        self.watch_level(&xml_path);
+        let definition = LevelDefinition::read(&xml_path, &xml, &mut |include, referrer| {
+            let path = self.resolve_path(include, Some(referrer), Some("xml"))?;
+            // watched before it's read, so fixing an include that fails to load reloads the level
+            self.watch_level_include(&path);
+            let xml = self.read_to_string(&path, None)?;
+            Ok((path, xml))
+        })?;
+        for diagnostic in &definition.diagnostics {
+            log::warn!("{diagnostic}");
+        }
+
+        let Some(model_path) = &definition.model else {
+            anyhow::bail!("xml does not have a <Model> node");
+        };
+
+        // queue everything known so far up front so progress doesn't jump backwards as much
+        let model_path = self.resolve_path(
+            Path::new(&model_path.path),
+            Some(&model_path.base),
+            Some("xnb"),
+        )?;
+        self.queue(&model_path);
+        let skymap_path = definition
+            .skymap
+            .as_ref()
+            .map(|skymap| {
+                let texture = &skymap.texture;
+                self.resolve_path(Path::new(&texture.path), Some(&texture.base), Some("xnb"))
+            })
+            .transpose()?;
+        if let Some(skymap_path) = &skymap_path {
+            self.queue(skymap_path);
+        }
+
+        let model = self.load_level_model(&model_path, None)?;
+
+        let skymap_texture = skymap_path
+            .map(|p| self.load_texture(&p, None))
+            .transpose()?;
+
+        Ok(CpuLevel {
+            model,
+            skymap: skymap_texture
+                .zip(definition.skymap.as_ref())
+                .map(|(texture, skymap)| CpuSkymap {
+                    texture,
+                    color: skymap.color,
+                }),
+            indoors: definition.indoor.unwrap_or(false),
+            definition,
+        })
+    }
+
+    pub fn load_level_model(
+        &mut self,
+        path: &Path,
+        base: Option<&Path>,
+    ) -> anyhow::Result<CpuLevelModel> {
+        let path = self.resolve_path(path, base, Some("xnb"))?;
+
+        let model_content = self.load_xnb_content(&path)?;
+        let XnbAsset::LevelModel(level_model) = model_content.primary_asset else {
+            anyhow::bail!("expected LevelModel at path {}", path.display());
+        };
+
+        let mut texture_paths = Vec::new();
+        for tree in &level_model.model.trees {
+            if let XnbAsset::RenderDeferredEffect(effect) = &tree.effect {
+                texture_paths.extend(self.material_texture_paths(effect, &path)?);
+            }
+        }
+        self.prefetch_textures(&texture_paths)?;
+
+        let mut trees = Vec::new();
+        for tree in level_model.model.trees {
+            debug_assert_eq!(tree.vertex_stride as usize, tree.vertex_decl.stride());
+
+            let XnbAsset::RenderDeferredEffect(effect) = tree.effect else {
+                if let XnbAsset::AdditiveEffect(_) = &tree.effect {
+                    log::warn!("skipping unimplemented BiTree with AdditiveEffect");
+                    continue;
+                }
+
+                anyhow::bail!(
+                    "expected RenderDeferredEffect inside LevelModel BiTree, got {}",
+                    tree.effect.as_ref()
+                );
+            };
+
+            let material = self.load_material(effect, &path)?;
+            trees.push(CpuBiTree {
+                visible: tree.visible,
+                mesh: CpuMesh {
+                    vertex_decl: tree.vertex_decl,
+                    vertex_buffer: tree.vertex_buffer,
+                    index_buffer: tree.index_buffer,
+                },
+                material,
+                node: tree.node,
+            });
+        }
+
+        let mut animated_parts = Vec::with_capacity(level_model.animated_parts.len());
+        for part in level_model.animated_parts {
+            animated_parts.push(self.animated_part(part, &model_content.shared_assets, &path)?);
+        }
+
+        let mut visual_effects = Vec::new();
+        for effect_storage in level_model.effect_storages {
+            let effect = self.load_visual_effect(&effect_storage.effect)?;
+            visual_effects.push(CpuVisualEffectPlacement {
+                name: effect_storage.name,
+                transform: Mat4::from_rotation_translation(
+                    Quat::look_to_rh(effect_storage.forward, Vec3::Y),
+                    effect_storage.position,
+                ),
+                effect,
+            });
+        }
+
+        let locators = level_model
+            .locators
+            .into_iter()
+            .map(|locator| CpuLocator {
+                name: locator.name,
+                // `read_mat4` keeps xna's row vector layout, scene nodes use column vectors
+                transform: locator.transform.transpose(),
+            })
+            .collect();
+
+        log::debug!("loaded LevelModel from file {}", path.display());
+
+        Ok(CpuLevelModel {
+            trees,
+            animated_parts,
+            visual_effects,
+            locators,
+        })
+    }
+
+    fn animated_part(
+        &mut self,
+        part: AnimatedLevelPart,
+        shared_assets: &[XnbAsset],
+        path: &Path,
+    ) -> anyhow::Result<CpuAnimatedPart> {
+        let model = self.model(part.model, shared_assets, path)?;
+        let children = part
+            .children
+            .into_iter()
+            .map(|child| self.animated_part(child, shared_assets, path))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(CpuAnimatedPart {
+            name: part.name,
+            model,
+            children,
+        })
+    }
+}
+
+/// every xml under Content/Levels, sorted by path. only the paths are looked at, some of the
+/// files are included by levels rather than being levels, see `is_level_xml`
+pub fn find_levels(vfs: &dyn Vfs) -> anyhow::Result<Vec<PathBuf>> {
+    let mut levels: Vec<PathBuf> = vfs
+        .files()?
+        .into_iter()
+        .filter(|path| is_level_path(path))
+        .collect();
+    levels.sort_by_key(|path| path.to_string_lossy().to_ascii_lowercase());
+    Ok(levels)
+}
+
+/// whether `xml` is a `<Scene>` with a `<Model>`. a level whose xml doesn't parse won't load
+/// either
+pub fn is_level_xml(xml: &str) -> bool {
+    Document::parse(xml).is_ok_and(|doc| {
+        let root = doc.root_element();
+        root.has_tag_name("Scene")
+            && root
+                .children()
+                .any(|child| child.tag_name().name().eq_ignore_ascii_case("model"))
+    })
+}
+
+fn is_level_path(path: &Path) -> bool {
+    let key = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .to_ascii_lowercase();
+    key.starts_with("content/levels/") && key.ends_with(".xml")
+}
@@ -0,0 +1,461 @@
+//! a level xml read into types. only `<Model>`, `<SkyMap>` and `<Indoor>` have to be well formed
+//! for a level to load, anything else that can't be read or isn't known is left out of the
+//! definition and listed in `LevelDefinition::diagnostics` instead
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+use glam::Vec3;
+use roxmltree::{Document, Node, TextPos};
+
+use crate::{assets::level::trigger::Trigger, xnb::asset::color::Color};
+
+#[derive(Debug, Default)]
+pub struct LevelDefinition {
+    /// the `LevelModel` xnb
+    pub model: Option<XmlPath>,
+    pub skymap: Option<SkyMapDefinition>,
+    pub indoor: Option<bool>,
+    pub triggers: Vec<Trigger>,
+    /// characters placed when the level starts, triggers can spawn more later
+    pub spawns: Vec<SpawnDefinition>,
+    /// trigger areas on top of the ones in the `LevelModel`
+    pub areas: Vec<AreaDefinition>,
+    /// sound cues that loop for as long as the level is loaded
+    pub ambience: Vec<SoundDefinition>,
+    pub music: Option<SoundDefinition>,
+    pub lighting: LightingDefinition,
+    /// every file included by the level xml or by the files it includes, as resolved
+    pub includes: Vec<PathBuf>,
+    pub diagnostics: Vec<LevelDiagnostic>,
+}
+
+/// a path written in a level xml, relative to the file it was written in
+#[derive(Debug, Clone)]
+pub struct XmlPath {
+    pub path: String,
+    pub base: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkyMapDefinition {
+    pub texture: XmlPath,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnDefinition {
+    /// the character type
+    pub kind: String,
+    pub area: String,
+    pub count: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AreaDefinition {
+    pub id: String,
+    pub position: Vec3,
+    pub size: Vec3,
+}
+
+#[derive(Debug, Clone)]
+pub struct SoundDefinition {
+    pub cue: String,
+    pub volume: f32,
+}
+
+/// changes to the lights of the `LevelModel`
+#[derive(Debug, Clone, Default)]
+pub struct LightingDefinition {
+    pub ambient_color: Option<Color>,
+    pub lights: Vec<LightOverride>,
+}
+
+/// a light of the `LevelModel` by name, fields that are `None` keep the model's value
+#[derive(Debug, Clone)]
+pub struct LightOverride {
+    pub name: String,
+    pub enabled: Option<bool>,
+    pub diffuse_color: Option<Color>,
+    pub ambient_color: Option<Color>,
+    pub reach: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelDiagnostic {
+    pub path: PathBuf,
+    pub pos: TextPos,
+    pub message: String,
+}
+
+impl LevelDiagnostic {
+    pub fn new(path: &Path, node: Node, message: impl Into<String>) -> Self {
+        LevelDiagnostic {
+            path: path.to_owned(),
+            pos: node.document().text_pos_at(node.range().start),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LevelDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.pos.row,
+            self.pos.col,
+            self.message
+        )
+    }
+}
+
+/// see `LevelDefinition::read`
+pub type ReadInclude<'a> = dyn FnMut(&Path, &Path) -> anyhow::Result<(PathBuf, String)> + 'a;
+
+impl LevelDefinition {
+    /// reads the level xml at `path`. `read_include` resolves the path of an `<Include>` against
+    /// the file it is in, the second argument, and reads it. included files that can't be read
+    /// are reported as diagnostics, only problems with the level xml itself are errors
+    pub fn read(path: &Path, xml: &str, read_include: &mut ReadInclude) -> anyhow::Result<Self> {
+        let mut definition = LevelDefinition::default();
+        let mut stack = vec![path.to_owned()];
+        definition.read_file(path, xml, read_include, &mut stack)?;
+        Ok(definition)
+    }
+
+    /// `stack` is the chain of files that included this one, to catch files including each other
+    fn read_file(
+        &mut self,
+        path: &Path,
+        xml: &str,
+        read_include: &mut ReadInclude,
+        stack: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let doc = Document::parse(xml)?;
+
+        let root = doc.root_element();
+        if root.tag_name().name() != "Scene" {
+            anyhow::bail!("expected root element to be an <Scene> node");
+        }
+
+        for child in root.children().filter(|child| child.is_element()) {
+            let child_name = child.tag_name().name().to_lowercase();
+
+            match child_name.as_str() {
+                "model" => {
+                    self.check_unique(path, child, self.model.is_some());
+                    self.model = Some(XmlPath {
+                        path: text(child)?.to_owned(),
+                        base: path.to_owned(),
+                    });
+                }
+                "skymap" => {
+                    self.check_unique(path, child, self.skymap.is_some());
+                    let color = find_attribute(child, "color").ok_or_else(|| {
+                        anyhow::anyhow!("expected <SkyMap> node to have a 'color' attribute")
+                    })?;
+                    let color = parse_color(color)
+                        .context("expected <SkyMap> node 'color' attribute to be a color")?;
+                    self.skymap = Some(SkyMapDefinition {
+                        texture: XmlPath {
+                            path: text(child)?.to_owned(),
+                            base: path.to_owned(),
+                        },
+                        color,
+                    });
+                }
+                "indoor" => {
+                    self.check_unique(path, child, self.indoor.is_some());
+                    let text = text(child)?;
+                    self.indoor = Some(parse_bool(text).with_context(|| {
+                        format!("expected <Indoor> node to have the text 'true' or 'false', got '{text}'")
+                    })?);
+                }
+                "include" => self.include(path, child, read_include, stack),
+                "trigger" => match Trigger::read(child, path, &mut self.diagnostics) {
+                    Ok(trigger) => self.triggers.push(trigger),
+                    Err(e) => self.report(path, child, e),
+                },
+                "spawn" => match SpawnDefinition::read(child, path, &mut self.diagnostics) {
+                    Ok(spawn) => self.spawns.push(spawn),
+                    Err(e) => self.report(path, child, e),
+                },
+                "area" => match AreaDefinition::read(child, path, &mut self.diagnostics) {
+                    Ok(area) => self.areas.push(area),
+                    Err(e) => self.report(path, child, e),
+                },
+                "ambience" => match SoundDefinition::read(child, path, &mut self.diagnostics) {
+                    Ok(sound) => self.ambience.push(sound),
+                    Err(e) => self.report(path, child, e),
+                },
+                "music" => {
+                    self.check_unique(path, child, self.music.is_some());
+                    match SoundDefinition::read(child, path, &mut self.diagnostics) {
+                        Ok(sound) => self.music = Some(sound),
+                        Err(e) => self.report(path, child, e),
+                    }
+                }
+                "lighting" => self.read_lighting(path, child),
+                _ => self.unknown_element(path, child),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn include(
+        &mut self,
+        path: &Path,
+        node: Node,
+        read_include: &mut ReadInclude,
+        stack: &mut Vec<PathBuf>,
+    ) {
+        let result = text(node).and_then(|include| read_include(Path::new(include), path));
+        let (include_path, xml) = match result {
+            Ok(v) => v,
+            Err(e) => return self.report(path, node, e.context("unable to read <Include>")),
+        };
+
+        if stack.contains(&include_path) {
+            let message = format!("{} includes itself", include_path.display());
+            return self.report(path, node, anyhow::anyhow!(message));
+        }
+
+        self.includes.push(include_path.clone());
+        stack.push(include_path.clone());
+        if let Err(e) = self.read_file(&include_path, &xml, read_include, stack) {
+            let e = e.context(format!("unable to read {}", include_path.display()));
+            self.report(path, node, e);
+        }
+        stack.pop();
+    }
+
+    fn read_lighting(&mut self, path: &Path, node: Node) {
+        check_attributes(node, &["ambientColor"], path, &mut self.diagnostics);
+        match parse_attribute_with(node, "ambientColor", parse_color) {
+            Ok(Some(color)) => self.lighting.ambient_color = Some(color),
+            Ok(None) => {}
+            Err(e) => self.report(path, node, e),
+        }
+
+        for child in node.children().filter(|child| child.is_element()) {
+            if !child.tag_name().name().eq_ignore_ascii_case("light") {
+                self.unknown_element(path, child);
+                continue;
+            }
+            match LightOverride::read(child, path, &mut self.diagnostics) {
+                Ok(light) => self.lighting.lights.push(light),
+                Err(e) => self.report(path, child, e),
+            }
+        }
+    }
+
+    /// the last of an element that should only be given once is used, like the game does
+    fn check_unique(&mut self, path: &Path, node: Node, seen: bool) {
+        if seen {
+            let message = format!(
+                "<{}> is given more than once, the last one is used",
+                node.tag_name().name()
+            );
+            self.diagnostics
+                .push(LevelDiagnostic::new(path, node, message));
+        }
+    }
+
+    fn unknown_element(&mut self, path: &Path, node: Node) {
+        self.diagnostics
+            .push(unknown_element_diagnostic(path, node));
+    }
+
+    fn report(&mut self, path: &Path, node: Node, e: anyhow::Error) {
+        self.diagnostics
+            .push(LevelDiagnostic::new(path, node, format!("{e:#}")));
+    }
+}
+
+impl SpawnDefinition {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        check_attributes(node, &["type", "area", "nr"], path, diagnostics);
+        Ok(SpawnDefinition {
+            kind: attribute(node, "type")?.to_owned(),
+            area: attribute(node, "area")?.to_owned(),
+            count: parse_attribute(node, "nr")?.unwrap_or(1),
+        })
+    }
+}
+
+impl AreaDefinition {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        check_attributes(node, &["id", "position", "size"], path, diagnostics);
+        let vec3 = |name| {
+            parse_attribute_with(node, name, |s| parse_floats(s).map(Vec3::from_array))?
+                .ok_or_else(|| missing_attribute(node, name))
+        };
+        Ok(AreaDefinition {
+            id: attribute(node, "id")?.to_owned(),
+            position: vec3("position")?,
+            size: vec3("size")?,
+        })
+    }
+}
+
+impl SoundDefinition {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        check_attributes(node, &["volume"], path, diagnostics);
+        Ok(SoundDefinition {
+            cue: text(node)?.to_owned(),
+            volume: parse_attribute(node, "volume")?.unwrap_or(1.0),
+        })
+    }
+}
+
+impl LightOverride {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        check_attributes(
+            node,
+            &["id", "enabled", "diffuseColor", "ambientColor", "reach"],
+            path,
+            diagnostics,
+        );
+        Ok(LightOverride {
+            name: attribute(node, "id")?.to_owned(),
+            enabled: parse_attribute_with(node, "enabled", parse_bool)?,
+            diffuse_color: parse_attribute_with(node, "diffuseColor", parse_color)?,
+            ambient_color: parse_attribute_with(node, "ambientColor", parse_color)?,
+            reach: parse_attribute(node, "reach")?,
+        })
+    }
+}
+
+pub(super) fn unknown_element_diagnostic(path: &Path, node: Node) -> LevelDiagnostic {
+    let parent = node
+        .parent_element()
+        .map(|parent| parent.tag_name().name())
+        .unwrap_or_default();
+    LevelDiagnostic::new(
+        path,
+        node,
+        format!("unknown element <{}> in <{parent}>", node.tag_name().name()),
+    )
+}
+
+/// reports the attributes of `node` that aren't in `known`. names are matched ignoring case,
+/// like element names
+pub(super) fn check_attributes(
+    node: Node,
+    known: &[&str],
+    path: &Path,
+    diagnostics: &mut Vec<LevelDiagnostic>,
+) {
+    for attribute in node.attributes() {
+        if !known
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(attribute.name()))
+        {
+            let message = format!(
+                "unknown attribute '{}' on <{}>",
+                attribute.name(),
+                node.tag_name().name()
+            );
+            diagnostics.push(LevelDiagnostic::new(path, node, message));
+        }
+    }
+}
+
+fn text<'a>(node: Node<'a, '_>) -> anyhow::Result<&'a str> {
+    node.text().ok_or_else(|| {
+        anyhow::anyhow!("expected <{}> node to contain text", node.tag_name().name())
+    })
+}
+
+pub(super) fn attribute<'a>(node: Node<'a, '_>, name: &str) -> anyhow::Result<&'a str> {
+    find_attribute(node, name).ok_or_else(|| missing_attribute(node, name))
+}
+
+/// the value of the attribute called `name`, ignoring case like `check_attributes`
+pub(super) fn find_attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes()
+        .find(|attribute| attribute.name().eq_ignore_ascii_case(name))
+        .map(|attribute| attribute.value())
+}
+
+fn missing_attribute(node: Node, name: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "expected <{}> node to have a '{name}' attribute",
+        node.tag_name().name()
+    )
+}
+
+/// `None` if `node` doesn't have the attribute
+fn parse_attribute<T>(node: Node, name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    parse_attribute_with(node, name, |s| Ok(s.parse::<T>()?))
+}
+
+fn parse_attribute_with<T>(
+    node: Node,
+    name: &str,
+    parse: impl FnOnce(&str) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    find_attribute(node, name)
+        .map(|value| {
+            parse(value).with_context(|| {
+                format!(
+                    "unable to parse <{}> node '{name}' attribute value '{value}'",
+                    node.tag_name().name()
+                )
+            })
+        })
+        .transpose()
+}
+
+pub(super) fn parse_bool(s: &str) -> anyhow::Result<bool> {
+    if s.eq_ignore_ascii_case("true") {
+        Ok(true)
+    } else if s.eq_ignore_ascii_case("false") {
+        Ok(false)
+    } else {
+        anyhow::bail!("expected 'true' or 'false', got '{s}'");
+    }
+}
+
+/// comma separated
+fn parse_floats<const N: usize>(s: &str) -> anyhow::Result<[f32; N]> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()?;
+    values
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("expected {N} comma separated values"))
+}
+
+fn parse_color(s: &str) -> anyhow::Result<Color> {
+    let [r, g, b] = parse_floats(s)?;
+    Ok(Color { r, g, b })
+}
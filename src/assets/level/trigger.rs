@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use anyhow::Context;
+use roxmltree::Node;
+
+use crate::assets::level::definition::{
+    LevelDiagnostic, SpawnDefinition, check_attributes, find_attribute, parse_bool,
+    unknown_element_diagnostic,
+};
+
+#[derive(Debug)]
+pub struct Trigger {
+    pub name: String,
+    pub repeat: TriggerRepeat,
+    pub autorun: bool,
+    pub conditions: Vec<TriggerCondition>,
+    pub actions: Vec<TriggerAction>,
+}
+
+impl Trigger {
+    /// conditions and actions that can't be read are left out and reported in `diagnostics`
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        check_attributes(node, &["id", "repeat", "autorun"], path, diagnostics);
+
+        let name = find_attribute(node, "id")
+            .ok_or_else(|| anyhow::anyhow!("expected <Trigger> node to have an 'id' attribute"))?
+            .to_string();
+
+        let repeat = if let Some(repeat_attr) = find_attribute(node, "repeat") {
+            TriggerRepeat::parse(repeat_attr)?
+        } else {
+            anyhow::bail!("expected <Trigger> node to have a 'repeat' attribute");
+        };
+
+        let autorun = if let Some(autorun_attr) = find_attribute(node, "autorun") {
+            parse_bool(autorun_attr).with_context(|| {
+                format!("expected trigger autorun attribute to be 'true' or 'false', got '{autorun_attr}'")
+            })?
+        } else {
+            true // TODO: is this the correct default?
+        };
+
+        let mut conditions = Vec::new();
+        let mut actions = Vec::new();
+        for child in node.children().filter(|n| n.is_element()) {
+            let child_name = child.tag_name().name().to_ascii_lowercase();
+            match child_name.as_str() {
+                "if" => {
+                    for condition_node in child.children().filter(|n| n.is_element()) {
+                        match TriggerCondition::read(condition_node, path, diagnostics) {
+                            Ok(v) => conditions.push(v),
+                            Err(e) => diagnostics.push(LevelDiagnostic::new(
+                                path,
+                                condition_node,
+                                format!("{e:#}"),
+                            )),
+                        }
+                    }
+                }
+                "then" => {
+                    for action_node in child.children().filter(|n| n.is_element()) {
+                        match TriggerAction::read(action_node, path, diagnostics) {
+                            Ok(v) => actions.push(v),
+                            Err(e) => diagnostics.push(LevelDiagnostic::new(
+                                path,
+                                action_node,
+                                format!("{e:#}"),
+                            )),
+                        }
+                    }
+                }
+                _ => diagnostics.push(unknown_element_diagnostic(path, child)),
+            }
+        }
+
+        Ok(Trigger {
+            name,
+            repeat,
+            autorun,
+            conditions,
+            actions,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum TriggerRepeat {
+    False,
+    True,
+    Count(f32),
+}
+
+impl TriggerRepeat {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        if s.eq_ignore_ascii_case("true") {
+            Ok(TriggerRepeat::True)
+        } else if s.eq_ignore_ascii_case("false") {
+            Ok(TriggerRepeat::False)
+        } else {
+            let count = s
+                .parse::<f32>()
+                .with_context(|| format!("unable to parse trigger repeat value {s}"))?;
+            Ok(TriggerRepeat::Count(count))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TriggerCondition {
+    Present(TriggerConditionPresent),
+}
+
+impl TriggerCondition {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        let name = node.tag_name().name().to_ascii_lowercase();
+
+        match name.as_str() {
+            "present" => Ok(TriggerCondition::Present(TriggerConditionPresent::read(
+                node,
+                path,
+                diagnostics,
+            )?)),
+            _ => anyhow::bail!("unknown trigger condition '{name}'"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TriggerConditionPresent {
+    pub kind: String,
+    pub area: String,
+    pub compare_method: CompareMethod,
+    pub number: i32,
+}
+
+impl TriggerConditionPresent {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        check_attributes(
+            node,
+            &["type", "area", "compareMethod", "nr"],
+            path,
+            diagnostics,
+        );
+
+        let Some(kind) = find_attribute(node, "type") else {
+            anyhow::bail!("expected <Present> node to have a 'type' attribute");
+        };
+
+        let Some(area) = find_attribute(node, "area") else {
+            anyhow::bail!("expected <Present> node to have an 'area' attribute");
+        };
+
+        let compare_method = if let Some(method_attr) = find_attribute(node, "compareMethod") {
+            if method_attr.eq_ignore_ascii_case("less") {
+                CompareMethod::Less
+            } else if method_attr.eq_ignore_ascii_case("equal") {
+                CompareMethod::Equal
+            } else if method_attr.eq_ignore_ascii_case("greater") {
+                CompareMethod::Greater
+            } else {
+                anyhow::bail!(
+                    "expected <Present> node 'compareMethod' attribute value to be 'less', 'equal', or 'greater', got '{method_attr}'"
+                );
+            }
+        } else {
+            anyhow::bail!("expected <Present> node to have a 'compareMethod' attribute");
+        };
+
+        let number = if let Some(number_attr) = find_attribute(node, "nr") {
+            number_attr.parse::<i32>().with_context(|| {
+                format!("unable to parse <Present> node 'nr' attribute value {number_attr}")
+            })?
+        } else {
+            anyhow::bail!("expected <Present> node to have a 'nr' attribute");
+        };
+
+        Ok(TriggerConditionPresent {
+            kind: kind.into(),
+            area: area.into(),
+            compare_method,
+            number,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum TriggerAction {
+    Spawn(SpawnDefinition),
+}
+
+impl TriggerAction {
+    pub fn read(
+        node: Node,
+        path: &Path,
+        diagnostics: &mut Vec<LevelDiagnostic>,
+    ) -> anyhow::Result<Self> {
+        let name = node.tag_name().name().to_ascii_lowercase();
+
+        match name.as_str() {
+            "spawn" => Ok(TriggerAction::Spawn(SpawnDefinition::read(
+                node,
+                path,
+                diagnostics,
+            )?)),
+            _ => anyhow::bail!("unknown trigger action '{name}'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMethod {
+    Less,
+    Equal,
+    Greater,
+}
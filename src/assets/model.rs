@@ -0,0 +1,199 @@
+use std::{collections::HashMap, path::Path};
+
+use glam::Mat4;
+
+use crate::{
+    assets::{AssetLoader, MaterialParams},
+    xnb::{
+        self,
+        asset::{
+            XnbAsset, index_buffer::IndexBuffer, model::BoundingSphere,
+            vertex_buffer::VertexBuffer, vertex_decl::VertexDeclaration,
+        },
+    },
+};
+
+/// a `Model` with the materials of its mesh parts loaded
+pub struct CpuModel {
+    pub bones: Vec<CpuBone>,
+    pub meshes: Vec<CpuModelMesh>,
+    /// indexed by `CpuModelPart::material`
+    pub materials: Vec<MaterialParams>,
+}
+
+#[derive(Clone)]
+pub struct CpuBone {
+    pub name: String,
+    /// relative to the parent bone, in the same convention as scene node transforms
+    pub transform: Mat4,
+    /// index into `CpuModel::bones`, `None` for the root
+    pub parent: Option<usize>,
+}
+
+pub struct CpuModelMesh {
+    pub name: String,
+    /// index into `CpuModel::bones` of the bone the mesh is attached to
+    pub bone: Option<usize>,
+    /// relative to `bone`
+    pub bounds: BoundingSphere,
+    pub vertex_buffer: VertexBuffer,
+    pub index_buffer: IndexBuffer,
+    pub parts: Vec<CpuModelPart>,
+}
+
+pub struct CpuModelPart {
+    pub vertex_decl: VertexDeclaration,
+    /// index into `CpuModel::materials`
+    pub material: usize,
+    pub start_index: u32,
+    pub index_count: u32,
+    /// in vertices from the start of the mesh's vertex buffer
+    pub base_vertex: i32,
+}
+
+impl AssetLoader {
+    pub fn load_model(&mut self, path: &Path, base: Option<&Path>) -> anyhow::Result<CpuModel> {
+        let path = self.resolve_path(path, base, Some("xnb"))?;
+
+        let content = self.load_xnb_content(&path)?;
+        let XnbAsset::Model(model) = content.primary_asset else {
+            anyhow::bail!("expected Model at path {}", path.display());
+        };
+        let model = self.model(model, &content.shared_assets, &path)?;
+
+        log::debug!("loaded Model from file {}", path.display());
+
+        Ok(model)
+    }
+
+    /// loads the materials of `model`. the effects of its mesh parts refer to the
+    /// `shared_assets` of the xnb it was read from, `path`, which texture paths are relative to
+    pub(crate) fn model(
+        &mut self,
+        model: xnb::Model,
+        shared_assets: &[XnbAsset],
+        path: &Path,
+    ) -> anyhow::Result<CpuModel> {
+        let num_bones = model.bones.len();
+        let bones = model
+            .bones
+            .into_iter()
+            .zip(&model.bones_hierarchy)
+            .map(|(bone, hierarchy)| CpuBone {
+                name: bone.name,
+                // `read_mat4` keeps xna's row vector layout, scene nodes use column vectors
+                transform: bone.transform.transpose(),
+                parent: bone_index(hierarchy.parent_ref, num_bones),
+            })
+            .collect();
+
+        // shared asset references are 1 based, 0 is no effect
+        let effect = |index: i32| {
+            (index - 1)
+                .try_into()
+                .ok()
+                .and_then(|i: usize| shared_assets.get(i))
+        };
+
+        let mut texture_paths = Vec::new();
+        for mesh in &model.meshes {
+            for part in &mesh.parts {
+                if let Some(XnbAsset::RenderDeferredEffect(effect)) =
+                    effect(part.shared_content_material_index)
+                {
+                    texture_paths.extend(self.material_texture_paths(effect, path)?);
+                }
+            }
+        }
+        self.prefetch_textures(&texture_paths)?;
+
+        // parts usually share a handful of effects, each is only loaded once
+        let mut materials = Vec::new();
+        let mut material_indices: HashMap<i32, usize> = HashMap::new();
+
+        let mut meshes = Vec::with_capacity(model.meshes.len());
+        for mesh in model.meshes {
+            let mut parts = Vec::with_capacity(mesh.parts.len());
+            for part in &mesh.parts {
+                let Some(vertex_decl) = model.vertex_decls.get(part.vertex_decl_index as usize)
+                else {
+                    anyhow::bail!(
+                        "mesh '{}' refers to missing vertex declaration {}",
+                        mesh.name,
+                        part.vertex_decl_index
+                    );
+                };
+
+                let material = match material_indices.get(&part.shared_content_material_index) {
+                    Some(material) => *material,
+                    None => {
+                        let effect = match effect(part.shared_content_material_index) {
+                            Some(XnbAsset::RenderDeferredEffect(effect)) => effect,
+                            Some(effect) => {
+                                log::warn!(
+                                    "skipping unimplemented part of mesh '{}' with {}",
+                                    mesh.name,
+                                    effect.as_ref()
+                                );
+                                continue;
+                            }
+                            None => {
+                                log::warn!(
+                                    "skipping part of mesh '{}' without an effect",
+                                    mesh.name
+                                );
+                                continue;
+                            }
+                        };
+                        materials.push(self.load_material(effect.clone(), path)?);
+                        material_indices
+                            .insert(part.shared_content_material_index, materials.len() - 1);
+                        materials.len() - 1
+                    }
+                };
+
+                // the vertex buffer is bound whole, so the offset of the part's stream is
+                // folded into its base vertex
+                let stride = vertex_decl.stride() as u32;
+                if stride == 0 || part.stream_offset % stride != 0 {
+                    anyhow::bail!(
+                        "mesh '{}' has a stream offset of {} which isn't a multiple of its vertex stride {stride}",
+                        mesh.name,
+                        part.stream_offset
+                    );
+                }
+
+                parts.push(CpuModelPart {
+                    vertex_decl: vertex_decl.clone(),
+                    material,
+                    start_index: part.start_index,
+                    index_count: part.primitive_count * 3,
+                    base_vertex: (part.base_vertex + part.stream_offset / stride) as i32,
+                });
+            }
+
+            meshes.push(CpuModelMesh {
+                name: mesh.name,
+                bone: bone_index(mesh.parent_bone_ref, num_bones),
+                bounds: mesh.bounds,
+                vertex_buffer: mesh.vertex_buffer,
+                index_buffer: mesh.index_buffer,
+                parts,
+            });
+        }
+
+        Ok(CpuModel {
+            bones,
+            meshes,
+            materials,
+        })
+    }
+}
+
+/// bone references are 1 based, 0 is no bone. ones past the last bone are treated the same, so
+/// whatever refers to them ends up at the root of the model
+fn bone_index(bone_ref: u32, num_bones: usize) -> Option<usize> {
+    (bone_ref as usize)
+        .checked_sub(1)
+        .filter(|&i| i < num_bones)
+}
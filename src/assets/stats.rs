@@ -0,0 +1,289 @@
+//! what assets cost in memory and load time, to find the heaviest levels and assets. the
+//! `AssetManager` keeps a record of everything it uploads, `AssetStats::from_level` builds the
+//! same records for a level that was only loaded on the cpu
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    assets::{
+        CpuTexture, MaterialParams,
+        level::{CpuAnimatedPart, CpuLevel},
+        model::CpuModel,
+    },
+    xnb::asset::{
+        index_buffer::IndexBuffer, render_deferred_effect::RenderDeferredEffect,
+        texture_2d::PixelFormat, vertex_buffer::VertexBuffer,
+    },
+};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetCategory {
+    Texture(PixelFormat),
+    VertexBuffer,
+    IndexBuffer,
+    /// the properties of a `RenderDeferredEffect`, its textures are counted on their own
+    Effect,
+}
+
+impl fmt::Display for AssetCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetCategory::Texture(format) => write!(f, "texture {format:?}"),
+            AssetCategory::VertexBuffer => write!(f, "vertex buffer"),
+            AssetCategory::IndexBuffer => write!(f, "index buffer"),
+            AssetCategory::Effect => write!(f, "effect"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetRecord {
+    pub name: String,
+    pub category: AssetCategory,
+    pub cpu_bytes: u64,
+    /// what uploading the asset takes, or would take if it hasn't been uploaded. effects only
+    /// take gpu memory once they are
+    pub gpu_bytes: u64,
+    /// meshes are used as stored in the xnb, uploading them is counted on their vertex buffer
+    pub load_time: Duration,
+    /// how many things use the asset, not counting caches
+    pub refs: usize,
+}
+
+impl AssetRecord {
+    pub fn texture(texture: &CpuTexture) -> Self {
+        AssetRecord {
+            name: texture.path.display().to_string(),
+            category: AssetCategory::Texture(texture.format),
+            cpu_bytes: texture.size_bytes(),
+            gpu_bytes: texture.size_bytes(),
+            load_time: texture.load_time,
+            refs: 0,
+        }
+    }
+
+    pub fn vertex_buffer(name: String, vertex_buffer: &VertexBuffer) -> Self {
+        let bytes = vertex_buffer.data.len() as u64;
+        AssetRecord {
+            name,
+            category: AssetCategory::VertexBuffer,
+            cpu_bytes: bytes,
+            gpu_bytes: bytes,
+            load_time: Duration::ZERO,
+            refs: 0,
+        }
+    }
+
+    pub fn index_buffer(name: String, index_buffer: &IndexBuffer) -> Self {
+        let bytes = index_buffer.data.len() as u64;
+        AssetRecord {
+            name,
+            category: AssetCategory::IndexBuffer,
+            cpu_bytes: bytes,
+            gpu_bytes: bytes,
+            load_time: Duration::ZERO,
+            refs: 0,
+        }
+    }
+
+    pub fn effect(name: String, effect: &RenderDeferredEffect) -> Self {
+        AssetRecord {
+            name,
+            category: AssetCategory::Effect,
+            cpu_bytes: size_of_val(effect) as u64,
+            gpu_bytes: 0,
+            load_time: Duration::ZERO,
+            refs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CategoryTotals {
+    pub assets: usize,
+    pub cpu_bytes: u64,
+    pub gpu_bytes: u64,
+    pub load_time: Duration,
+}
+
+impl std::ops::AddAssign<&AssetRecord> for CategoryTotals {
+    fn add_assign(&mut self, rhs: &AssetRecord) {
+        self.assets += 1;
+        self.cpu_bytes += rhs.cpu_bytes;
+        self.gpu_bytes += rhs.gpu_bytes;
+        self.load_time += rhs.load_time;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AssetStats {
+    pub records: Vec<AssetRecord>,
+}
+
+impl AssetStats {
+    /// textures are counted once no matter how many materials use them, `refs` is how many do
+    pub fn from_level(level: &CpuLevel) -> Self {
+        let mut stats = AssetStats::default();
+        let mut textures = TextureUses::default();
+
+        for (i, tree) in level.model.trees.iter().enumerate() {
+            let name = format!("bitree {i}");
+            stats.add_mesh(&name, &tree.mesh.vertex_buffer, &tree.mesh.index_buffer);
+            stats.add_material(&name, &tree.material, &mut textures);
+        }
+        for part in &level.model.animated_parts {
+            stats.add_animated_part(part, &mut textures);
+        }
+        if let Some(skymap) = &level.skymap {
+            textures.add(&skymap.texture);
+        }
+
+        for (texture, refs) in textures.uses.into_values() {
+            stats.records.push(AssetRecord {
+                refs,
+                ..AssetRecord::texture(&texture)
+            });
+        }
+        stats
+    }
+
+    fn add_animated_part(&mut self, part: &CpuAnimatedPart, textures: &mut TextureUses) {
+        self.add_model(&part.name, &part.model, textures);
+        for child in &part.children {
+            self.add_animated_part(child, textures);
+        }
+    }
+
+    /// like `AssetManager`, every part of a mesh gets its own effect uniform
+    fn add_model(&mut self, name: &str, model: &CpuModel, textures: &mut TextureUses) {
+        for mesh in &model.meshes {
+            let mesh_name = format!("{name}/{}", mesh.name);
+            self.add_mesh(&mesh_name, &mesh.vertex_buffer, &mesh.index_buffer);
+            for (i, part) in mesh.parts.iter().enumerate() {
+                let effect = &model.materials[part.material].effect;
+                self.records.push(AssetRecord {
+                    refs: 1,
+                    ..AssetRecord::effect(format!("{mesh_name} part {i}"), effect)
+                });
+            }
+        }
+        for material in &model.materials {
+            textures.add_material(material);
+        }
+    }
+
+    fn add_mesh(&mut self, name: &str, vertex_buffer: &VertexBuffer, index_buffer: &IndexBuffer) {
+        for record in [
+            AssetRecord::vertex_buffer(name.to_owned(), vertex_buffer),
+            AssetRecord::index_buffer(name.to_owned(), index_buffer),
+        ] {
+            self.records.push(AssetRecord { refs: 1, ..record });
+        }
+    }
+
+    fn add_material(&mut self, name: &str, material: &MaterialParams, textures: &mut TextureUses) {
+        self.records.push(AssetRecord {
+            refs: 1,
+            ..AssetRecord::effect(name.to_owned(), &material.effect)
+        });
+        textures.add_material(material);
+    }
+
+    /// keyed by the `Display` of `AssetCategory`
+    pub fn totals(&self) -> BTreeMap<String, CategoryTotals> {
+        let mut totals: BTreeMap<String, CategoryTotals> = BTreeMap::new();
+        for record in &self.records {
+            *totals.entry(record.category.to_string()).or_default() += record;
+        }
+        totals
+    }
+
+    pub fn total(&self) -> CategoryTotals {
+        let mut total = CategoryTotals::default();
+        for record in &self.records {
+            total += record;
+        }
+        total
+    }
+
+    /// the `count` assets taking the most memory, cpu and gpu combined
+    pub fn heaviest(&self, count: usize) -> Vec<&AssetRecord> {
+        let mut records: Vec<&AssetRecord> = self.records.iter().collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.cpu_bytes + record.gpu_bytes));
+        records.truncate(count);
+        records
+    }
+
+    /// one line per category and the total, followed by the `heaviest` heaviest assets
+    pub fn report(&self, heaviest: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (category, totals) in self.totals() {
+            lines.push(format!("{category:>16}: {}", format_totals(totals)));
+        }
+        lines.push(format!("{:>16}: {}", "total", format_totals(self.total())));
+
+        let heaviest = self.heaviest(heaviest);
+        if !heaviest.is_empty() {
+            lines.push("heaviest assets:".to_owned());
+        }
+        for record in heaviest {
+            lines.push(format!(
+                "  {:.2} MiB cpu, {:.2} MiB gpu, {:.1} ms, {} refs, {}: {}",
+                record.cpu_bytes as f64 / MIB,
+                record.gpu_bytes as f64 / MIB,
+                record.load_time.as_secs_f64() * 1000.0,
+                record.refs,
+                record.category,
+                record.name
+            ));
+        }
+        lines
+    }
+}
+
+fn format_totals(totals: CategoryTotals) -> String {
+    format!(
+        "{} assets, {:.2} MiB cpu, {:.2} MiB gpu, {:.1} ms",
+        totals.assets,
+        totals.cpu_bytes as f64 / MIB,
+        totals.gpu_bytes as f64 / MIB,
+        totals.load_time.as_secs_f64() * 1000.0
+    )
+}
+
+/// the textures of a level and how many materials use each
+#[derive(Default)]
+struct TextureUses {
+    uses: HashMap<PathBuf, (Arc<CpuTexture>, usize)>,
+}
+
+impl TextureUses {
+    fn add(&mut self, texture: &Arc<CpuTexture>) {
+        self.uses
+            .entry(texture.path.clone())
+            .or_insert_with(|| (texture.clone(), 0))
+            .1 += 1;
+    }
+
+    fn add_material(&mut self, material: &MaterialParams) {
+        for maps in [&material.material_0, &material.material_1] {
+            for texture in [&maps.diffuse, &maps.material, &maps.normal]
+                .into_iter()
+                .flatten()
+            {
+                self.add(texture);
+            }
+        }
+        if let Some(texture) = &material.reflection_map {
+            self.add(texture);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! locating files inside a magicka install
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::vfs::Vfs;
+
+/// every file and directory in a magicka install, keyed by their lowercase path relative to the
+/// install root, so paths whose casing doesn't match the files still resolve with a hash lookup
+pub struct ContentIndex {
+    /// lowercase '/' separated relative path -> real relative path
+    files: HashMap<String, PathBuf>,
+    dirs: HashMap<String, PathBuf>,
+    collisions: Vec<Vec<PathBuf>>,
+}
+
+impl ContentIndex {
+    pub fn build(vfs: &dyn Vfs) -> anyhow::Result<Self> {
+        let mut index = ContentIndex {
+            files: HashMap::new(),
+            dirs: HashMap::new(),
+            collisions: Vec::new(),
+        };
+        index.dirs.insert(String::new(), PathBuf::new());
+
+        let mut files = vfs.files()?;
+        // sorted so the same path wins every time, and reports are stable between runs
+        files.sort();
+
+        let mut collisions: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for file in files {
+            // directories differing only in case are merged, which of them a file is listed
+            // under doesn't change how it resolves. an overlay layer may well case them
+            // differently than the base game
+            for dir in file.ancestors().skip(1) {
+                if dir.as_os_str().is_empty() {
+                    break;
+                }
+                index
+                    .dirs
+                    .entry(index_key(dir))
+                    .or_insert_with(|| dir.to_owned());
+            }
+
+            let key = index_key(&file);
+            match index.files.get(&key) {
+                Some(existing) if *existing != file => {
+                    let paths = collisions
+                        .entry(key)
+                        .or_insert_with(|| vec![existing.clone()]);
+                    if !paths.contains(&file) {
+                        paths.push(file);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    index.files.insert(key, file);
+                }
+            }
+        }
+
+        let mut collisions: Vec<Vec<PathBuf>> = collisions.into_values().collect();
+        collisions.sort();
+        for paths in collisions {
+            log::warn!(
+                "ambiguous content paths differing only in case, using the first: {}",
+                paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            index.collisions.push(paths);
+        }
+
+        log::debug!(
+            "indexed {} files in {} directories",
+            index.files.len(),
+            index.dirs.len()
+        );
+
+        Ok(index)
+    }
+
+    /// groups of relative file paths that only differ in case. only the first of each is reachable
+    pub fn collisions(&self) -> &[Vec<PathBuf>] {
+        &self.collisions
+    }
+
+    /// the real path of `path`, relative to the root of the vfs the index was built from. `None`
+    /// for files that aren't in the index
+    /// - `path` may be cased differently from the file and use either separator, as paths read
+    ///   from xnbs do. `ensure_extension` is added if it has no extension
+    /// - `base` is the directory `path` is relative to, the root if `None`. a file is taken to
+    ///   mean the directory it's in. absolute paths aren't in the index
+    pub fn resolve(
+        &self,
+        path: &Path,
+        base: Option<&Path>,
+        ensure_extension: Option<&str>,
+    ) -> Option<PathBuf> {
+        let mut components: Vec<String> = Vec::new();
+
+        if let Some(base) = base {
+            if base.has_root() {
+                return None;
+            }
+            push_components(&mut components, base);
+
+            // a base that isn't a directory is a file next to `path`
+            if !self.dirs.contains_key(&components.join("/")) {
+                components.pop();
+            }
+        }
+
+        let path_str = path.to_string_lossy();
+        let has_extension = Path::new(path_str.rsplit(['/', '\\']).next().unwrap_or_default())
+            .extension()
+            .is_some();
+        push_components(&mut components, path);
+
+        let mut key = components.join("/");
+        if let Some(extension) = ensure_extension
+            && !has_extension
+        {
+            key.push('.');
+            key.push_str(&extension.to_ascii_lowercase());
+        }
+
+        self.files
+            .get(&key)
+            .or_else(|| self.dirs.get(&key))
+            .cloned()
+    }
+}
+
+/// appends the lowercase components of `path` to `components`, split on either separator
+fn push_components(components: &mut Vec<String>, path: &Path) {
+    for component in path.to_string_lossy().split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component.to_ascii_lowercase()),
+        }
+    }
+}
+
+fn index_key(relative: &Path) -> String {
+    let mut components = Vec::new();
+    push_components(&mut components, relative);
+    components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::{MemoryVfs, OverlayVfs};
+
+    #[test]
+    fn resolves_through_an_overlay() {
+        let mut base = MemoryVfs::default();
+        base.insert("Content/Levels/Level.xml", "");
+        base.insert("Content/Models/Tree.xnb", "base tree");
+        base.insert("Content/Models/Rock.xnb", "base rock");
+        let mut layer = MemoryVfs::default();
+        layer.insert("content/models/tree.xnb", "mod tree");
+        let vfs = OverlayVfs::new(vec![Box::new(base), Box::new(layer)]).unwrap();
+        let index = ContentIndex::build(&vfs).unwrap();
+
+        let level = Path::new("Content/Levels/Level.xml");
+        let tree = index
+            .resolve(Path::new("..\\Models\\TREE"), Some(level), Some("xnb"))
+            .unwrap();
+        assert_eq!(tree, Path::new("content/models/tree.xnb"));
+        assert_eq!(vfs.read_to_string(&tree).unwrap(), "mod tree");
+
+        let rock = index
+            .resolve(Path::new("content/models/rock.xnb"), None, Some("xnb"))
+            .unwrap();
+        assert_eq!(rock, Path::new("Content/Models/Rock.xnb"));
+        assert_eq!(vfs.read_to_string(&rock).unwrap(), "base rock");
+
+        assert_eq!(
+            index.resolve(Path::new("Models"), Some(Path::new("content")), None),
+            Some(PathBuf::from("Content/Models"))
+        );
+        assert_eq!(
+            index.resolve(Path::new("Bush"), Some(level), Some("xnb")),
+            None
+        );
+        assert!(index.collisions().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+pub mod cache;
+pub mod dedup_pipelines;
+pub mod deps;
+pub mod diff;
+pub mod fix_effects;
+pub mod inspect;
+pub mod lint_effects;
+pub mod patch_level;
+pub mod scan;
+pub mod stats;
+
+/// recursively collects all files under `path` with one of the given extensions (case-insensitive).
+/// results are sorted so reports are stable between runs
+pub fn collect_files(path: &Path, extensions: &[&str]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files_inner(path, extensions, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files_inner(
+    path: &Path,
+    extensions: &[&str],
+    files: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(path)? {
+        let entry = match entry {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("error: {e}");
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            collect_files_inner(&path, extensions, files)?;
+        } else if file_type.is_file() && has_extension(&path, extensions) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// runs `f`, converting a panic into an error so one bad file doesn't abort a whole corpus run.
+/// a lot of the parsers still `assert!` on values they don't understand yet
+pub fn catch_panic<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    std::panic::set_hook(previous_hook);
+
+    match result {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = if let Some(s) = payload.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = payload.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic payload".to_string()
+            };
+            anyhow::bail!("panicked: {message}");
+        }
+    }
+}
+
+/// writes `value` as pretty printed json to `out`, or stdout if `out` is `None`
+pub fn write_json(value: &impl serde::Serialize, out: Option<&Path>) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    if let Some(out) = out {
+        std::fs::write(out, json)?;
+    } else {
+        println!("{json}");
+    }
+    Ok(())
+}
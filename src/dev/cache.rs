@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use crate::assets::disk_cache::{CACHE_VERSION, DiskCache, EntryStats};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// prints the number and size of entries of every kind in the cache at `dir`, or the default
+/// cache directory
+pub fn stats(dir: Option<&Path>) -> anyhow::Result<()> {
+    let disk_cache = open(dir)?;
+    let stats = disk_cache.stats()?;
+
+    println!("{} (version {CACHE_VERSION})", disk_cache.dir().display());
+    for (kind, kind_stats) in &stats.kinds {
+        println!("{kind:>10}: {}", format_stats(*kind_stats));
+    }
+    if stats.outdated.entries > 0 {
+        println!("{:>10}: {}", "outdated", format_stats(stats.outdated));
+    }
+    println!("{:>10}: {}", "total", format_stats(stats.total()));
+
+    Ok(())
+}
+
+/// deletes every entry in the cache at `dir`, or the default cache directory
+pub fn clear(dir: Option<&Path>) -> anyhow::Result<()> {
+    let disk_cache = open(dir)?;
+    let removed = disk_cache.clear()?;
+    println!(
+        "removed {} from {}",
+        format_stats(removed),
+        disk_cache.dir().display()
+    );
+    Ok(())
+}
+
+fn open(dir: Option<&Path>) -> anyhow::Result<DiskCache> {
+    let dir = match dir {
+        Some(dir) => dir.to_owned(),
+        None => DiskCache::default_dir().ok_or_else(|| {
+            anyhow::anyhow!("unable to determine the cache directory, pass one with --dir")
+        })?,
+    };
+    DiskCache::open(dir)
+}
+
+fn format_stats(stats: EntryStats) -> String {
+    format!(
+        "{} entries, {:.1} MiB",
+        stats.entries,
+        stats.bytes as f64 / MIB
+    )
+}
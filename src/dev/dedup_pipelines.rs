@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::BufReader,
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    dev::{catch_panic, collect_files, write_json},
+    xnb::{
+        Xnb, XnbContent,
+        asset::{
+            XnbAsset,
+            level_model::{AnimatedLevelPart, Liquid},
+            model::Model,
+            vertex_decl::VertexDeclaration,
+        },
+    },
+};
+
+/// how many example files are kept for each unique pipeline
+const MAX_EXAMPLES: usize = 5;
+
+#[derive(clap::ValueEnum, Clone, Copy, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct DedupedPipelineInfo {
+    vertex_declaration: VertexDeclaration,
+    effect: String,
+}
+
+#[derive(Default)]
+struct DedupedPipelineUsage {
+    /// number of mesh parts using this pipeline
+    count: u32,
+    /// what kind of mesh the parts came from (BiTree, Model, AnimatedLevelPart, ...)
+    sources: BTreeSet<&'static str>,
+    examples: BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+struct PipelineRow {
+    count: u32,
+    effect: String,
+    stride: usize,
+    vertex_declaration: Vec<String>,
+    sources: Vec<&'static str>,
+    examples: Vec<String>,
+}
+
+/// recursively parses all mesh bearing assets under `path` and finds all unique effect and vertex layout combinations
+pub fn dedup_pipelines(path: &str, format: OutputFormat, out: Option<&Path>) -> anyhow::Result<()> {
+    let root = Path::new(path);
+    let xnb_paths = collect_files(root, &["xnb"])?;
+
+    let mut map: HashMap<DedupedPipelineInfo, DedupedPipelineUsage> = HashMap::new();
+
+    let mut num_processed = 0;
+    let mut num_errors = 0;
+    for path in &xnb_paths {
+        num_processed += 1;
+        let display_path = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        match catch_panic(|| handle_file(path, &display_path, &mut map)) {
+            Ok(_) => {}
+            Err(e) => {
+                num_errors += 1;
+                eprintln!("error on {display_path}: {e}");
+                continue;
+            }
+        }
+    }
+
+    let mut rows = map
+        .into_iter()
+        .map(|(pipeline, usage)| PipelineRow {
+            count: usage.count,
+            effect: pipeline.effect,
+            stride: pipeline.vertex_declaration.stride(),
+            vertex_declaration: pipeline
+                .vertex_declaration
+                .elements
+                .iter()
+                .map(|el| format!("{}@{}", el.debug_string(), el.offset))
+                .collect(),
+            sources: usage.sources.into_iter().collect(),
+            examples: usage.examples.into_iter().collect(),
+        })
+        .collect::<Vec<_>>();
+    rows.sort_unstable_by(|a, b| {
+        a.count
+            .cmp(&b.count)
+            .then_with(|| a.effect.cmp(&b.effect))
+            .then_with(|| a.vertex_declaration.cmp(&b.vertex_declaration))
+    });
+
+    match format {
+        OutputFormat::Text => {
+            let mut text = String::new();
+            for row in &rows {
+                text.push_str(&format!(
+                    "count: {:>5}, effect: {}, sources: {}, vertex decl: {}\n",
+                    row.count,
+                    row.effect,
+                    row.sources.join(","),
+                    row.vertex_declaration.join(" "),
+                ));
+            }
+            write_output(&text, out)?;
+        }
+        OutputFormat::Json => write_json(&rows, out)?,
+        OutputFormat::Csv => {
+            let mut csv = String::from("count,effect,stride,vertex_declaration,sources,examples\n");
+            for row in &rows {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    row.count,
+                    csv_field(&row.effect),
+                    row.stride,
+                    csv_field(&row.vertex_declaration.join(" ")),
+                    csv_field(&row.sources.join(";")),
+                    csv_field(&row.examples.join(";")),
+                ));
+            }
+            write_output(&csv, out)?;
+        }
+    }
+
+    eprintln!(
+        "processed {} files with {} errors",
+        num_processed, num_errors
+    );
+    eprintln!("{} unique pipelines found", rows.len());
+
+    Ok(())
+}
+
+fn handle_file(
+    path: &Path,
+    display_path: &str,
+    map: &mut HashMap<DedupedPipelineInfo, DedupedPipelineUsage>,
+) -> anyhow::Result<()> {
+    let file = std::fs::File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let xnb = Xnb::read(&mut reader)?;
+    let content = xnb.parse_content()?;
+
+    let mut add = |source: &'static str, vertex_declaration: &VertexDeclaration, effect: String| {
+        let key = DedupedPipelineInfo {
+            vertex_declaration: vertex_declaration.clone(),
+            effect,
+        };
+        let usage = map.entry(key).or_default();
+        usage.count += 1;
+        usage.sources.insert(source);
+        if usage.examples.len() < MAX_EXAMPLES {
+            usage.examples.insert(display_path.to_string());
+        }
+    };
+
+    match &content.primary_asset {
+        XnbAsset::Model(model) => {
+            add_model(&content, model, "Model", &mut add)?;
+        }
+        XnbAsset::BiTreeModel(model) => {
+            for tree in &model.trees {
+                add("BiTree", &tree.vertex_decl, tree.effect.as_ref().into());
+            }
+        }
+        XnbAsset::LevelModel(level_model) => {
+            for tree in &level_model.model.trees {
+                add("BiTree", &tree.vertex_decl, tree.effect.as_ref().into());
+            }
+
+            for part in &level_model.animated_parts {
+                add_animated_level_part(&content, part, &mut add)?;
+            }
+
+            for liquid in &level_model.liquids {
+                add_liquid(liquid, &mut add);
+            }
+
+            for force_field in &level_model.force_fields {
+                add(
+                    "ForceField",
+                    &force_field.vertex_declaration,
+                    "ForceField".into(),
+                );
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn add_model(
+    content: &XnbContent,
+    model: &Model,
+    source: &'static str,
+    add: &mut impl FnMut(&'static str, &VertexDeclaration, String),
+) -> anyhow::Result<()> {
+    for mesh in &model.meshes {
+        for part in &mesh.parts {
+            let Some(vertex_declaration) = model.vertex_decls.get(part.vertex_decl_index as usize)
+            else {
+                anyhow::bail!(
+                    "mesh part vertex declaration index {} is out of range",
+                    part.vertex_decl_index
+                );
+            };
+            let effect = (part.shared_content_material_index - 1)
+                .try_into()
+                .ok()
+                .and_then(|i: usize| content.shared_assets.get(i))
+                .map(|asset| asset.as_ref().to_string())
+                .unwrap_or_else(|| "Missing".into());
+            add(source, vertex_declaration, effect);
+        }
+    }
+
+    Ok(())
+}
+
+fn add_animated_level_part(
+    content: &XnbContent,
+    part: &AnimatedLevelPart,
+    add: &mut impl FnMut(&'static str, &VertexDeclaration, String),
+) -> anyhow::Result<()> {
+    add_model(content, &part.model, "AnimatedLevelPart", add)?;
+
+    for liquid in &part.liquids {
+        add_liquid(liquid, add);
+    }
+
+    for child in &part.children {
+        add_animated_level_part(content, child, add)?;
+    }
+
+    Ok(())
+}
+
+fn add_liquid(liquid: &Liquid, add: &mut impl FnMut(&'static str, &VertexDeclaration, String)) {
+    match liquid {
+        Liquid::Water(water) => add(
+            "Water",
+            &water.vertex_declaration,
+            "RenderDeferredLiquidEffect".into(),
+        ),
+        Liquid::Lava(_) => {}
+    }
+}
+
+/// writes `text` to `out`, or to stdout without one
+fn write_output(text: &str, out: Option<&Path>) -> anyhow::Result<()> {
+    if let Some(out) = out {
+        std::fs::write(out, text)?;
+    } else {
+        print!("{text}");
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Write as _,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use roxmltree::Document;
+
+use crate::{
+    content::ContentIndex,
+    dev::{catch_panic, has_extension},
+    vfs::{DirectoryVfs, Vfs},
+    vfx::{VisualEffectAsset, VisualEffectIndex},
+    xnb::{
+        Xnb,
+        asset::{
+            XnbAsset,
+            level_model::{AnimatedLevelPart, LevelModel, Liquid},
+            render_deferred_effect::RenderDeferredEffectMaterial,
+            render_deferred_liquid_effect::RenderDeferredLiquidEffect,
+        },
+    },
+};
+
+/// physics entity templates are referenced by name only, this is where the game keeps them
+const PHYSICS_ENTITY_DIR: &str = "Content/Data/PhysicsEntities";
+
+#[derive(Debug, Clone, Copy)]
+enum ReferenceKind {
+    Model,
+    SkyMap,
+    Texture,
+    VisualEffect,
+    PhysicsEntity,
+}
+
+struct Reference {
+    kind: ReferenceKind,
+    name: String,
+}
+
+struct Unresolved {
+    referrer: String,
+    kind: ReferenceKind,
+    name: String,
+    error: String,
+}
+
+#[derive(Default)]
+struct DependencyGraph {
+    edges: BTreeMap<String, BTreeSet<String>>,
+    /// files that were resolved but couldn't be read, so their own references are unknown
+    unreadable: BTreeMap<String, String>,
+    unresolved: Vec<Unresolved>,
+}
+
+/// walks every reference reachable from the level xml at `level_path` (relative to `magicka_path`),
+/// prints the transitive dependencies and any references that can't be resolved. visual effects
+/// also have to parse to count as resolved
+pub fn deps(magicka_path: &str, level_path: &Path, dot: Option<&Path>) -> anyhow::Result<()> {
+    let vfs = DirectoryVfs::new(magicka_path);
+    let visual_effects = VisualEffectIndex::build(&vfs)?;
+    // every path below is relative to the install, resolved through the index
+    let index = ContentIndex::build(&vfs)?;
+    let level_path = index
+        .resolve(level_path, None, None)
+        .ok_or_else(|| anyhow::anyhow!("unable to find path {}", level_path.display()))?;
+
+    let display = |path: &Path| path.to_string_lossy().replace('\\', "/");
+
+    let mut graph = DependencyGraph::default();
+    let mut visited = HashSet::from([level_path.clone()]);
+    let mut queue = vec![level_path.clone()];
+    // lowercase name -> why the effect can't be loaded, if it can't
+    let mut effects: HashMap<String, Option<String>> = HashMap::new();
+
+    while let Some(path) = queue.pop() {
+        let referrer = display(&path);
+        let references = match catch_panic(|| read_references(&vfs, &path)) {
+            Ok(references) => references,
+            Err(e) => {
+                graph.unreadable.insert(referrer, format!("{e:#}"));
+                continue;
+            }
+        };
+
+        for reference in references {
+            let resolved = match reference.kind {
+                ReferenceKind::VisualEffect => {
+                    let name = reference.name.to_ascii_lowercase();
+                    let error = effects.entry(name.clone()).or_insert_with(|| {
+                        catch_panic(|| read_visual_effect(&vfs, &visual_effects, &name))
+                            .err()
+                            .map(|e| format!("{e:#}"))
+                    });
+                    match error {
+                        None => Ok(format!("effect:{name}")),
+                        Some(error) => Err(anyhow::anyhow!("{error}")),
+                    }
+                }
+                kind => resolve_reference(&index, &path, kind, &reference.name).map(|resolved| {
+                    let node = display(&resolved);
+                    if visited.insert(resolved.clone()) {
+                        queue.push(resolved);
+                    }
+                    node
+                }),
+            };
+
+            match resolved {
+                Ok(node) => {
+                    graph
+                        .edges
+                        .entry(referrer.clone())
+                        .or_default()
+                        .insert(node);
+                }
+                Err(e) => graph.unresolved.push(Unresolved {
+                    referrer: referrer.clone(),
+                    kind: reference.kind,
+                    name: reference.name,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+    }
+
+    let root = display(&level_path);
+    let dependencies = graph
+        .edges
+        .values()
+        .flatten()
+        .filter(|node| **node != root)
+        .collect::<BTreeSet<_>>();
+
+    println!("dependencies of {root}:");
+    for dependency in &dependencies {
+        println!("  {dependency}");
+    }
+
+    if !graph.unreadable.is_empty() {
+        println!("unreadable files:");
+        for (path, error) in &graph.unreadable {
+            println!("  {path}: {error}");
+        }
+    }
+
+    if !graph.unresolved.is_empty() {
+        println!("unresolved references:");
+        for unresolved in &graph.unresolved {
+            println!(
+                "  {}: {:?} '{}': {}",
+                unresolved.referrer, unresolved.kind, unresolved.name, unresolved.error
+            );
+        }
+    }
+
+    eprintln!(
+        "{} dependencies, {} unreadable files, {} unresolved references",
+        dependencies.len(),
+        graph.unreadable.len(),
+        graph.unresolved.len()
+    );
+
+    if let Some(dot) = dot {
+        std::fs::write(dot, to_dot(&graph))?;
+    }
+
+    Ok(())
+}
+
+/// `referrer` and the result are relative to the install
+fn resolve_reference(
+    index: &ContentIndex,
+    referrer: &Path,
+    kind: ReferenceKind,
+    name: &str,
+) -> anyhow::Result<PathBuf> {
+    let (path, base, extension) = match kind {
+        // level xml paths are relative to the level xml, paths inside xnbs are relative to the
+        // xnb. the index accepts the windows separators the latter use
+        ReferenceKind::Model | ReferenceKind::SkyMap | ReferenceKind::Texture => {
+            (PathBuf::from(name), Some(referrer), "xnb")
+        }
+        ReferenceKind::PhysicsEntity => (Path::new(PHYSICS_ENTITY_DIR).join(name), None, "xml"),
+        ReferenceKind::VisualEffect => unreachable!("visual effects are looked up by name"),
+    };
+    index
+        .resolve(&path, base, Some(extension))
+        .ok_or_else(|| anyhow::anyhow!("unable to find path {}", path.display()))
+}
+
+/// parses the effect the way `AssetLoader::load_visual_effect` does, effects don't reference
+/// anything themselves
+fn read_visual_effect(vfs: &dyn Vfs, index: &VisualEffectIndex, name: &str) -> anyhow::Result<()> {
+    let Some(path) = index.get(name) else {
+        anyhow::bail!("visual effect '{name}' not found");
+    };
+    let xml = vfs.read_to_string(path)?;
+    VisualEffectAsset::read_xml(&xml)
+        .with_context(|| format!("failed to read visual effect at path {}", path.display()))?;
+    Ok(())
+}
+
+/// returns the direct references of a single file. files that don't reference anything are leaves
+fn read_references(vfs: &dyn Vfs, path: &Path) -> anyhow::Result<Vec<Reference>> {
+    let mut references = Vec::new();
+
+    if has_extension(path, &["xml"]) {
+        let xml = vfs.read_to_string(path)?;
+        let doc = Document::parse(&xml)?;
+        let root = doc.root_element();
+
+        // only level xmls are understood for now, other xml files are leaves
+        if root.tag_name().name() == "Scene" {
+            for child in root.children().filter(|child| child.is_element()) {
+                let kind = match child.tag_name().name().to_lowercase().as_str() {
+                    "model" => ReferenceKind::Model,
+                    "skymap" => ReferenceKind::SkyMap,
+                    _ => continue,
+                };
+                if let Some(text) = child.text() {
+                    push_reference(&mut references, kind, text);
+                }
+            }
+        }
+    } else if has_extension(path, &["xnb"]) {
+        let xnb = Xnb::read(&mut Cursor::new(vfs.read(path)?))?;
+        let content = xnb.parse_content()?;
+
+        for asset in std::iter::once(&content.primary_asset).chain(&content.shared_assets) {
+            collect_asset_references(asset, &mut references);
+        }
+    }
+
+    Ok(references)
+}
+
+fn collect_asset_references(asset: &XnbAsset, references: &mut Vec<Reference>) {
+    match asset {
+        XnbAsset::RenderDeferredEffect(effect) => {
+            push_reference(references, ReferenceKind::Texture, &effect.reflection_map);
+            collect_material_references(&effect.material_0, references);
+            if let Some(material_1) = &effect.material_1 {
+                collect_material_references(material_1, references);
+            }
+        }
+        XnbAsset::AdditiveEffect(effect) if effect.texture_enabled => {
+            push_reference(references, ReferenceKind::Texture, &effect.texture);
+        }
+        XnbAsset::RenderDeferredLiquidEffect(effect) => {
+            collect_liquid_effect_references(effect, references);
+        }
+        XnbAsset::BiTreeModel(model) => {
+            for tree in &model.trees {
+                collect_asset_references(&tree.effect, references);
+            }
+        }
+        XnbAsset::LevelModel(level_model) => {
+            collect_level_model_references(level_model, references);
+        }
+        _ => {}
+    }
+}
+
+fn collect_material_references(
+    material: &RenderDeferredEffectMaterial,
+    references: &mut Vec<Reference>,
+) {
+    for texture in [
+        &material.diffuse_texture,
+        &material.material_texture,
+        &material.normal_texture,
+    ] {
+        push_reference(references, ReferenceKind::Texture, texture);
+    }
+}
+
+fn collect_liquid_effect_references(
+    effect: &RenderDeferredLiquidEffect,
+    references: &mut Vec<Reference>,
+) {
+    for texture in [
+        &effect.reflection_map,
+        &effect.bottom_texture,
+        &effect.water_normal_map,
+        &effect.ice_diffuse_map,
+        &effect.ice_normal_map,
+    ] {
+        push_reference(references, ReferenceKind::Texture, texture);
+    }
+}
+
+fn collect_level_model_references(level_model: &LevelModel, references: &mut Vec<Reference>) {
+    for tree in &level_model.model.trees {
+        collect_asset_references(&tree.effect, references);
+    }
+
+    for effect_storage in &level_model.effect_storages {
+        push_reference(
+            references,
+            ReferenceKind::VisualEffect,
+            &effect_storage.effect,
+        );
+    }
+
+    for physics_entity in &level_model.physics_entity_storages {
+        push_reference(
+            references,
+            ReferenceKind::PhysicsEntity,
+            &physics_entity.template,
+        );
+    }
+
+    for liquid in &level_model.liquids {
+        collect_liquid_references(liquid, references);
+    }
+
+    for force_field in &level_model.force_fields {
+        push_reference(
+            references,
+            ReferenceKind::Texture,
+            &force_field.displacement_map,
+        );
+    }
+
+    for part in &level_model.animated_parts {
+        collect_animated_level_part_references(part, references);
+    }
+}
+
+/// animated part model effects are stored in the shared assets, so only the part specific data is walked here
+fn collect_animated_level_part_references(
+    part: &AnimatedLevelPart,
+    references: &mut Vec<Reference>,
+) {
+    for effect_storage in &part.effect_storages {
+        push_reference(
+            references,
+            ReferenceKind::VisualEffect,
+            &effect_storage.effect,
+        );
+    }
+
+    for liquid in &part.liquids {
+        collect_liquid_references(liquid, references);
+    }
+
+    for child in &part.children {
+        collect_animated_level_part_references(child, references);
+    }
+}
+
+fn collect_liquid_references(liquid: &Liquid, references: &mut Vec<Reference>) {
+    match liquid {
+        Liquid::Water(water) => collect_liquid_effect_references(&water.effect, references),
+        Liquid::Lava(_) => {}
+    }
+}
+
+/// empty strings are used for unset textures
+fn push_reference(references: &mut Vec<Reference>, kind: ReferenceKind, name: &str) {
+    let name = name.trim();
+    if !name.is_empty() {
+        references.push(Reference {
+            kind,
+            name: name.to_string(),
+        });
+    }
+}
+
+fn to_dot(graph: &DependencyGraph) -> String {
+    let mut dot = String::from("digraph deps {\n    rankdir=LR;\n    node [shape=box];\n");
+
+    for (from, tos) in &graph.edges {
+        for to in tos {
+            writeln!(dot, "    {} -> {};", dot_id(from), dot_id(to)).unwrap();
+        }
+    }
+
+    for path in graph.unreadable.keys() {
+        writeln!(dot, "    {} [color=orange];", dot_id(path)).unwrap();
+    }
+
+    for (i, unresolved) in graph.unresolved.iter().enumerate() {
+        let label = format!("{:?} {}", unresolved.kind, unresolved.name);
+        writeln!(
+            dot,
+            "    missing{i} [label={}, color=red, style=dashed];\n    {} -> missing{i} [color=red];",
+            dot_id(&label),
+            dot_id(&unresolved.referrer)
+        )
+        .unwrap();
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// a quoted dot id. only '"' and '\' are escaped, rust's `{:?}` also turns control and
+/// unprintable characters into `\u{..}` escapes that dot doesn't understand
+fn dot_id(s: &str) -> String {
+    let mut id = String::with_capacity(s.len() + 2);
+    id.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            id.push('\\');
+        }
+        id.push(c);
+    }
+    id.push('"');
+    id
+}
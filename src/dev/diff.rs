@@ -0,0 +1,188 @@
+use std::{io::BufReader, path::Path};
+
+use serde_json::Value;
+
+use crate::xnb::{Xnb, XnbContent};
+
+pub struct DiffOptions {
+    /// numbers closer than this are considered equal
+    pub tolerance: f64,
+    /// numeric lists and strings longer than this are summarized instead of compared element by element
+    pub max_inline_len: usize,
+}
+
+/// parses two xnb files and prints the field level differences between their asset trees
+pub fn diff(path_a: &Path, path_b: &Path, options: &DiffOptions) -> anyhow::Result<()> {
+    let a = serde_json::to_value(load_content(path_a)?)?;
+    let b = serde_json::to_value(load_content(path_b)?)?;
+
+    let mut differences = Vec::new();
+    diff_values("", &a, &b, options, &mut differences);
+
+    for difference in &differences {
+        println!("{difference}");
+    }
+
+    eprintln!("{} differences found", differences.len());
+
+    Ok(())
+}
+
+fn load_content(path: &Path) -> anyhow::Result<XnbContent> {
+    let file = std::fs::File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let xnb = Xnb::read(&mut reader)?;
+    xnb.parse_content()
+}
+
+fn diff_values(path: &str, a: &Value, b: &Value, options: &DiffOptions, out: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Number(na), Value::Number(nb)) => {
+            let (fa, fb) = (na.as_f64().unwrap(), nb.as_f64().unwrap());
+            if (fa - fb).abs() > options.tolerance {
+                out.push(format!(
+                    "{path}: {} -> {}",
+                    format_number(fa),
+                    format_number(fb)
+                ));
+            }
+        }
+        (Value::String(sa), Value::String(sb)) => {
+            if sa == sb {
+                return;
+            }
+
+            if sa.len() > options.max_inline_len || sb.len() > options.max_inline_len {
+                out.push(format!("{path}: {}", summarize_buffer_diff(sa, sb)));
+            } else {
+                out.push(format!("{path}: {a} -> {b}"));
+            }
+        }
+        (Value::Array(va), Value::Array(vb)) => {
+            let is_numeric = va.iter().chain(vb.iter()).all(Value::is_number);
+            if is_numeric
+                && (va.len() > options.max_inline_len || vb.len() > options.max_inline_len)
+            {
+                let num_different = va
+                    .iter()
+                    .zip(vb.iter())
+                    .filter(|(a, b)| {
+                        (a.as_f64().unwrap() - b.as_f64().unwrap()).abs() > options.tolerance
+                    })
+                    .count();
+                if num_different > 0 || va.len() != vb.len() {
+                    out.push(format!(
+                        "{path}: {} elements -> {} elements, {num_different} differ",
+                        va.len(),
+                        vb.len()
+                    ));
+                }
+                return;
+            }
+
+            if va.len() != vb.len() {
+                out.push(format!("{path}: length {} -> {}", va.len(), vb.len()));
+            }
+
+            for (i, (ea, eb)) in va.iter().zip(vb.iter()).enumerate() {
+                diff_values(&format!("{path}[{i}]"), ea, eb, options, out);
+            }
+
+            for (i, removed) in va.iter().enumerate().skip(vb.len()) {
+                out.push(format!("{path}[{i}]: removed {}", summarize(removed)));
+            }
+            for (i, added) in vb.iter().enumerate().skip(va.len()) {
+                out.push(format!("{path}[{i}]: added {}", summarize(added)));
+            }
+        }
+        (Value::Object(oa), Value::Object(ob)) => {
+            // enums are serialized as single key objects, report a variant change as one difference
+            if oa.len() == 1 && ob.len() == 1 {
+                let (ka, _) = oa.iter().next().unwrap();
+                let (kb, _) = ob.iter().next().unwrap();
+                if ka != kb && ka.starts_with(char::is_uppercase) {
+                    out.push(format!("{path}: {ka} -> {kb}"));
+                    return;
+                }
+            }
+
+            for (key, va) in oa {
+                let field_path = join_path(path, key);
+                match ob.get(key) {
+                    Some(vb) => diff_values(&field_path, va, vb, options, out),
+                    None => out.push(format!("{field_path}: removed {}", summarize(va))),
+                }
+            }
+            for (key, vb) in ob {
+                if !oa.contains_key(key) {
+                    let field_path = join_path(path, key);
+                    out.push(format!("{field_path}: added {}", summarize(vb)));
+                }
+            }
+        }
+        _ => {
+            if a != b {
+                out.push(format!("{path}: {} -> {}", summarize(a), summarize(b)));
+            }
+        }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// most numbers in xnb files are `f32`, print them without the noise from widening to `f64`
+fn format_number(n: f64) -> String {
+    let narrow = n as f32;
+    if narrow as f64 == n {
+        format!("{narrow:?}")
+    } else {
+        format!("{n:?}")
+    }
+}
+
+fn summarize(value: &Value) -> String {
+    match value {
+        Value::Array(v) => format!("<list of {} elements>", v.len()),
+        Value::Object(v) => format!("<object with {} fields>", v.len()),
+        Value::String(s) if s.len() > 64 => format!("<string of {} chars>", s.len()),
+        other => other.to_string(),
+    }
+}
+
+/// buffers are serialized as hex strings, so compare them byte by byte
+fn summarize_buffer_diff(a: &str, b: &str) -> String {
+    let is_hex = |s: &str| s.len().is_multiple_of(2) && s.bytes().all(|c| c.is_ascii_hexdigit());
+    if is_hex(a) && is_hex(b) {
+        let (bytes_a, bytes_b) = (a.as_bytes().chunks(2), b.as_bytes().chunks(2));
+        let mut num_different = 0;
+        let mut first_different = None;
+        for (i, (ba, bb)) in bytes_a.zip(bytes_b).enumerate() {
+            if ba != bb {
+                num_different += 1;
+                first_different.get_or_insert(i);
+            }
+        }
+
+        let mut summary = format!(
+            "buffer of {} bytes -> {} bytes, {num_different} bytes differ",
+            a.len() / 2,
+            b.len() / 2
+        );
+        if let Some(first) = first_different {
+            summary.push_str(&format!(" (first at offset {first:#x})"));
+        }
+        summary
+    } else {
+        format!(
+            "<string of {} chars> -> <string of {} chars>",
+            a.len(),
+            b.len()
+        )
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use roxmltree::Document;
+
+use crate::{
+    dev::collect_files,
+    vfx::{VisualEffectAsset, escape_malformed_references},
+};
+
+pub struct FixEffectsOptions {
+    /// also rewrite files that already parse, so every effect ends up in the same normalized form
+    pub all: bool,
+    /// write rewritten effects under this directory, at the same path relative to Content/Effects
+    pub output: Option<PathBuf>,
+    /// overwrite the effects of the install, keeping the original next to each as `.bak`
+    pub in_place: bool,
+}
+
+impl FixEffectsOptions {
+    /// nothing is written unless asked for
+    fn dry_run(&self) -> bool {
+        self.output.is_none() && !self.in_place
+    }
+}
+
+/// rewrites effect xml that only loads after `read_xml` repairs it (such as malformed entity
+/// references) into valid xml. every rewrite is parsed again and compared to the original
+/// effect, and is refused if it leaves out any element, attribute, text or comment of the
+/// original, since `write_xml` only writes what the parser kept
+pub fn fix_effects(magicka_path: &str, options: &FixEffectsOptions) -> anyhow::Result<()> {
+    let effects_path = Path::new(magicka_path).join("Content/Effects");
+    let files = collect_files(&effects_path, &["xml"])?;
+
+    let mut fixed = 0;
+    let mut normalized = 0;
+    let mut failed = 0;
+
+    for path in &files {
+        let relative = path.strip_prefix(&effects_path).unwrap_or(path);
+
+        let result = (|| -> anyhow::Result<Option<bool>> {
+            let xml = std::fs::read_to_string(path)?;
+            let broken = roxmltree::Document::parse(&xml).is_err();
+            if !broken && !options.all {
+                return Ok(None);
+            }
+
+            let effect = VisualEffectAsset::read_xml(&xml)?;
+            let rewritten = effect.write_xml();
+            if rewritten == xml {
+                return Ok(None);
+            }
+
+            let reparsed = VisualEffectAsset::read_xml(&rewritten)?;
+            if reparsed != effect {
+                anyhow::bail!("rewritten effect does not match the original, leaving it alone");
+            }
+
+            let lost = lost_content(&xml, &rewritten)?;
+            if !lost.is_empty() {
+                anyhow::bail!("rewriting would lose {}, leaving it alone", lost.join(", "));
+            }
+
+            if let Some(output) = &options.output {
+                let out_path = output.join(relative);
+                if let Some(parent) = out_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(out_path, rewritten)?;
+            } else if options.in_place {
+                std::fs::copy(path, path.with_extension("xml.bak"))?;
+                std::fs::write(path, rewritten)?;
+            }
+            Ok(Some(broken))
+        })();
+
+        match result {
+            Ok(Some(true)) => {
+                println!("fixed {}", relative.display());
+                fixed += 1;
+            }
+            Ok(Some(false)) => {
+                println!("normalized {}", relative.display());
+                normalized += 1;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                println!("failed {}: {e:#}", relative.display());
+                failed += 1;
+            }
+        }
+    }
+
+    let verb = if options.dry_run() {
+        "would rewrite"
+    } else {
+        "rewrote"
+    };
+    eprintln!(
+        "{verb} {} of {} effects ({fixed} fixed, {normalized} normalized), {failed} could not be fixed",
+        fixed + normalized,
+        files.len()
+    );
+
+    Ok(())
+}
+
+/// what `source` has that `rewritten` doesn't, as element paths with the attribute, `#text` or
+/// `#comment` they lost. elements and attributes are compared ignoring case and values aren't
+/// compared at all, those are checked by comparing the parsed effects
+fn lost_content(source: &str, rewritten: &str) -> anyhow::Result<Vec<String>> {
+    let escaped;
+    let source = match Document::parse(source) {
+        Ok(doc) => doc,
+        Err(roxmltree::Error::MalformedEntityReference(_)) => {
+            escaped = escape_malformed_references(source);
+            Document::parse(&escaped)?
+        }
+        Err(e) => Err(e)?,
+    };
+    let rewritten = Document::parse(rewritten)?;
+
+    let source = content_counts(&source);
+    let rewritten = content_counts(&rewritten);
+    let lost = source
+        .into_iter()
+        .filter_map(|(key, count)| {
+            let missing = count.saturating_sub(rewritten.get(&key).copied().unwrap_or(0));
+            (missing > 0).then(|| format!("{missing}x {key}"))
+        })
+        .collect();
+    Ok(lost)
+}
+
+/// how many times each element, attribute, non-blank text and comment appears, keyed by where
+fn content_counts(doc: &Document) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for node in doc.descendants() {
+        let mut path: Vec<String> = node
+            .ancestors()
+            .filter(|n| n.is_element())
+            .map(|n| n.tag_name().name().to_ascii_lowercase())
+            .collect();
+        path.reverse();
+        let element = path.join("/");
+
+        let mut keys = Vec::new();
+        if node.is_element() {
+            keys.push(format!("<{element}>"));
+            for attribute in node.attributes() {
+                keys.push(format!(
+                    "<{element}> '{}'",
+                    attribute.name().to_ascii_lowercase()
+                ));
+            }
+        } else if node.is_text() && node.text().is_some_and(|text| !text.trim().is_empty()) {
+            keys.push(format!("<{element}> #text"));
+        } else if node.is_comment() {
+            keys.push(format!("<{element}> #comment"));
+        }
+
+        for key in keys {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    counts
+}
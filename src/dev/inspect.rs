@@ -0,0 +1,181 @@
+use std::{
+    io::{BufReader, Cursor, IsTerminal},
+    path::Path,
+};
+
+use crate::{
+    dev::catch_panic,
+    xnb::{
+        Xnb,
+        trace::{self, Span, TraceReader},
+    },
+};
+
+const BYTES_PER_LINE: usize = 16;
+
+pub struct InspectOptions {
+    /// leaf fields spanning more lines than this are elided in the middle
+    pub max_lines: usize,
+}
+
+/// prints the decompressed content stream of an xnb as a hex dump annotated with the fields
+/// the parsers consumed, followed by any bytes that weren't consumed
+pub fn inspect(path: &Path, options: &InspectOptions) -> anyhow::Result<()> {
+    let file = std::fs::File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let xnb = Xnb::read(&mut reader)?;
+    let data = xnb.decompress()?;
+
+    println!(
+        "{:?} {:?}, compressed: {}, {} bytes of content",
+        xnb.header.platform,
+        xnb.header.version,
+        xnb.header.compressed,
+        data.len()
+    );
+
+    let (result, trace) = trace::record(|| {
+        catch_panic(|| {
+            let mut reader = TraceReader::new(Cursor::new(data.as_ref()));
+            Xnb::read_content(&mut reader)
+        })
+    });
+
+    let printer = Printer {
+        data: &data,
+        max_lines: options.max_lines,
+        color: std::io::stdout().is_terminal(),
+    };
+
+    let mut cursor = 0;
+    for (i, span) in trace.spans.iter().enumerate() {
+        let end = span.end.unwrap_or(trace.position) as usize;
+        let start = span.start as usize;
+
+        if !span.is_leaf(trace.spans.get(i + 1)) {
+            let incomplete = if span.end.is_none() {
+                " (incomplete)"
+            } else {
+                ""
+            };
+            println!(
+                "{start:08x}  {:width$}  {}{}: {}{incomplete}",
+                "",
+                indent(span.depth),
+                span.name,
+                span.type_name,
+                width = BYTES_PER_LINE * 3 - 1,
+            );
+            continue;
+        }
+
+        if start > cursor {
+            printer.print_bytes(cursor, start, 0, "<untraced>", false);
+        }
+        printer.print_bytes(start, end, span.depth, &leaf_label(span), false);
+        cursor = cursor.max(end);
+    }
+
+    let position = trace.position as usize;
+    if position > cursor {
+        printer.print_bytes(cursor, position, 0, "<untraced>", false);
+    }
+
+    match result {
+        Ok(_) if position == data.len() => {
+            eprintln!("all {} bytes consumed", data.len());
+        }
+        Ok(_) => {
+            printer.print_bytes(position, data.len(), 0, "<unparsed>", true);
+            eprintln!(
+                "{} trailing bytes were not consumed by the parser",
+                data.len() - position
+            );
+        }
+        Err(e) => {
+            printer.print_bytes(position, data.len(), 0, "<unparsed>", true);
+            eprintln!("parsing failed at offset {position:#x}: {e:#}");
+        }
+    }
+
+    Ok(())
+}
+
+struct Printer<'a> {
+    data: &'a [u8],
+    max_lines: usize,
+    color: bool,
+}
+
+impl Printer<'_> {
+    fn print_bytes(&self, start: usize, end: usize, depth: usize, label: &str, highlight: bool) {
+        let end = end.min(self.data.len());
+        if start >= end {
+            println!(
+                "{start:08x}  {:width$}  {}{label}",
+                "",
+                indent(depth),
+                width = BYTES_PER_LINE * 3 - 1
+            );
+            return;
+        }
+
+        let lines = self.data[start..end]
+            .chunks(BYTES_PER_LINE)
+            .enumerate()
+            .collect::<Vec<_>>();
+        // unparsed bytes are always printed in full, they're what you're here to look at
+        let elide = !highlight && lines.len() > self.max_lines;
+
+        for (i, chunk) in &lines {
+            if elide && *i >= self.max_lines.saturating_sub(1) && *i + 1 < lines.len() {
+                if *i == self.max_lines.saturating_sub(1) {
+                    let hidden = lines.len() - self.max_lines;
+                    println!("{:8}  ... {hidden} more lines", "");
+                }
+                continue;
+            }
+
+            let offset = start + i * BYTES_PER_LINE;
+            let hex = chunk
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let hex = if highlight && self.color {
+                format!(
+                    "\x1b[31m{hex:width$}\x1b[0m",
+                    width = BYTES_PER_LINE * 3 - 1
+                )
+            } else {
+                format!("{hex:width$}", width = BYTES_PER_LINE * 3 - 1)
+            };
+
+            if *i == 0 {
+                println!("{offset:08x}  {hex}  {}{label}", indent(depth));
+            } else {
+                println!("{offset:08x}  {hex}");
+            }
+        }
+    }
+}
+
+fn leaf_label(span: &Span) -> String {
+    let mut label = format!("{}: {}", span.name, span.type_name);
+    if let Some(value) = &span.value {
+        label.push_str(&format!(" = {value}"));
+    }
+    if let Some(end) = span.end {
+        let len = end - span.start;
+        if len > BYTES_PER_LINE as u64 {
+            label.push_str(&format!(" ({len} bytes)"));
+        }
+    } else {
+        label.push_str(" (failed)");
+    }
+    label
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
@@ -0,0 +1,419 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use roxmltree::{Document, Node, TextPos};
+
+use crate::{dev::collect_files, vfx::escape_malformed_references};
+
+/// animatable properties any emitter can have
+const PROPERTY_TAGS: &[&str] = &[
+    "LifeTimeMin",
+    "LifeTimeMax",
+    "LifeTimeDistribution",
+    "SpreadArcHorizontalAngle",
+    "SpreadArcHorizontalDistribution",
+    "SpreadArcVerticalMin",
+    "SpreadArcVerticalMax",
+    "SpreadArcVerticalDistribution",
+    "SpreadConeAngle",
+    "SpreadConeDistribution",
+    "PositionX",
+    "PositionY",
+    "PositionZ",
+    "PositionXOffset",
+    "PositionYOffset",
+    "PositionZOffset",
+    "VelocityMin",
+    "VelocityMax",
+    "VelocityDistribution",
+    "Drag",
+    "Gravity",
+    "RotationMin",
+    "RotationMax",
+    "RotationSpeedMin",
+    "RotationSpeedMax",
+    "RotationPCCW",
+    "SizeStartMin",
+    "SizeStartMax",
+    "SizeStartDistribution",
+    "SizeEndMin",
+    "SizeEndMax",
+    "SizeEndDistribution",
+    "HueMin",
+    "HueMax",
+    "HueDistribution",
+    "SatMin",
+    "SatMax",
+    "SatDistribution",
+    "ValueMin",
+    "ValueMax",
+    "ValueDistribution",
+    "AlphaMin",
+    "AlphaMax",
+    "AlphaDistribution",
+];
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Severity {
+    /// the file won't load
+    Error,
+    /// the file loads but probably doesn't do what its author intended
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+struct Diagnostic {
+    pos: TextPos,
+    severity: Severity,
+    message: String,
+}
+
+/// checks every effect under Content/Effects against the schema `VisualEffectAsset::read_xml`
+/// expects and prints every problem found, instead of stopping at the first one like the parser
+pub fn lint_effects(magicka_path: &str) -> anyhow::Result<()> {
+    let effects_path = Path::new(magicka_path).join("Content/Effects");
+    let files = collect_files(&effects_path, &["xml"])?;
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    let mut files_with_problems = 0;
+
+    for path in &files {
+        let relative = path.strip_prefix(&effects_path).unwrap_or(path);
+        let diagnostics = match std::fs::read_to_string(path) {
+            Ok(xml) => lint_effect(&xml),
+            Err(e) => vec![Diagnostic {
+                pos: TextPos::new(1, 1),
+                severity: Severity::Error,
+                message: format!("unable to read file: {e}"),
+            }],
+        };
+
+        if !diagnostics.is_empty() {
+            files_with_problems += 1;
+        }
+
+        for diagnostic in diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            println!(
+                "{}:{}:{}: {}: {}",
+                relative.display(),
+                diagnostic.pos.row,
+                diagnostic.pos.col,
+                diagnostic.severity,
+                diagnostic.message
+            );
+        }
+    }
+
+    eprintln!(
+        "checked {} effects: {errors} errors and {warnings} warnings in {files_with_problems} files",
+        files.len()
+    );
+
+    Ok(())
+}
+
+fn lint_effect(xml: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    // mirror the retry in `read_xml` so the rest of the file still gets checked
+    let escaped;
+    let doc = match Document::parse(xml) {
+        Ok(doc) => doc,
+        Err(e @ roxmltree::Error::MalformedEntityReference(_)) => {
+            diagnostics.push(Diagnostic {
+                pos: e.pos(),
+                severity: Severity::Warning,
+                message: format!("{e}, it's escaped when loading"),
+            });
+            escaped = escape_malformed_references(xml);
+            match Document::parse(&escaped) {
+                Ok(doc) => doc,
+                Err(e) => {
+                    diagnostics.push(Diagnostic {
+                        pos: e.pos(),
+                        severity: Severity::Error,
+                        message: e.to_string(),
+                    });
+                    return diagnostics;
+                }
+            }
+        }
+        Err(e) => {
+            diagnostics.push(Diagnostic {
+                pos: e.pos(),
+                severity: Severity::Error,
+                message: e.to_string(),
+            });
+            return diagnostics;
+        }
+    };
+
+    let mut linter = Linter {
+        doc: &doc,
+        diagnostics,
+    };
+    linter.effect(doc.root_element());
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| (d.pos.row, d.pos.col, d.severity));
+    diagnostics
+}
+
+struct Linter<'a, 'input> {
+    doc: &'a Document<'input>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_, '_> {
+    fn report(&mut self, offset: usize, severity: Severity, message: String) {
+        self.diagnostics.push(Diagnostic {
+            pos: self.doc.text_pos_at(offset),
+            severity,
+            message,
+        });
+    }
+
+    fn error(&mut self, node: Node, message: String) {
+        self.report(node.range().start, Severity::Error, message);
+    }
+
+    fn warning(&mut self, node: Node, message: String) {
+        self.report(node.range().start, Severity::Warning, message);
+    }
+
+    /// checks that `node` has the attribute `name` and that it parses as `T`
+    fn attribute<T: std::str::FromStr>(&mut self, node: Node, name: &str) -> Option<T> {
+        let tag_name = node.tag_name().name();
+        // same as the parser, only the casing of 'value' on properties varies between files
+        let ignore_case = name == "value" && tag_name != "Key";
+        let Some(attr) = node.attributes().find(|attr| {
+            attr.name() == name || (ignore_case && attr.name().eq_ignore_ascii_case(name))
+        }) else {
+            self.error(
+                node,
+                format!("<{tag_name}> is missing the required '{name}' attribute"),
+            );
+            return None;
+        };
+
+        let value = attr.value();
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.report(
+                    attr.range_value().start,
+                    Severity::Error,
+                    format!("<{tag_name}> '{name}' has an invalid value '{value}'"),
+                );
+                None
+            }
+        }
+    }
+
+    /// like `attribute`, for values that must be one of `allowed` (case-insensitive)
+    fn keyword(&mut self, node: Node, allowed: &[&str]) {
+        let Some(value) = self.attribute::<String>(node, "value") else {
+            return;
+        };
+        if !allowed.iter().any(|a| a.eq_ignore_ascii_case(&value)) {
+            self.error(
+                node,
+                format!(
+                    "<{}> value '{value}' should be one of {}",
+                    node.tag_name().name(),
+                    allowed.join(", ")
+                ),
+            );
+        }
+    }
+
+    fn effect(&mut self, root: Node) {
+        if root.tag_name().name() != "Effect" {
+            self.error(
+                root,
+                format!(
+                    "expected the root element to be <Effect>, got <{}>",
+                    root.tag_name().name()
+                ),
+            );
+            return;
+        }
+
+        if let Some(kind) = self.attribute::<String>(root, "type")
+            && !matches!(kind.as_str(), "Single" | "Looping" | "Infinite")
+        {
+            self.error(
+                root,
+                format!("<Effect> type '{kind}' should be one of Single, Looping, Infinite"),
+            );
+        }
+        if let Some(duration) = self.attribute::<f32>(root, "duration")
+            && duration < 0.0
+        {
+            self.warning(root, format!("<Effect> has a negative duration {duration}"));
+        }
+        self.attribute::<u32>(root, "keyFramesPerSecond");
+
+        for child in root.children().filter(|n| n.is_element()) {
+            match child.tag_name().name() {
+                "ContinuousEmitter" | "PulseEmitter" => self.emitter(child),
+                other => self.error(
+                    child,
+                    format!(
+                        "unknown tag <{other}>, expected <ContinuousEmitter> or <PulseEmitter>"
+                    ),
+                ),
+            }
+        }
+    }
+
+    fn emitter(&mut self, node: Node) {
+        let tag_name = node.tag_name().name();
+        let is_pulse = tag_name == "PulseEmitter";
+        self.attribute::<String>(node, "name");
+
+        let mut seen: HashMap<&str, Node> = HashMap::new();
+        for child in node.children().filter(|n| n.is_element()) {
+            let child_name = child.tag_name().name();
+            if let Some(previous) = seen.insert(child_name, child) {
+                let pos = self.doc.text_pos_at(previous.range().start);
+                self.warning(
+                    child,
+                    format!(
+                        "<{child_name}> is repeated, the one at {}:{} is ignored",
+                        pos.row, pos.col
+                    ),
+                );
+            }
+
+            match child_name {
+                "BlendMode" => self.keyword(child, &["Additive", "Alpha"]),
+                "SpreadType" => self.keyword(child, &["Arc", "Cone"]),
+                "HSV" | "ColorControlAlpha" | "Colorize" => self.keyword(child, &["true", "false"]),
+                "Particle" => {
+                    self.attribute::<u8>(child, "value");
+                }
+                "NrOfParticles" if is_pulse => {
+                    self.attribute::<u32>(child, "value");
+                }
+                "ParticlesPerSecond" if !is_pulse => self.property(child),
+                "NrOfParticles" | "ParticlesPerSecond" => {
+                    self.warning(child, format!("<{child_name}> is ignored in <{tag_name}>"));
+                }
+                _ if PROPERTY_TAGS.contains(&child_name) => self.property(child),
+                _ => self.warning(child, format!("unknown tag <{child_name}> in <{tag_name}>")),
+            }
+        }
+
+        let kind_specific = if is_pulse {
+            "NrOfParticles"
+        } else {
+            "ParticlesPerSecond"
+        };
+        for required in ["BlendMode", "SpreadType", "Particle", kind_specific] {
+            if !seen.contains_key(required) {
+                self.error(
+                    node,
+                    format!("<{tag_name}> is missing the required <{required}>"),
+                );
+            }
+        }
+    }
+
+    /// a constant `value` attribute or a list of <Key> children
+    fn property(&mut self, node: Node) {
+        let tag_name = node.tag_name().name();
+        let has_value = node
+            .attributes()
+            .any(|attr| attr.name().eq_ignore_ascii_case("value"));
+        if has_value {
+            if let Some(value) = self.attribute::<f32>(node, "value") {
+                self.check_range(node, value);
+            }
+            return;
+        }
+
+        let mut times: HashMap<i32, Node> = HashMap::new();
+        for child in node.children().filter(|n| n.is_element()) {
+            if child.tag_name().name() != "Key" {
+                self.warning(
+                    child,
+                    format!(
+                        "unknown tag <{}> in <{tag_name}>, expected <Key>",
+                        child.tag_name().name()
+                    ),
+                );
+                continue;
+            }
+
+            let time = self.attribute::<i32>(child, "time");
+            if let Some(value) = self.attribute::<f32>(child, "value") {
+                self.check_range(child, value);
+            }
+
+            let Some(time) = time else {
+                continue;
+            };
+            if time < 0 {
+                self.warning(
+                    child,
+                    format!("<{tag_name}> has a negative keyframe time {time}"),
+                );
+            }
+            if let Some(previous) = times.insert(time, child) {
+                let pos = self.doc.text_pos_at(previous.range().start);
+                self.warning(
+                    child,
+                    format!(
+                        "<{tag_name}> has a duplicate keyframe time {time}, also used at {}:{}",
+                        pos.row, pos.col
+                    ),
+                );
+            }
+        }
+
+        if times.is_empty()
+            && !node
+                .children()
+                .any(|n| n.is_element() && n.tag_name().name() == "Key")
+        {
+            self.error(
+                node,
+                format!("<{tag_name}> needs a 'value' attribute or <Key> children"),
+            );
+        }
+    }
+
+    fn check_range(&mut self, node: Node, value: f32) {
+        let name = node
+            .ancestors()
+            .find(|n| n.tag_name().name() != "Key")
+            .map(|n| n.tag_name().name())
+            .unwrap_or_default();
+        match name {
+            "LifeTimeMin" | "LifeTimeMax" if value < 0.0 => {
+                self.warning(node, format!("<{name}> has a negative lifetime {value}"));
+            }
+            // stored as a percentage, the simulation divides it by 100
+            "RotationPCCW" if !(0.0..=100.0).contains(&value) => {
+                self.warning(
+                    node,
+                    format!("<{name}> chance {value} is outside of 0 to 100 percent"),
+                );
+            }
+            _ => {}
+        }
+    }
+}
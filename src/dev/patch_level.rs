@@ -0,0 +1,282 @@
+use std::{
+    io::{BufReader, BufWriter, Cursor},
+    path::Path,
+};
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{
+    dev::catch_panic,
+    xnb::{
+        Xnb,
+        asset::{
+            XnbAsset,
+            color::Color,
+            level_model::{
+                AnimatedLevelPart, EffectStorage, LevelModel, LevelModelLightKind,
+                LevelModelLightVariation, Locator,
+            },
+        },
+    },
+};
+
+/// placement changes to apply to a `LevelModel`. entries are matched by name, except physics
+/// entities which don't have one and are matched by their index instead. fields that are left
+/// out keep their current value
+#[derive(serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct LevelPatch {
+    lights: Vec<LightPatch>,
+    locators: Vec<LocatorPatch>,
+    effect_storages: Vec<EffectStoragePatch>,
+    trigger_areas: Vec<TriggerAreaPatch>,
+    physics_entities: Vec<PhysicsEntityPatch>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightPatch {
+    name: String,
+    position: Option<Vec3>,
+    direction: Option<Vec3>,
+    kind: Option<LevelModelLightKind>,
+    variation: Option<LevelModelLightVariation>,
+    reach: Option<f32>,
+    use_attenuation: Option<bool>,
+    cutoff_angle: Option<f32>,
+    sharpness: Option<f32>,
+    diffuse_color: Option<Color>,
+    ambient_color: Option<Color>,
+    specular_amount: Option<f32>,
+    variation_amount: Option<f32>,
+    variation_speed: Option<f32>,
+    shadow_map_size: Option<i32>,
+    casts_shadows: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocatorPatch {
+    name: String,
+    /// 16 floats in the same order `dev diff` indexes them
+    transform: Option<Mat4>,
+    /// replaces only the translation of the transform
+    translation: Option<Vec3>,
+    radius: Option<f32>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EffectStoragePatch {
+    name: String,
+    position: Option<Vec3>,
+    forward: Option<Vec3>,
+    range: Option<f32>,
+    effect: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerAreaPatch {
+    name: String,
+    position: Option<Vec3>,
+    side_lengths: Option<Vec3>,
+    orientation: Option<Quat>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PhysicsEntityPatch {
+    index: usize,
+    transform: Option<Mat4>,
+    /// replaces only the translation of the transform
+    translation: Option<Vec3>,
+    template: Option<String>,
+}
+
+/// applies a json patch to the placement data of a level xnb and writes it back uncompressed.
+/// the geometry is carried over byte for byte, which is checked before anything is written
+pub fn patch_level(path: &Path, patch_path: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let patch = std::fs::read_to_string(patch_path)?;
+    let patch: LevelPatch = serde_json::from_str(&patch)
+        .map_err(|e| anyhow::anyhow!("invalid patch {}: {e}", patch_path.display()))?;
+
+    let file = std::fs::File::open(path)?;
+    let xnb = Xnb::read(&mut BufReader::new(file))?;
+    let data = xnb.decompress()?;
+    let mut content = catch_panic(|| Xnb::read_content(&mut Cursor::new(data.as_ref())))?;
+
+    // refuse to touch files our serializer can't reproduce exactly, since anything we
+    // don't understand about them would be silently lost
+    let mut unchanged = Vec::with_capacity(data.len());
+    content.write(&mut unchanged)?;
+    if unchanged != data.as_ref() {
+        let offset = unchanged
+            .iter()
+            .zip(data.iter())
+            .position(|(a, b)| a != b)
+            .unwrap_or(unchanged.len().min(data.len()));
+        anyhow::bail!(
+            "{} does not survive a round trip (first difference at offset {offset:#x}), refusing to patch it",
+            path.display()
+        );
+    }
+
+    let XnbAsset::LevelModel(level) = &mut content.primary_asset else {
+        anyhow::bail!(
+            "expected {} to be a LevelModel, found {}",
+            path.display(),
+            content.primary_asset.as_ref()
+        );
+    };
+
+    let changes = apply_patch(level, patch)?;
+
+    let mut patched = Vec::with_capacity(data.len());
+    content.write(&mut patched)?;
+    // make sure the result still parses before it replaces anything
+    catch_panic(|| Xnb::read_content(&mut Cursor::new(&patched)))?;
+
+    let out_path = output.unwrap_or(path);
+    let xnb = Xnb::from_content(xnb.header.platform, xnb.header.hi_def, patched);
+    let out_file = std::fs::File::create(out_path)?;
+    xnb.write(&mut BufWriter::new(out_file))?;
+
+    eprintln!("applied {changes} changes, wrote {}", out_path.display());
+
+    Ok(())
+}
+
+/// returns the number of entries that were changed
+fn apply_patch(level: &mut LevelModel, patch: LevelPatch) -> anyhow::Result<usize> {
+    let mut changes = 0;
+
+    for light_patch in patch.lights {
+        let light = level
+            .lights
+            .iter_mut()
+            .find(|l| l.name == light_patch.name)
+            .ok_or_else(|| anyhow::anyhow!("no light named {}", light_patch.name))?;
+        set(&mut light.position, light_patch.position);
+        set(&mut light.direction, light_patch.direction);
+        set(&mut light.kind, light_patch.kind);
+        set(&mut light.variation, light_patch.variation);
+        set(&mut light.reach, light_patch.reach);
+        set(&mut light.use_attenuation, light_patch.use_attenuation);
+        set(&mut light.cutoff_angle, light_patch.cutoff_angle);
+        set(&mut light.sharpness, light_patch.sharpness);
+        set(&mut light.diffuse_color, light_patch.diffuse_color);
+        set(&mut light.ambient_color, light_patch.ambient_color);
+        set(&mut light.specular_amount, light_patch.specular_amount);
+        set(&mut light.variation_amount, light_patch.variation_amount);
+        set(&mut light.variation_speed, light_patch.variation_speed);
+        set(&mut light.shadow_map_size, light_patch.shadow_map_size);
+        set(&mut light.casts_shadows, light_patch.casts_shadows);
+        changes += 1;
+    }
+
+    for locator_patch in patch.locators {
+        let locator = find_locator(
+            &mut level.locators,
+            &mut level.animated_parts,
+            &locator_patch.name,
+        )
+        .ok_or_else(|| anyhow::anyhow!("no locator named {}", locator_patch.name))?;
+        set(&mut locator.transform, locator_patch.transform);
+        if let Some(translation) = locator_patch.translation {
+            set_translation(&mut locator.transform, translation);
+        }
+        set(&mut locator.radius, locator_patch.radius);
+        changes += 1;
+    }
+
+    for effect_patch in patch.effect_storages {
+        let effect = find_effect_storage(
+            &mut level.effect_storages,
+            &mut level.animated_parts,
+            &effect_patch.name,
+        )
+        .ok_or_else(|| anyhow::anyhow!("no effect storage named {}", effect_patch.name))?;
+        set(&mut effect.position, effect_patch.position);
+        set(&mut effect.forward, effect_patch.forward);
+        set(&mut effect.range, effect_patch.range);
+        set(&mut effect.effect, effect_patch.effect);
+        changes += 1;
+    }
+
+    for area_patch in patch.trigger_areas {
+        let area = level
+            .trigger_areas
+            .iter_mut()
+            .find(|a| a.name == area_patch.name)
+            .ok_or_else(|| anyhow::anyhow!("no trigger area named {}", area_patch.name))?;
+        set(&mut area.position, area_patch.position);
+        set(&mut area.side_lengths, area_patch.side_lengths);
+        set(&mut area.orientation, area_patch.orientation);
+        changes += 1;
+    }
+
+    let num_entities = level.physics_entity_storages.len();
+    for entity_patch in patch.physics_entities {
+        let entity = level
+            .physics_entity_storages
+            .get_mut(entity_patch.index)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "physics entity index {} is out of range, the level has {num_entities}",
+                    entity_patch.index
+                )
+            })?;
+        set(&mut entity.transform, entity_patch.transform);
+        if let Some(translation) = entity_patch.translation {
+            set_translation(&mut entity.transform, translation);
+        }
+        set(&mut entity.template, entity_patch.template);
+        changes += 1;
+    }
+
+    Ok(changes)
+}
+
+/// level matrices are kept in xna's row vector layout, so the translation is the last row
+fn set_translation(transform: &mut Mat4, translation: Vec3) {
+    let mut rows = transform.transpose();
+    rows.w_axis = translation.extend(1.0);
+    *transform = rows.transpose();
+}
+
+fn set<T>(target: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *target = value;
+    }
+}
+
+/// looks in the level's own locators first, then in the animated parts depth first
+fn find_locator<'a>(
+    locators: &'a mut [Locator],
+    parts: &'a mut [AnimatedLevelPart],
+    name: &str,
+) -> Option<&'a mut Locator> {
+    if let Some(locator) = locators.iter_mut().find(|l| l.name == name) {
+        return Some(locator);
+    }
+
+    parts
+        .iter_mut()
+        .find_map(|part| find_locator(&mut part.locators, &mut part.children, name))
+}
+
+/// like `find_locator`, for effect storages
+fn find_effect_storage<'a>(
+    effects: &'a mut [EffectStorage],
+    parts: &'a mut [AnimatedLevelPart],
+    name: &str,
+) -> Option<&'a mut EffectStorage> {
+    if let Some(effect) = effects.iter_mut().find(|e| e.name == name) {
+        return Some(effect);
+    }
+
+    parts
+        .iter_mut()
+        .find_map(|part| find_effect_storage(&mut part.effect_storages, &mut part.children, name))
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    content::ContentIndex,
+    dev::{catch_panic, collect_files, has_extension, write_json},
+    read_ext::MyReadBytesExt,
+    vfs::DirectoryVfs,
+    vfx::{VisualEffectAsset, VisualEffectIndex},
+    xnb::{
+        Xnb,
+        asset::{is_known_type_reader, type_reader_base_name},
+    },
+};
+
+/// files that aren't xnbs or effects are only checked for well formed xml
+const XML_KIND: &str = "xml";
+const VISUAL_EFFECT_KIND: &str = "VisualEffect";
+
+#[derive(Serialize, Default)]
+pub struct ScanReport {
+    pub files_total: u32,
+    pub files_ok: u32,
+    pub files_failed: u32,
+    /// keyed by the type reader of the primary asset, or a pseudo kind for xml files
+    pub kinds: BTreeMap<String, KindCounts>,
+    pub unknown_type_readers: BTreeSet<String>,
+    pub failures: Vec<FileFailure>,
+    /// paths that only differ in case, which can't be told apart when resolving references
+    pub case_collisions: Vec<Vec<String>>,
+    /// effect files with the same file stem, which references to effects can't tell apart
+    pub duplicate_effects: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Default)]
+pub struct KindCounts {
+    pub ok: u32,
+    pub failed: u32,
+}
+
+#[derive(Serialize)]
+pub struct FileFailure {
+    pub path: String,
+    pub kind: String,
+    pub error: String,
+}
+
+/// parse every xnb and xml file under `<magicka_path>/Content` and report which ones fail
+pub fn scan(magicka_path: &str, out: Option<&Path>) -> anyhow::Result<()> {
+    let magicka_path = Path::new(magicka_path);
+    let content_path = magicka_path.join("Content");
+    let files = collect_files(&content_path, &["xnb", "xml"])?;
+
+    let mut report = ScanReport::default();
+
+    for path in &files {
+        let relative_path = path.strip_prefix(magicka_path).unwrap_or(path);
+        let display_path = relative_path.to_string_lossy().replace('\\', "/");
+
+        let (kind, result) = if has_extension(path, &["xnb"]) {
+            scan_xnb(path, &mut report.unknown_type_readers)
+        } else if is_effect_path(&display_path) {
+            let result = catch_panic(|| {
+                let xml = std::fs::read_to_string(path)?;
+                VisualEffectAsset::read_xml(&xml)?;
+                Ok(())
+            });
+            (VISUAL_EFFECT_KIND.to_string(), result)
+        } else {
+            let result = catch_panic(|| {
+                let xml = std::fs::read_to_string(path)?;
+                roxmltree::Document::parse(&xml)?;
+                Ok(())
+            });
+            (XML_KIND.to_string(), result)
+        };
+
+        report.files_total += 1;
+        let counts = report.kinds.entry(kind.clone()).or_default();
+        match result {
+            Ok(_) => {
+                report.files_ok += 1;
+                counts.ok += 1;
+            }
+            Err(e) => {
+                report.files_failed += 1;
+                counts.failed += 1;
+                report.failures.push(FileFailure {
+                    path: display_path,
+                    kind,
+                    error: format!("{e:#}"),
+                });
+            }
+        }
+    }
+
+    let vfs = DirectoryVfs::new(magicka_path);
+    let index = ContentIndex::build(&vfs)?;
+    report.case_collisions = display_groups(index.collisions());
+    let effect_index = VisualEffectIndex::build(&vfs)?;
+    report.duplicate_effects = display_groups(effect_index.duplicates());
+
+    eprintln!(
+        "scanned {} files, {} ok, {} failed, {} unknown type readers, {} case collisions, {} duplicate effects",
+        report.files_total,
+        report.files_ok,
+        report.files_failed,
+        report.unknown_type_readers.len(),
+        report.case_collisions.len(),
+        report.duplicate_effects.len()
+    );
+
+    write_json(&report, out)?;
+
+    Ok(())
+}
+
+/// returns the type reader name of the primary asset (or a placeholder if the header couldn't be read)
+/// along with the result of parsing the full content
+fn scan_xnb(
+    path: &Path,
+    unknown_type_readers: &mut BTreeSet<String>,
+) -> (String, anyhow::Result<()>) {
+    let mut kind = "unknown".to_string();
+
+    let result = catch_panic(|| {
+        let file = std::fs::File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let xnb = Xnb::read(&mut reader)?;
+        let decompressed = xnb.decompress()?;
+
+        let mut header_reader = Cursor::new(decompressed.as_ref());
+        let type_readers = Xnb::read_type_readers(&mut header_reader)?;
+        for type_reader in &type_readers {
+            if !is_known_type_reader(&type_reader.name) {
+                unknown_type_readers.insert(type_reader_base_name(&type_reader.name).to_string());
+            }
+        }
+
+        let _shared_asset_count = header_reader.read_7bit_encoded_i32()?;
+        let primary_type_id = header_reader.read_7bit_encoded_i32()? as usize;
+        kind = match primary_type_id {
+            0 => "null".to_string(),
+            id => type_readers
+                .get(id - 1)
+                .map(|r| type_reader_base_name(&r.name).to_string())
+                .unwrap_or_else(|| format!("invalid type id {id}")),
+        };
+
+        Xnb::parse_content_from(&decompressed)?;
+        Ok(())
+    });
+
+    (kind, result)
+}
+
+fn display_groups(groups: &[Vec<PathBuf>]) -> Vec<Vec<String>> {
+    groups
+        .iter()
+        .map(|paths| {
+            paths
+                .iter()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .collect()
+        })
+        .collect()
+}
+
+fn is_effect_path(relative_path: &str) -> bool {
+    relative_path
+        .to_ascii_lowercase()
+        .starts_with("content/effects/")
+}
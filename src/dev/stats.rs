@@ -0,0 +1,28 @@
+use std::{path::Path, sync::Arc, time::Instant};
+
+use crate::{
+    assets::{AssetLoader, stats::AssetStats},
+    vfs,
+};
+
+/// loads the level xml at `level_path` (relative to `magicka_path`) without a gpu, then prints
+/// what its assets cost by category and the `heaviest` heaviest ones. the disk cache isn't used,
+/// so load times are those of a first run
+pub fn stats(magicka_path: &str, level_path: &Path, heaviest: usize) -> anyhow::Result<()> {
+    let vfs: Arc<dyn vfs::Vfs> = vfs::open(Path::new(magicka_path))?.into();
+    let mut loader = AssetLoader::new(vfs);
+
+    let start = Instant::now();
+    let level = loader.load_level(level_path, None)?;
+    println!(
+        "loaded {} in {:.1} ms",
+        level_path.display(),
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+
+    for line in AssetStats::from_level(&level).report(heaviest) {
+        println!("{line}");
+    }
+
+    Ok(())
+}
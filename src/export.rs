@@ -0,0 +1,376 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::xnb::{
+    Texture2D, Texture3D,
+    asset::texture_2d::{ColorSpace, PixelFormat, bytes_per_row, rows_per_image},
+};
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Container {
+    /// decoded mip 0, one file per slice for volume textures
+    #[default]
+    Png,
+    /// original blocks and full mip chain
+    Dds,
+    /// original blocks and full mip chain
+    Ktx2,
+}
+
+impl Container {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Container::Png => "png",
+            Container::Dds => "dds",
+            Container::Ktx2 => "ktx2",
+        }
+    }
+}
+
+/// the parts of `Texture2D` and `Texture3D` the containers care about.
+/// mips hold every slice of their level back to back, like they do in the xnb
+pub struct TextureLevels<'a> {
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    /// `None` for 2d textures
+    pub depth: Option<u32>,
+    pub mips: &'a [Vec<u8>],
+}
+
+impl<'a> From<&'a Texture2D> for TextureLevels<'a> {
+    fn from(texture: &'a Texture2D) -> Self {
+        TextureLevels {
+            format: texture.format,
+            width: texture.width,
+            height: texture.height,
+            depth: None,
+            mips: &texture.mips,
+        }
+    }
+}
+
+impl<'a> From<&'a Texture3D> for TextureLevels<'a> {
+    fn from(texture: &'a Texture3D) -> Self {
+        TextureLevels {
+            format: texture.format,
+            width: texture.width,
+            height: texture.height,
+            depth: Some(texture.depth),
+            mips: &texture.mips,
+        }
+    }
+}
+
+impl TextureLevels<'_> {
+    /// bytes in one slice of mip 0
+    fn slice_size(&self) -> anyhow::Result<u32> {
+        Ok(bytes_per_row(self.width, 0, self.format)?
+            * rows_per_image(self.height, 0, self.format)?)
+    }
+}
+
+/// `color_space` is only recorded by ktx2, dds has no way to tell
+pub fn write_container(
+    texture: &TextureLevels,
+    container: Container,
+    color_space: ColorSpace,
+) -> anyhow::Result<Vec<u8>> {
+    match container {
+        Container::Png => {
+            anyhow::bail!("png textures are written by decoding, not by copying blocks")
+        }
+        Container::Dds => write_dds(texture),
+        Container::Ktx2 => write_ktx2(texture, color_space),
+    }
+}
+
+// https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dds-header
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSD_DEPTH: u32 = 0x800000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+pub fn write_dds(texture: &TextureLevels) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"DDS ");
+
+    let is_compressed = texture.format != PixelFormat::Color;
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_MIPMAPCOUNT;
+    flags |= if is_compressed {
+        DDSD_LINEARSIZE
+    } else {
+        DDSD_PITCH
+    };
+    if texture.depth.is_some() {
+        flags |= DDSD_DEPTH;
+    }
+
+    let pitch_or_linear_size = if is_compressed {
+        texture.slice_size()?
+    } else {
+        bytes_per_row(texture.width, 0, texture.format)?
+    };
+
+    out.write_u32::<LittleEndian>(124)?;
+    out.write_u32::<LittleEndian>(flags)?;
+    out.write_u32::<LittleEndian>(texture.height)?;
+    out.write_u32::<LittleEndian>(texture.width)?;
+    out.write_u32::<LittleEndian>(pitch_or_linear_size)?;
+    out.write_u32::<LittleEndian>(texture.depth.unwrap_or(0))?;
+    out.write_u32::<LittleEndian>(texture.mips.len() as u32)?;
+    out.extend_from_slice(&[0; 11 * 4]);
+
+    // pixel format
+    out.write_u32::<LittleEndian>(32)?;
+    match texture.format {
+        PixelFormat::Color => {
+            out.write_u32::<LittleEndian>(DDPF_RGB | DDPF_ALPHAPIXELS)?;
+            out.write_u32::<LittleEndian>(0)?;
+            out.write_u32::<LittleEndian>(32)?;
+            // bgra in memory
+            out.write_u32::<LittleEndian>(0x00ff0000)?;
+            out.write_u32::<LittleEndian>(0x0000ff00)?;
+            out.write_u32::<LittleEndian>(0x000000ff)?;
+            out.write_u32::<LittleEndian>(0xff000000)?;
+        }
+        PixelFormat::Bc1 | PixelFormat::Bc3 => {
+            let four_cc = if texture.format == PixelFormat::Bc1 {
+                b"DXT1"
+            } else {
+                b"DXT5"
+            };
+            out.write_u32::<LittleEndian>(DDPF_FOURCC)?;
+            out.extend_from_slice(four_cc);
+            out.extend_from_slice(&[0; 5 * 4]);
+        }
+    }
+
+    let mut caps = DDSCAPS_TEXTURE;
+    if texture.mips.len() > 1 {
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    let mut caps2 = 0;
+    if texture.depth.is_some() {
+        caps |= DDSCAPS_COMPLEX;
+        caps2 |= DDSCAPS2_VOLUME;
+    }
+    out.write_u32::<LittleEndian>(caps)?;
+    out.write_u32::<LittleEndian>(caps2)?;
+    // caps3, caps4, reserved2
+    out.extend_from_slice(&[0; 3 * 4]);
+
+    // dds and xnb both store each level with all of its slices back to back
+    for mip in texture.mips {
+        out.extend_from_slice(mip);
+    }
+
+    Ok(out)
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+// the same formats `PixelFormat::to_wgpu` picks for each `ColorSpace`
+const VK_FORMAT_B8G8R8A8_UNORM: u32 = 44;
+const VK_FORMAT_B8G8R8A8_SRGB: u32 = 50;
+const VK_FORMAT_BC1_RGBA_UNORM_BLOCK: u32 = 133;
+const VK_FORMAT_BC1_RGBA_SRGB_BLOCK: u32 = 134;
+const VK_FORMAT_BC3_UNORM_BLOCK: u32 = 137;
+const VK_FORMAT_BC3_SRGB_BLOCK: u32 = 138;
+
+// https://registry.khronos.org/DataFormat/specs/1.3/dataformat.1.3.html
+const KHR_DF_MODEL_RGBSDA: u8 = 1;
+const KHR_DF_MODEL_BC1A: u8 = 128;
+const KHR_DF_MODEL_BC3: u8 = 130;
+const KHR_DF_PRIMARIES_BT709: u8 = 1;
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+const KHR_DF_CHANNEL_RED: u8 = 0;
+const KHR_DF_CHANNEL_GREEN: u8 = 1;
+const KHR_DF_CHANNEL_BLUE: u8 = 2;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+const KHR_DF_CHANNEL_BC1A_ALPHAPRESENT: u8 = 1;
+const KHR_DF_CHANNEL_BC3_COLOR: u8 = 0;
+const KHR_DF_CHANNEL_BC3_ALPHA: u8 = 15;
+/// alpha isn't affected by the srgb transfer function
+const KHR_DF_SAMPLE_DATATYPE_LINEAR: u8 = 0x10;
+
+/// a sample in a basic data format descriptor block
+struct DfdSample {
+    bit_offset: u16,
+    bit_length: u8,
+    channel: u8,
+    upper: u32,
+}
+
+pub fn write_ktx2(texture: &TextureLevels, color_space: ColorSpace) -> anyhow::Result<Vec<u8>> {
+    let srgb = color_space == ColorSpace::Srgb;
+    let (transfer, alpha_datatype) = if srgb {
+        (KHR_DF_TRANSFER_SRGB, KHR_DF_SAMPLE_DATATYPE_LINEAR)
+    } else {
+        // every sample is linear already
+        (KHR_DF_TRANSFER_LINEAR, 0)
+    };
+
+    let (vk_format, block_dim, color_model, samples) = match texture.format {
+        PixelFormat::Color => (
+            if srgb {
+                VK_FORMAT_B8G8R8A8_SRGB
+            } else {
+                VK_FORMAT_B8G8R8A8_UNORM
+            },
+            1,
+            KHR_DF_MODEL_RGBSDA,
+            vec![
+                DfdSample {
+                    bit_offset: 0,
+                    bit_length: 8,
+                    channel: KHR_DF_CHANNEL_BLUE,
+                    upper: 255,
+                },
+                DfdSample {
+                    bit_offset: 8,
+                    bit_length: 8,
+                    channel: KHR_DF_CHANNEL_GREEN,
+                    upper: 255,
+                },
+                DfdSample {
+                    bit_offset: 16,
+                    bit_length: 8,
+                    channel: KHR_DF_CHANNEL_RED,
+                    upper: 255,
+                },
+                DfdSample {
+                    bit_offset: 24,
+                    bit_length: 8,
+                    channel: KHR_DF_CHANNEL_ALPHA | alpha_datatype,
+                    upper: 255,
+                },
+            ],
+        ),
+        PixelFormat::Bc1 => (
+            if srgb {
+                VK_FORMAT_BC1_RGBA_SRGB_BLOCK
+            } else {
+                VK_FORMAT_BC1_RGBA_UNORM_BLOCK
+            },
+            4,
+            KHR_DF_MODEL_BC1A,
+            vec![DfdSample {
+                bit_offset: 0,
+                bit_length: 64,
+                channel: KHR_DF_CHANNEL_BC1A_ALPHAPRESENT,
+                upper: u32::MAX,
+            }],
+        ),
+        PixelFormat::Bc3 => (
+            if srgb {
+                VK_FORMAT_BC3_SRGB_BLOCK
+            } else {
+                VK_FORMAT_BC3_UNORM_BLOCK
+            },
+            4,
+            KHR_DF_MODEL_BC3,
+            vec![
+                DfdSample {
+                    bit_offset: 0,
+                    bit_length: 64,
+                    channel: KHR_DF_CHANNEL_BC3_ALPHA | alpha_datatype,
+                    upper: u32::MAX,
+                },
+                DfdSample {
+                    bit_offset: 64,
+                    bit_length: 64,
+                    channel: KHR_DF_CHANNEL_BC3_COLOR,
+                    upper: u32::MAX,
+                },
+            ],
+        ),
+    };
+
+    let block_size = texture.format.block_size();
+
+    let mut dfd = Vec::new();
+    let descriptor_block_size = 24 + 16 * samples.len() as u16;
+    dfd.write_u32::<LittleEndian>(4 + descriptor_block_size as u32)?;
+    // vendor id and descriptor type are both 0 for the basic block
+    dfd.write_u32::<LittleEndian>(0)?;
+    dfd.write_u16::<LittleEndian>(2)?;
+    dfd.write_u16::<LittleEndian>(descriptor_block_size)?;
+    dfd.extend_from_slice(&[color_model, KHR_DF_PRIMARIES_BT709, transfer, 0]);
+    dfd.extend_from_slice(&[block_dim - 1, block_dim - 1, 0, 0]);
+    dfd.extend_from_slice(&[block_size as u8, 0, 0, 0, 0, 0, 0, 0]);
+    for sample in &samples {
+        dfd.write_u16::<LittleEndian>(sample.bit_offset)?;
+        dfd.write_u8(sample.bit_length - 1)?;
+        dfd.write_u8(sample.channel)?;
+        dfd.extend_from_slice(&[0; 4]);
+        dfd.write_u32::<LittleEndian>(0)?;
+        dfd.write_u32::<LittleEndian>(sample.upper)?;
+    }
+
+    let level_count = texture.mips.len();
+    let dfd_offset = KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_ENTRY_SIZE * level_count;
+
+    // levels are stored smallest first, each aligned to lcm(block size, 4)
+    let alignment = (block_size as usize).max(4);
+    let mut level_offsets = vec![0; level_count];
+    let mut offset = dfd_offset + dfd.len();
+    for (i, mip) in texture.mips.iter().enumerate().rev() {
+        offset = offset.next_multiple_of(alignment);
+        level_offsets[i] = offset;
+        offset += mip.len();
+    }
+
+    let mut out = Vec::with_capacity(offset);
+    out.extend_from_slice(&KTX2_IDENTIFIER);
+    out.write_u32::<LittleEndian>(vk_format)?;
+    // type size is 1 for block compressed and 8 bit formats
+    out.write_u32::<LittleEndian>(1)?;
+    out.write_u32::<LittleEndian>(texture.width)?;
+    out.write_u32::<LittleEndian>(texture.height)?;
+    out.write_u32::<LittleEndian>(texture.depth.unwrap_or(0))?;
+    // layer count, face count
+    out.write_u32::<LittleEndian>(0)?;
+    out.write_u32::<LittleEndian>(1)?;
+    out.write_u32::<LittleEndian>(level_count as u32)?;
+    // no supercompression
+    out.write_u32::<LittleEndian>(0)?;
+
+    // dfd, kvd, sgd
+    out.write_u32::<LittleEndian>(dfd_offset as u32)?;
+    out.write_u32::<LittleEndian>(dfd.len() as u32)?;
+    out.write_u32::<LittleEndian>(0)?;
+    out.write_u32::<LittleEndian>(0)?;
+    out.write_u64::<LittleEndian>(0)?;
+    out.write_u64::<LittleEndian>(0)?;
+
+    for (mip, level_offset) in texture.mips.iter().zip(&level_offsets) {
+        out.write_u64::<LittleEndian>(*level_offset as u64)?;
+        out.write_u64::<LittleEndian>(mip.len() as u64)?;
+        out.write_u64::<LittleEndian>(mip.len() as u64)?;
+    }
+
+    out.extend_from_slice(&dfd);
+
+    for (i, mip) in texture.mips.iter().enumerate().rev() {
+        out.resize(level_offsets[i], 0);
+        out.extend_from_slice(mip);
+    }
+
+    Ok(out)
+}
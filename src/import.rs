@@ -0,0 +1,162 @@
+use std::{
+    io::{BufReader, BufWriter, Cursor},
+    path::Path,
+};
+
+use image::{RgbaImage, imageops::FilterType};
+
+use crate::xnb::{
+    Platform, TypeReader, Xnb, XnbContent,
+    asset::{
+        XnbAsset,
+        texture_2d::{self, PixelFormat, Texture2D},
+    },
+};
+
+/// the reader name written when there's no reference texture to copy it from
+const DEFAULT_TEXTURE_2D_READER_NAME: &str = "Microsoft.Xna.Framework.Content.Texture2DReader, Microsoft.Xna.Framework, Version=3.1.0.0, Culture=neutral, PublicKeyToken=6d5c3888ef60e27d";
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum TextureFormat {
+    Color,
+    Bc1,
+    Bc3,
+}
+
+impl From<TextureFormat> for PixelFormat {
+    fn from(format: TextureFormat) -> Self {
+        match format {
+            TextureFormat::Color => PixelFormat::Color,
+            TextureFormat::Bc1 => PixelFormat::Bc1,
+            TextureFormat::Bc3 => PixelFormat::Bc3,
+        }
+    }
+}
+
+pub struct TextureImportOptions<'a> {
+    /// overrides the format of the reference texture
+    pub format: Option<TextureFormat>,
+    /// an original texture to copy the pixel format, type reader and header flags from
+    pub reference: Option<&'a Path>,
+    /// only write the full size image instead of a full mip chain
+    pub no_mips: bool,
+}
+
+/// converts a png into a `Texture2D` xnb
+pub fn import_texture(
+    png_path: &Path,
+    out_path: &Path,
+    options: &TextureImportOptions,
+) -> anyhow::Result<()> {
+    let image = image::open(png_path)?.to_rgba8();
+
+    let mut format = PixelFormat::Color;
+    let mut platform = Platform::Windows;
+    let mut hi_def = false;
+    let mut type_reader = TypeReader {
+        name: DEFAULT_TEXTURE_2D_READER_NAME.to_string(),
+        version: 0,
+    };
+
+    if let Some(reference_path) = options.reference {
+        let file = std::fs::File::open(reference_path)?;
+        let xnb = Xnb::read(&mut BufReader::new(file))?;
+        let content = xnb.parse_content()?;
+        let XnbAsset::Texture2D(reference) = &content.primary_asset else {
+            anyhow::bail!(
+                "expected reference {} to be a Texture2D",
+                reference_path.display()
+            );
+        };
+
+        if reference.width != image.width() || reference.height != image.height() {
+            eprintln!(
+                "warning: image is {}x{} but the reference texture is {}x{}",
+                image.width(),
+                image.height(),
+                reference.width,
+                reference.height
+            );
+        }
+
+        format = reference.format;
+        platform = xnb.header.platform;
+        hi_def = xnb.header.hi_def;
+        if let Some(reader) = content.type_readers.into_iter().next() {
+            type_reader = reader;
+        }
+    }
+
+    if let Some(override_format) = options.format {
+        format = override_format.into();
+    }
+
+    let mip_count = if options.no_mips {
+        1
+    } else {
+        image.width().max(image.height()).ilog2() + 1
+    };
+
+    let mut mips = Vec::with_capacity(mip_count as usize);
+    for mip_index in 0..mip_count {
+        let width = (image.width() >> mip_index).max(1);
+        let height = (image.height() >> mip_index).max(1);
+        // every level is resized from the full image so errors don't accumulate down the chain
+        let mip = if mip_index == 0 {
+            image.clone()
+        } else {
+            image::imageops::resize(&image, width, height, FilterType::Triangle)
+        };
+        mips.push(encode_mip(&mip, format));
+    }
+
+    let texture = Texture2D {
+        format,
+        width: image.width(),
+        height: image.height(),
+        mips,
+    };
+
+    let content = XnbContent {
+        type_readers: vec![type_reader],
+        primary_asset: XnbAsset::Texture2D(texture),
+        shared_assets: Vec::new(),
+    };
+    let mut data = Vec::new();
+    content.write(&mut data)?;
+
+    // make sure our own reader accepts what we just wrote before handing it to the game
+    Xnb::read_content(&mut Cursor::new(&data))?;
+
+    let xnb = Xnb::from_content(platform, hi_def, data);
+    let out_file = std::fs::File::create(out_path)?;
+    xnb.write(&mut BufWriter::new(out_file))?;
+
+    eprintln!(
+        "wrote {}x{} {format:?} texture with {mip_count} mips to {}",
+        image.width(),
+        image.height(),
+        out_path.display()
+    );
+
+    Ok(())
+}
+
+fn encode_mip(mip: &RgbaImage, format: PixelFormat) -> Vec<u8> {
+    let (width, height) = (mip.width() as usize, mip.height() as usize);
+    let compressed_format = match format {
+        PixelFormat::Color => return texture_2d::rgba8_to_bgra8(mip.as_raw()),
+        PixelFormat::Bc1 => texpresso::Format::Bc1,
+        PixelFormat::Bc3 => texpresso::Format::Bc3,
+    };
+
+    let mut data = vec![0; compressed_format.compressed_size(width, height)];
+    compressed_format.compress(
+        mip.as_raw(),
+        width,
+        height,
+        texpresso::Params::default(),
+        &mut data,
+    );
+    data
+}
@@ -0,0 +1,18 @@
+//! parsers and writers for magicka's content files. the viewer is behind the `renderer` feature
+
+pub mod assets;
+pub mod content;
+pub mod read_ext;
+pub mod vfs;
+pub mod vfx;
+pub mod write_ext;
+pub mod xnb;
+
+#[cfg(feature = "renderer")]
+pub mod app;
+#[cfg(feature = "renderer")]
+pub mod asset_manager;
+#[cfg(feature = "renderer")]
+pub mod renderer;
+#[cfg(feature = "renderer")]
+pub mod scene;
@@ -1,28 +1,23 @@
 use std::{
-    collections::HashMap,
-    ffi::OsStr,
     io::{BufReader, Write},
-    path::Path,
+    path::PathBuf,
 };
 
+use aldrheim::{assets, content, read_ext, vfs, vfx, xnb};
 use clap::Parser;
 use image::{ExtendedColorType, ImageEncoder, codecs::png::PngEncoder};
-use winit::event_loop::EventLoop;
 
-use crate::{
-    app::App,
-    xnb::{
-        Xnb,
-        asset::{XnbAsset, texture_2d, vertex_decl::VertexDeclaration},
+use crate::xnb::{
+    Xnb,
+    asset::{
+        XnbAsset,
+        texture_2d::{self, ColorSpace},
     },
 };
 
-mod app;
-mod asset_manager;
-mod read_ext;
-mod renderer;
-mod scene;
-mod xnb;
+mod dev;
+mod export;
+mod import;
 
 #[derive(clap::Parser)]
 struct Args {
@@ -32,16 +27,33 @@ struct Args {
 
 #[derive(clap::Subcommand, Clone)]
 enum Subcommands {
+    #[cfg(feature = "renderer")]
     Run(RunCommand),
     Extract(ExtractCommand),
+    Import(ImportCommand),
     Dev(DevCommand),
 }
 
 /// Run the game
+#[cfg(feature = "renderer")]
 #[derive(clap::Args, Clone)]
 struct RunCommand {
     /// path to magicka install directory
     path: String,
+    /// mod directory or zip archive layered over the install, may be repeated. later overlays
+    /// take priority over earlier ones
+    #[arg(long = "overlay", value_name = "PATH")]
+    overlays: Vec<PathBuf>,
+    /// directory processed assets are cached in, defaults to the user's cache directory
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+    /// don't read or write the processed asset cache
+    #[arg(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+    /// level xml to start in, relative to the install directory. page up and page down switch
+    /// to the previous and next level, f2 to a typed one
+    #[arg(long)]
+    level: Option<PathBuf>,
 }
 
 /// Extract content from an XNB file
@@ -49,6 +61,43 @@ struct RunCommand {
 struct ExtractCommand {
     /// path to xnb file
     path: String,
+    /// file format textures are written as
+    #[arg(short, long, value_enum, default_value_t)]
+    container: export::Container,
+    /// the texture holds data such as a normal or material map rather than colors, so ktx2
+    /// output is marked linear instead of srgb
+    #[arg(long)]
+    linear: bool,
+}
+
+/// Convert files back into XNB content
+#[derive(clap::Args, Clone)]
+struct ImportCommand {
+    #[command(subcommand)]
+    subcommand: ImportSubcommands,
+}
+
+#[derive(clap::Subcommand, Clone)]
+enum ImportSubcommands {
+    Texture(ImportTextureCommand),
+}
+
+/// Build a Texture2D XNB from a PNG
+#[derive(clap::Args, Clone)]
+struct ImportTextureCommand {
+    /// path to png file
+    png: PathBuf,
+    /// path to write the xnb file to
+    out: PathBuf,
+    /// original texture to match the pixel format and header of
+    #[arg(short, long)]
+    reference: Option<PathBuf>,
+    /// pixel format to write, overrides the reference texture's format
+    #[arg(short, long, value_enum)]
+    format: Option<import::TextureFormat>,
+    /// don't generate mipmaps
+    #[arg(long)]
+    no_mips: bool,
 }
 
 /// Development utilities
@@ -61,28 +110,216 @@ struct DevCommand {
 #[derive(clap::Subcommand, Clone)]
 enum DevSubcommands {
     DedupPipelines(DedupPipelinesCommand),
+    Scan(ScanCommand),
+    Diff(DiffCommand),
+    Deps(DepsCommand),
+    Inspect(InspectCommand),
+    PatchLevel(PatchLevelCommand),
+    FixEffects(FixEffectsCommand),
+    LintEffects(LintEffectsCommand),
+    Cache(CacheCommand),
+    Stats(StatsCommand),
 }
 
-/// Parse all models in a directory and find all unique shader and vertex layout combinations
+/// Recursively parse all mesh bearing assets in a directory and find all unique shader and vertex layout combinations
 #[derive(clap::Args, Clone)]
 struct DedupPipelinesCommand {
     /// path to search directory
     path: String,
+    /// output format
+    #[arg(short, long, value_enum, default_value_t)]
+    format: dev::dedup_pipelines::OutputFormat,
+    /// write the output to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Try to parse every XNB and XML file under Content and report parser coverage as JSON
+#[derive(clap::Args, Clone)]
+struct ScanCommand {
+    /// path to magicka install directory
+    path: String,
+    /// write the report to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Parse two XNB files and print the field level differences between them
+#[derive(clap::Args, Clone)]
+struct DiffCommand {
+    /// path to the original xnb file
+    a: PathBuf,
+    /// path to the changed xnb file
+    b: PathBuf,
+    /// numbers closer than this are considered equal
+    #[arg(short, long, default_value_t = 0.0)]
+    tolerance: f64,
+    /// lists and buffers longer than this are summarized instead of compared element by element
+    #[arg(long, default_value_t = 64)]
+    max_inline_len: usize,
+}
+
+/// List every asset a level transitively depends on and report references that can't be resolved
+#[derive(clap::Args, Clone)]
+struct DepsCommand {
+    /// path to magicka install directory
+    path: String,
+    /// path to the level xml, relative to the magicka install directory
+    level: PathBuf,
+    /// also write the dependency graph to this file in graphviz dot format
+    #[arg(long)]
+    dot: Option<PathBuf>,
+}
+
+/// Print the decompressed content of an XNB file as a hex dump annotated with the parsed fields
+#[derive(clap::Args, Clone)]
+struct InspectCommand {
+    /// path to xnb file
+    path: PathBuf,
+    /// fields spanning more lines than this are shortened
+    #[arg(long, default_value_t = 4)]
+    max_lines: usize,
+}
+
+/// Apply a JSON patch to the lights, locators, effects, trigger areas and physics entities of a level XNB
+#[derive(clap::Args, Clone)]
+struct PatchLevelCommand {
+    /// path to the level xnb file
+    path: PathBuf,
+    /// path to the json patch
+    patch: PathBuf,
+    /// write the patched level here instead of overwriting the original
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Rewrite visual effect XML files that only load after being repaired into valid, normalized XML
+#[derive(clap::Args, Clone)]
+struct FixEffectsCommand {
+    /// path to magicka install directory
+    path: String,
+    /// normalize every effect, not just the broken ones
+    #[arg(long)]
+    all: bool,
+    /// write rewritten effects to this directory instead of only printing which files would be
+    /// rewritten
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// overwrite the effects of the install, keeping each original as .xml.bak
+    #[arg(long, conflicts_with = "output")]
+    in_place: bool,
+}
+
+/// Check every visual effect under Content/Effects and report schema problems with their line and column
+#[derive(clap::Args, Clone)]
+struct LintEffectsCommand {
+    /// path to magicka install directory
+    path: String,
+}
+
+/// Inspect or clear the cache of processed assets
+#[derive(clap::Args, Clone)]
+struct CacheCommand {
+    #[command(subcommand)]
+    subcommand: CacheSubcommands,
+    /// cache directory, defaults to the user's cache directory
+    #[arg(long, global = true)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(clap::Subcommand, Clone)]
+enum CacheSubcommands {
+    /// Print the number and size of cached entries
+    Stats,
+    /// Delete every cached entry
+    Clear,
+}
+
+/// Load a level without a GPU and report the memory and load time of its assets by category
+#[derive(clap::Args, Clone)]
+struct StatsCommand {
+    /// path to magicka install directory
+    path: String,
+    /// path to the level xml, relative to the magicka install directory
+    level: PathBuf,
+    /// how many of the heaviest assets to list
+    #[arg(long, default_value_t = 20)]
+    heaviest: usize,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
     match args.subcommand {
+        #[cfg(feature = "renderer")]
         Subcommands::Run(args) => {
-            run(&args.path)?;
+            run(&args)?;
         }
         Subcommands::Extract(args) => {
-            extract(&args.path)?;
+            let color_space = if args.linear {
+                ColorSpace::Linear
+            } else {
+                ColorSpace::Srgb
+            };
+            extract(&args.path, args.container, color_space)?;
         }
+        Subcommands::Import(args) => match args.subcommand {
+            ImportSubcommands::Texture(args) => {
+                let options = import::TextureImportOptions {
+                    format: args.format,
+                    reference: args.reference.as_deref(),
+                    no_mips: args.no_mips,
+                };
+                import::import_texture(&args.png, &args.out, &options)?;
+            }
+        },
         Subcommands::Dev(args) => match args.subcommand {
             DevSubcommands::DedupPipelines(args) => {
-                dedup_pipelines(&args.path)?;
+                dev::dedup_pipelines::dedup_pipelines(
+                    &args.path,
+                    args.format,
+                    args.output.as_deref(),
+                )?;
+            }
+            DevSubcommands::Scan(args) => {
+                dev::scan::scan(&args.path, args.output.as_deref())?;
+            }
+            DevSubcommands::Diff(args) => {
+                let options = dev::diff::DiffOptions {
+                    tolerance: args.tolerance,
+                    max_inline_len: args.max_inline_len,
+                };
+                dev::diff::diff(&args.a, &args.b, &options)?;
+            }
+            DevSubcommands::Deps(args) => {
+                dev::deps::deps(&args.path, &args.level, args.dot.as_deref())?;
+            }
+            DevSubcommands::Inspect(args) => {
+                let options = dev::inspect::InspectOptions {
+                    max_lines: args.max_lines,
+                };
+                dev::inspect::inspect(&args.path, &options)?;
+            }
+            DevSubcommands::PatchLevel(args) => {
+                dev::patch_level::patch_level(&args.path, &args.patch, args.output.as_deref())?;
+            }
+            DevSubcommands::FixEffects(args) => {
+                let options = dev::fix_effects::FixEffectsOptions {
+                    all: args.all,
+                    output: args.output.clone(),
+                    in_place: args.in_place,
+                };
+                dev::fix_effects::fix_effects(&args.path, &options)?;
+            }
+            DevSubcommands::LintEffects(args) => {
+                dev::lint_effects::lint_effects(&args.path)?;
+            }
+            DevSubcommands::Cache(args) => match args.subcommand {
+                CacheSubcommands::Stats => dev::cache::stats(args.dir.as_deref())?,
+                CacheSubcommands::Clear => dev::cache::clear(args.dir.as_deref())?,
+            },
+            DevSubcommands::Stats(args) => {
+                dev::stats::stats(&args.path, &args.level, args.heaviest)?;
             }
         },
     }
@@ -90,7 +327,11 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn extract(path: &str) -> anyhow::Result<()> {
+fn extract(
+    path: &str,
+    container: export::Container,
+    color_space: ColorSpace,
+) -> anyhow::Result<()> {
     let file = std::fs::File::open(path)?;
     let mut reader = BufReader::new(file);
 
@@ -108,6 +349,22 @@ fn extract(path: &str) -> anyhow::Result<()> {
         out_file.write_all(&decompressed)?;
     }
 
+    if container != export::Container::Png {
+        let texture: export::TextureLevels = match &content.primary_asset {
+            XnbAsset::Texture2D(texture) => texture.into(),
+            XnbAsset::Texture3D(texture) => texture.into(),
+            _ => anyhow::bail!("only textures can be written to {}", container.extension()),
+        };
+
+        let out_path = format!("{path}.{}", container.extension());
+        std::fs::write(
+            out_path,
+            export::write_container(&texture, container, color_space)?,
+        )?;
+
+        return Ok(());
+    }
+
     match content.primary_asset {
         XnbAsset::Texture2D(texture) => {
             // dump png
@@ -159,114 +416,33 @@ fn extract(path: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn dedup_pipelines(path: &str) -> anyhow::Result<()> {
-    let mut xnb_paths = Vec::new();
-
-    let entries = std::fs::read_dir(path)?;
-    for entry in entries {
-        let entry = match entry {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("error: {e}");
-                continue;
-            }
-        };
-
-        let path = entry.path();
-
-        if !entry.file_type()?.is_file() {
-            println!("skipping non file path: {}", path.display());
-            continue;
-        }
-
-        if path.extension() != Some(OsStr::new("xnb")) {
-            println!("skipping non xnb file: {}", path.display());
-            continue;
-        }
+#[cfg(feature = "renderer")]
+fn run(args: &RunCommand) -> anyhow::Result<()> {
+    use aldrheim::assets::disk_cache::DiskCache;
 
-        xnb_paths.push(path);
-    }
-
-    // number of mesh parts using a unique vertex declaration and effect
-    let mut map: HashMap<DedupedPipelineInfo, u32> = HashMap::new();
-
-    let mut num_processed = 0;
-    let mut num_errors = 0;
-    for path in &xnb_paths {
-        num_processed += 1;
-        match dedup_pipelines_handle_file(path, &mut map) {
-            Ok(_) => {}
-            Err(e) => {
-                num_errors += 1;
-                eprintln!("error on {}: {}", path.file_name().unwrap().display(), e);
-                continue;
-            }
-        }
-    }
-
-    let mut kvs = map.iter().collect::<Vec<_>>();
-    kvs.sort_unstable_by_key(|kv| *kv.1);
-
-    for (pipeline, count) in &kvs {
-        print!(
-            "count: {:>5}, effect: {}, vertex decl: ",
-            count, pipeline.effect
-        );
-        for el in &pipeline.vertex_declaration.elements {
-            print!("{} ", el.debug_string());
-        }
-        println!();
-    }
-
-    println!(
-        "processed {} files with {} errors",
-        num_processed, num_errors
-    );
-    println!("{} unique pipelines found", kvs.len());
-
-    Ok(())
-}
-
-fn dedup_pipelines_handle_file(
-    path: impl AsRef<Path>,
-    map: &mut HashMap<DedupedPipelineInfo, u32>,
-) -> anyhow::Result<()> {
-    let path = path.as_ref();
-    let file = std::fs::File::open(path)?;
-    let mut reader = BufReader::new(file);
-    let xnb = Xnb::read(&mut reader)?;
-    let content = xnb.parse_content()?;
+    env_logger::init();
 
-    let XnbAsset::Model(model) = content.primary_asset else {
-        return Ok(());
+    let cache_dir = if args.no_cache {
+        None
+    } else {
+        args.cache_dir.clone().or_else(DiskCache::default_dir)
     };
-
-    for mesh in &model.meshes {
-        for part in &mesh.parts {
-            let key = DedupedPipelineInfo {
-                vertex_declaration: model.vertex_decls[part.vertex_decl_index as usize].clone(),
-                effect: content.shared_assets[(part.shared_content_material_index - 1) as usize]
-                    .as_ref()
-                    .into(),
-            };
-            map.entry(key).and_modify(|count| *count += 1).or_insert(1);
+    // the cache only makes loading faster, run without it rather than not at all
+    let disk_cache = cache_dir.and_then(|dir| match DiskCache::open(&dir) {
+        Ok(disk_cache) => Some(disk_cache),
+        Err(e) => {
+            log::warn!("running without a disk cache: {e:#}");
+            None
         }
-    }
-
-    Ok(())
-}
-
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct DedupedPipelineInfo {
-    vertex_declaration: VertexDeclaration,
-    effect: String,
-}
-
-fn run(path: &str) -> anyhow::Result<()> {
-    env_logger::init();
-
-    let event_loop = EventLoop::with_user_event().build()?;
-    let mut app = App::new(path)?;
+    });
+
+    let event_loop = winit::event_loop::EventLoop::with_user_event().build()?;
+    let mut app = aldrheim::app::App::new(
+        args.path.as_ref(),
+        &args.overlays,
+        disk_cache,
+        args.level.clone(),
+    )?;
     event_loop.run_app(&mut app)?;
 
     Ok(())
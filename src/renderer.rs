@@ -14,6 +14,7 @@ use crate::{
         },
     },
     scene::{self, Skymap},
+    xnb::asset::model::BoundingSphere,
 };
 
 pub mod camera;
@@ -24,13 +25,24 @@ pub struct RenderContext {
     pub queue: wgpu::Queue,
     pub surface_format: wgpu::TextureFormat,
     pub linear_sampler: wgpu::Sampler,
-    pub placeholder_texture_view: wgpu::TextureView,
+    pub fallback_textures: FallbackTextures,
 
     pub vertex_storage_buffer_bind_group_layout: wgpu::BindGroupLayout,
     pub uniform_buffer_bind_group_layout: wgpu::BindGroupLayout,
     pub texture_2d_bind_group_layout: wgpu::BindGroupLayout,
-    pub texture_2d_2x_bind_group_layout: wgpu::BindGroupLayout,
     pub texture_3d_bind_group_layout: wgpu::BindGroupLayout,
+    pub texture_cube_bind_group_layout: wgpu::BindGroupLayout,
+    pub material_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// bound in place of the textures a material doesn't have, chosen so they don't change the result
+pub struct FallbackTextures {
+    /// for diffuse and material maps, which are multiplied with the material's own values
+    pub white: wgpu::TextureView,
+    /// a normal map pointing straight out of the surface
+    pub flat_normal: wgpu::TextureView,
+    /// a reflection map that reflects nothing
+    pub black_cube: wgpu::TextureView,
 }
 
 impl RenderContext {
@@ -156,16 +168,16 @@ impl RenderContext {
                 ],
             });
 
-        let texture_2d_2x_bind_group_layout =
+        let texture_3d_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Texture2D 2x Bind Group Layout"),
+                label: Some("Texture3D Bind Group Layout"),
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
+                            view_dimension: wgpu::TextureViewDimension::D3,
                             multisampled: false,
                         },
                         count: None,
@@ -173,32 +185,22 @@ impl RenderContext {
                     wgpu::BindGroupLayoutEntry {
                         binding: 1,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
                 ],
             });
 
-        let texture_3d_bind_group_layout =
+        let texture_cube_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Texture3D Bind Group Layout"),
+                label: Some("TextureCube Bind Group Layout"),
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D3,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
                             multisampled: false,
                         },
                         count: None,
@@ -212,51 +214,67 @@ impl RenderContext {
                 ],
             });
 
-        let placeholder_pixel = [0xFF, 0x00, 0xFF, 0xFF];
-        let placeholder_texture_size = wgpu::Extent3d {
-            width: 1,
-            height: 1,
-            depth_or_array_layers: 1,
-        };
-        let placeholder_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Placeholder Texture"),
-            size: placeholder_texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-        let placeholder_texture_view =
-            placeholder_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: &placeholder_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &placeholder_pixel,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4),
-                rows_per_image: Some(1),
+        // diffuse, material and normal maps of both materials, then the reflection map
+        let texture_entry = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
             },
-            placeholder_texture_size,
-        );
+            count: None,
+        };
+        let material_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Material Bind Group Layout"),
+                entries: &[
+                    texture_entry(0, wgpu::TextureViewDimension::D2),
+                    texture_entry(1, wgpu::TextureViewDimension::D2),
+                    texture_entry(2, wgpu::TextureViewDimension::D2),
+                    texture_entry(3, wgpu::TextureViewDimension::D2),
+                    texture_entry(4, wgpu::TextureViewDimension::D2),
+                    texture_entry(5, wgpu::TextureViewDimension::D2),
+                    texture_entry(6, wgpu::TextureViewDimension::Cube),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let fallback_textures = FallbackTextures {
+            white: create_solid_texture(&device, &queue, "White Texture", [0xFF; 4], 1),
+            flat_normal: create_solid_texture(
+                &device,
+                &queue,
+                "Flat Normal Texture",
+                [0x80, 0x80, 0xFF, 0xFF],
+                1,
+            ),
+            black_cube: create_solid_texture(
+                &device,
+                &queue,
+                "Black Cube Texture",
+                [0x00, 0x00, 0x00, 0xFF],
+                6,
+            ),
+        };
 
         let ctx = RenderContext {
             device,
             queue,
             surface_format,
             linear_sampler,
-            placeholder_texture_view,
+            fallback_textures,
             vertex_storage_buffer_bind_group_layout,
             uniform_buffer_bind_group_layout,
             texture_2d_bind_group_layout,
-            texture_2d_2x_bind_group_layout,
             texture_3d_bind_group_layout,
+            texture_cube_bind_group_layout,
+            material_bind_group_layout,
             // skymap_bind_group_layout,
         };
         Ok((ctx, surface, surface_config))
@@ -433,6 +451,11 @@ impl Renderer {
             .bitrees
             .iter()
             .filter(|draw| frustum.test_aabb(&draw.node.bounding_box));
+        let culled_models = self
+            .draw_commands
+            .models
+            .iter()
+            .filter(|draw| frustum.test_sphere(&draw.world_bounds()));
 
         let surface_texture = self.surface.get_current_texture()?;
         let surface_view = surface_texture
@@ -495,7 +518,7 @@ impl Renderer {
                     &draw.node.tree.vertex_layout_uniform_bind_group,
                     &[],
                 );
-                render_pass.set_bind_group(3, &draw.node.tree.texture_bind_group, &[]);
+                render_pass.set_bind_group(3, &draw.node.tree.material.texture_bind_group, &[]);
                 render_pass.set_push_constants(
                     wgpu::ShaderStages::VERTEX,
                     0,
@@ -512,6 +535,28 @@ impl Renderer {
                 );
             }
 
+            // render models, on the same pipeline as bitrees
+            for draw in culled_models {
+                let mesh = &draw.node.model.meshes[draw.node.mesh];
+                render_pass.set_bind_group(1, &mesh.vertex_buffer_bind_group, &[]);
+                render_pass.set_push_constants(
+                    wgpu::ShaderStages::VERTEX,
+                    0,
+                    bytemuck::cast_slice(&[draw.transform]),
+                );
+                render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+                for part in &mesh.parts {
+                    let material = &draw.node.model.materials[part.material];
+                    render_pass.set_bind_group(2, &part.vertex_layout_uniform_bind_group, &[]);
+                    render_pass.set_bind_group(3, &material.texture_bind_group, &[]);
+                    render_pass.draw_indexed(
+                        part.start_index..part.start_index + part.index_count,
+                        part.base_vertex,
+                        0..1,
+                    );
+                }
+            }
+
             // render particles
             render_pass.set_pipeline(&self.particles_pipeline.pipeline);
             render_pass.set_bind_group(0, &self.camera_uniform_bind_group, &[]);
@@ -566,14 +611,22 @@ pub struct CameraUniform {
 pub struct DrawCommands {
     pub skymap: Option<Skymap>,
     pub bitrees: Vec<BiTreeDrawCommand>,
+    pub models: Vec<ModelDrawCommand>,
     pub particles: Vec<ParticleInstance>,
 }
 
+impl Default for DrawCommands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DrawCommands {
     pub fn new() -> Self {
         DrawCommands {
             skymap: None,
             bitrees: Vec::new(),
+            models: Vec::new(),
             particles: Vec::new(),
         }
     }
@@ -581,6 +634,7 @@ impl DrawCommands {
     pub fn clear(&mut self) {
         self.skymap = None;
         self.bitrees.clear();
+        self.models.clear();
         self.particles.clear();
     }
 
@@ -591,6 +645,13 @@ impl DrawCommands {
         });
     }
 
+    pub fn add_model(&mut self, model: scene::ModelNode, transform: Mat4) {
+        self.models.push(ModelDrawCommand {
+            node: model,
+            transform,
+        });
+    }
+
     pub fn add_particles(&mut self, particles: impl IntoIterator<Item = ParticleInstance>) {
         self.particles.extend(particles);
     }
@@ -601,6 +662,77 @@ pub struct BiTreeDrawCommand {
     pub transform: Mat4,
 }
 
+pub struct ModelDrawCommand {
+    pub node: scene::ModelNode,
+    pub transform: Mat4,
+}
+
+impl ModelDrawCommand {
+    /// the mesh's bounds moved by `transform`, grown to still contain it when scaled unevenly
+    pub fn world_bounds(&self) -> BoundingSphere {
+        let bounds = &self.node.model.meshes[self.node.mesh].bounds;
+        let scale = self
+            .transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(self.transform.y_axis.truncate().length())
+            .max(self.transform.z_axis.truncate().length());
+        BoundingSphere {
+            center: self.transform.transform_point3(bounds.center),
+            radius: bounds.radius * scale,
+        }
+    }
+}
+
+/// a 1x1 linear rgba texture with `layers` layers of `pixel`, viewed as a cube if there are 6
+fn create_solid_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    pixel: [u8; 4],
+    layers: u32,
+) -> wgpu::TextureView {
+    let size = wgpu::Extent3d {
+        width: 1,
+        height: 1,
+        depth_or_array_layers: layers,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &pixel.repeat(layers as usize),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4),
+            rows_per_image: Some(1),
+        },
+        size,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(if layers == 6 {
+            wgpu::TextureViewDimension::Cube
+        } else {
+            wgpu::TextureViewDimension::D2
+        }),
+        ..Default::default()
+    })
+}
+
 pub fn create_depth_texture(
     device: &wgpu::Device,
     surface_config: &wgpu::SurfaceConfiguration,
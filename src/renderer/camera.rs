@@ -123,7 +123,9 @@ impl Frustum {
     }
 
     pub fn test_sphere(&self, sphere: &BoundingSphere) -> bool {
-        todo!()
+        self.planes()
+            .into_iter()
+            .all(|p| p.normal.dot(sphere.center) + p.distance >= -sphere.radius)
     }
 
     pub fn test_aabb(&self, aabb: &BoundingBox) -> bool {
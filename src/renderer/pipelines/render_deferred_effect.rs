@@ -31,7 +31,7 @@ impl RenderDeferredEffectPipeline {
                         // effect properties uniform
                         &context.uniform_buffer_bind_group_layout,
                         // effect textures
-                        &context.texture_2d_2x_bind_group_layout,
+                        &context.material_bind_group_layout,
                     ],
                     push_constant_ranges: &[wgpu::PushConstantRange {
                         stages: wgpu::ShaderStages::VERTEX,
@@ -152,6 +152,7 @@ impl RenderDeferredEffectUniform {
         let mut m1_diffuse_color_r = 0.0;
         let mut m1_diffuse_color_g = 0.0;
         let mut m1_diffuse_color_b = 0.0;
+        let mut m1_diffuse_texture_enabled = 0;
         let mut m1_diffuse_texture_alpha_enabled = 0;
         let mut m1_alpha_mask_enabled = 0;
 
@@ -160,6 +161,7 @@ impl RenderDeferredEffectUniform {
             m1_diffuse_color_r = material_1.diffuse_color.r;
             m1_diffuse_color_g = material_1.diffuse_color.g;
             m1_diffuse_color_b = material_1.diffuse_color.b;
+            m1_diffuse_texture_enabled = !material_1.diffuse_texture.is_empty() as i32;
             m1_diffuse_texture_alpha_enabled = if material_1.diffuse_texture_alpha_disabled {
                 0
             } else {
@@ -184,14 +186,14 @@ impl RenderDeferredEffectUniform {
             m0_diffuse_color_r,
             m0_diffuse_color_g,
             m0_diffuse_color_b,
-            m0_diffuse_texture_enabled: 1, // TODO
+            m0_diffuse_texture_enabled: !effect.material_0.diffuse_texture.is_empty() as i32,
             m0_diffuse_texture_alpha_enabled,
             m0_alpha_mask_enabled,
             m1_enabled,
             m1_diffuse_color_r,
             m1_diffuse_color_g,
             m1_diffuse_color_b,
-            m1_diffuse_texture_enabled: 1, // TODO
+            m1_diffuse_texture_enabled,
             m1_diffuse_texture_alpha_enabled,
             m1_alpha_mask_enabled,
         })
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::{rc::Rc, sync::Arc};
 
 use glam::{Mat4, Vec3};
 
@@ -6,21 +6,30 @@ use crate::{
     asset_manager::{BiTreeAsset, ModelAsset, TextureAsset},
     renderer::{DrawCommands, camera::Camera},
     scene::vfx::VisualEffectNode,
+    vfx::VisualEffectAsset,
     xnb::asset::{color::Color, model::BoundingBox},
 };
 
 pub mod level;
-pub mod trigger;
+pub mod model;
 pub mod vfx;
 
 pub struct Scene {
     pub root_node: SceneNode,
     pub camera: Camera,
+    /// where `reset_camera` looks at, the level's spawn locator if it has one
+    pub spawn: Option<Vec3>,
 
     pub indoors: bool,
     pub skymap: Option<Skymap>,
 }
 
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Scene {
     pub fn new() -> Scene {
         Scene {
@@ -31,23 +40,35 @@ impl Scene {
                 children: Vec::new(),
                 kind: SceneNodeKind::Empty,
             },
-            camera: Camera {
-                position: Vec3::new(0.0, 5.0, 0.0),
-                pitch_radians: 0.0,
-                yaw_radians: 0.0,
-                fov_y_radians: 75.0f32.to_radians(),
-                z_near: 0.1,
-                z_far: 10000.0,
-            },
+            camera: default_camera(),
+            spawn: None,
             indoors: false,
             skymap: None,
         }
     }
 
+    /// puts the camera back above and behind `spawn`, looking at it
+    pub fn reset_camera(&mut self) {
+        self.camera = default_camera();
+        if let Some(spawn) = self.spawn {
+            self.camera.position = spawn + SPAWN_CAMERA_OFFSET;
+            self.camera.look_at(spawn);
+        }
+    }
+
     pub fn update(&mut self, dt: f32) {
         self.root_node.update(dt);
     }
 
+    /// points every effect node playing `old` at `new`, returns how many there were
+    pub fn replace_visual_effect(
+        &mut self,
+        old: &Arc<VisualEffectAsset>,
+        new: &Arc<VisualEffectAsset>,
+    ) -> usize {
+        self.root_node.replace_visual_effect(old, new)
+    }
+
     pub fn render(&mut self, draw_commands: &mut DrawCommands) {
         if !self.root_node.visible {
             return;
@@ -61,6 +82,20 @@ impl Scene {
     }
 }
 
+/// roughly where the game's own camera sits relative to the player
+const SPAWN_CAMERA_OFFSET: Vec3 = Vec3::new(0.0, 8.0, 8.0);
+
+fn default_camera() -> Camera {
+    Camera {
+        position: Vec3::new(0.0, 5.0, 0.0),
+        pitch_radians: 0.0,
+        yaw_radians: 0.0,
+        fov_y_radians: 75.0f32.to_radians(),
+        z_near: 0.1,
+        z_far: 10000.0,
+    }
+}
+
 pub struct SceneNode {
     pub name: String,
     pub visible: bool,
@@ -83,6 +118,25 @@ impl SceneNode {
         }
     }
 
+    fn replace_visual_effect(
+        &mut self,
+        old: &Arc<VisualEffectAsset>,
+        new: &Arc<VisualEffectAsset>,
+    ) -> usize {
+        let mut replaced = 0;
+        if let SceneNodeKind::VisualEffect(vfx_node) = &mut self.kind
+            && Arc::ptr_eq(&vfx_node.effect, old)
+        {
+            vfx_node.set_effect(new.clone());
+            replaced += 1;
+        }
+
+        for child in self.children.iter_mut() {
+            replaced += child.replace_visual_effect(old, new);
+        }
+        replaced
+    }
+
     pub fn render(&mut self, draw_commands: &mut DrawCommands, transform_stack: &mut Vec<Mat4>) {
         if !self.visible {
             return;
@@ -93,7 +147,9 @@ impl SceneNode {
         transform_stack.push(current_transform);
 
         match &mut self.kind {
-            SceneNodeKind::Model(model_node) => todo!(),
+            SceneNodeKind::Model(model_node) => {
+                draw_commands.add_model(model_node.clone(), current_transform);
+            }
             // TODO: it seems like bitree parent nodes draw all of the same mesh as their child nodes combined?
             // should i render just the parent nodes or just the leaf child nodes?
             SceneNodeKind::BiTree(bitree_node) => {
@@ -123,6 +179,8 @@ pub enum SceneNodeKind {
 #[derive(Clone)]
 pub struct ModelNode {
     pub model: Rc<ModelAsset>,
+    /// index into `ModelAsset::meshes`
+    pub mesh: usize,
 }
 
 #[derive(Clone)]
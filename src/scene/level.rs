@@ -1,11 +1,12 @@
-use std::path::Path;
+use std::{path::Path, rc::Rc};
 
-use roxmltree::Document;
+use glam::{Mat4, Vec3};
 
 use crate::{
-    asset_manager::AssetManager,
-    scene::{Scene, Skymap},
-    xnb::asset::color::Color,
+    asset_manager::{AssetManager, BiTreeAsset},
+    assets::level::{CpuAnimatedPart, CpuLevel, CpuLevelModel},
+    scene::{self, Scene, SceneNode, SceneNodeKind, Skymap, vfx::VisualEffectNode},
+    xnb::BiTreeNode,
 };
 
 impl Scene {
@@ -14,96 +15,105 @@ impl Scene {
         base_path: Option<&Path>,
         asset_manager: &mut AssetManager,
     ) -> anyhow::Result<Self> {
-        let xml = asset_manager.read_to_string(xml_path, base_path)?;
-        let doc = Document::parse(&xml)?;
+        let level = asset_manager.loader().load_level(xml_path, base_path)?;
+        Scene::from_level(&level, asset_manager)
+    }
 
-        let root = doc.root_element();
-        if root.tag_name().name() != "Scene" {
-            anyhow::bail!("expected root element to be an <Scene> node");
-        }
+    /// uploads a level loaded by `AssetLoader::load_level` and builds its scene
+    pub fn from_level(level: &CpuLevel, asset_manager: &mut AssetManager) -> anyhow::Result<Self> {
+        let model_node = level_model_node(&level.model, asset_manager)?;
+
+        let mut scene = Scene::new();
+
+        scene.root_node.children.push(model_node);
+        scene.skymap = level.skymap.as_ref().map(|skymap| Skymap {
+            texture: asset_manager.upload_texture(&skymap.texture),
+            color: skymap.color,
+        });
+        scene.indoors = level.indoors;
 
-        let mut model_path: Option<&str> = None;
-        let mut skymap_path: Option<&str> = None;
-        let mut skymap_color: Option<Color> = None;
-        let mut indoors: Option<bool> = None;
-
-        for child in root.children().filter(|child| child.is_element()) {
-            let child_name = child.tag_name().name().to_lowercase();
-
-            match child_name.as_str() {
-                "model" => {
-                    let text = child
-                        .text()
-                        .ok_or_else(|| anyhow::anyhow!("expected <Model> node to contain text"))?;
-                    model_path = Some(text);
-                }
-                "skymap" => {
-                    let text = child
-                        .text()
-                        .ok_or_else(|| anyhow::anyhow!("expected <Model> node to contain text"))?;
-
-                    let color_str = child.attribute("color").ok_or_else(|| {
-                        anyhow::anyhow!("expected <SkyMap> node to have a 'color' attribute")
-                    })?;
-                    let color = color_str
-                        .split(',')
-                        .map(|v| v.parse::<f32>())
-                        .collect::<Result<Vec<f32>, _>>()?;
-                    if color.len() != 3 {
-                        anyhow::bail!(
-                            "expected <SkyMap> node 'color' attribute to have 3 comma separated values"
-                        );
-                    }
-                    let color = Color {
-                        r: color[0],
-                        g: color[1],
-                        b: color[2],
-                    };
-
-                    skymap_path = Some(text);
-                    skymap_color = Some(color);
-                }
-                "indoor" => {
-                    let text = child
-                        .text()
-                        .ok_or_else(|| anyhow::anyhow!("expected <Model> node to contain text"))?;
-                    if text.eq_ignore_ascii_case("true") {
-                        indoors = Some(true);
-                    } else if text.eq_ignore_ascii_case("false") {
-                        indoors = Some(false);
-                    } else {
-                        anyhow::bail!(
-                            "expected <Indoor> node to have the text 'true' or 'false', got '{text}'"
-                        );
-                    }
-                }
-                _ => {}
-            }
+        if let Some(locator) = level.model.spawn_locator() {
+            log::debug!("spawning at locator '{}'", locator.name);
+            scene.spawn = Some(locator.transform.transform_point3(Vec3::ZERO));
         }
+        scene.reset_camera();
 
-        let skymap_texture = skymap_path
-            .map(|p| asset_manager.load_texture(Path::new(p), Some(xml_path)))
-            .transpose()?;
+        Ok(scene)
+    }
+}
 
-        let Some(model_path) = model_path else {
-            anyhow::bail!("xml does not have a <Model> node");
-        };
+fn level_model_node(
+    model: &CpuLevelModel,
+    asset_manager: &mut AssetManager,
+) -> anyhow::Result<SceneNode> {
+    let mut scene_node = SceneNode {
+        name: "Level Model".into(),
+        visible: true,
+        transform: Mat4::IDENTITY,
+        children: Vec::new(),
+        kind: SceneNodeKind::Empty,
+    };
 
-        let model_node = asset_manager.load_level_model(Path::new(model_path), Some(xml_path))?;
+    for (i, tree) in model.trees.iter().enumerate() {
+        let asset = asset_manager.upload_bitree(&format!("bitree {i}"), tree)?;
+        load_level_model_bitree_node_recursive(&mut scene_node, &tree.node, asset)?;
+    }
 
-        let mut scene = Scene::new();
+    for part in &model.animated_parts {
+        scene_node
+            .children
+            .push(animated_part_node(part, asset_manager)?);
+    }
 
-        scene.root_node.children.push(model_node);
-        scene.skymap = skymap_texture.map(|texture| Skymap {
-            texture,
-            color: skymap_color.unwrap_or(Color {
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-            }),
+    for placement in &model.visual_effects {
+        scene_node.children.push(SceneNode {
+            name: placement.name.clone(),
+            visible: true,
+            transform: placement.transform,
+            children: Vec::new(),
+            kind: SceneNodeKind::VisualEffect(VisualEffectNode::new(placement.effect.clone())),
         });
-        scene.indoors = indoors.unwrap_or(false);
+    }
 
-        Ok(scene)
+    Ok(scene_node)
+}
+
+fn animated_part_node(
+    part: &CpuAnimatedPart,
+    asset_manager: &mut AssetManager,
+) -> anyhow::Result<SceneNode> {
+    let model = asset_manager.upload_model(&part.name, &part.model)?;
+    let mut node = SceneNode::from_model(part.name.clone(), model);
+    for child in &part.children {
+        node.children
+            .push(animated_part_node(child, asset_manager)?);
     }
+    Ok(node)
+}
+
+fn load_level_model_bitree_node_recursive(
+    parent: &mut SceneNode,
+    bitree_node: &BiTreeNode,
+    bitree_asset: Rc<BiTreeAsset>,
+) -> anyhow::Result<()> {
+    let mut node = SceneNode {
+        name: "BiTree Node".into(),
+        visible: bitree_asset.visible,
+        transform: Mat4::IDENTITY,
+        children: Vec::new(),
+        kind: SceneNodeKind::BiTree(scene::BiTreeNode {
+            tree: bitree_asset.clone(),
+            start_index: bitree_node.start_index as u32,
+            index_count: bitree_node.primitive_count as u32 * 3,
+            bounding_box: bitree_node.bounding_box.clone(),
+        }),
+    };
+
+    for child in bitree_node.iter_children() {
+        load_level_model_bitree_node_recursive(&mut node, child, bitree_asset.clone())?;
+    }
+
+    parent.children.push(node);
+
+    Ok(())
 }
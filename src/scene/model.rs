@@ -0,0 +1,56 @@
+use std::rc::Rc;
+
+use glam::Mat4;
+
+use crate::{
+    asset_manager::ModelAsset,
+    scene::{ModelNode, SceneNode, SceneNodeKind},
+};
+
+impl SceneNode {
+    /// a node for every bone of `model`, posed as it was exported, with a node for each mesh
+    /// under the bone it's attached to
+    pub fn from_model(name: String, model: Rc<ModelAsset>) -> SceneNode {
+        let mut node = SceneNode {
+            name,
+            visible: true,
+            transform: Mat4::IDENTITY,
+            children: Vec::new(),
+            kind: SceneNodeKind::Empty,
+        };
+        add_bone_children(&mut node, None, &model);
+        node
+    }
+}
+
+/// adds the meshes and child bones of `bone` to `parent`, `None` being the root of the model
+fn add_bone_children(parent: &mut SceneNode, bone: Option<usize>, model: &Rc<ModelAsset>) {
+    for (i, mesh) in model.meshes.iter().enumerate() {
+        if mesh.bone == bone {
+            parent.children.push(SceneNode {
+                name: mesh.name.clone(),
+                visible: true,
+                transform: Mat4::IDENTITY,
+                children: Vec::new(),
+                kind: SceneNodeKind::Model(ModelNode {
+                    model: model.clone(),
+                    mesh: i,
+                }),
+            });
+        }
+    }
+
+    for (i, child) in model.bones.iter().enumerate() {
+        if child.parent == bone {
+            let mut node = SceneNode {
+                name: child.name.clone(),
+                visible: true,
+                transform: child.transform,
+                children: Vec::new(),
+                kind: SceneNodeKind::Empty,
+            };
+            add_bone_children(&mut node, Some(i), model);
+            parent.children.push(node);
+        }
+    }
+}
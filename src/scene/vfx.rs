@@ -1,18 +1,18 @@
 use std::{
     f32::consts::{PI, TAU},
-    rc::Rc,
+    sync::Arc,
 };
 
 use glam::{Mat4, Quat, Vec3};
 use rand::Rng;
 
 use crate::{
-    asset_manager::vfx::{SpreadType, VisualEffectAsset, emitter::ParticleEmitterKind},
     renderer::{DrawCommands, pipelines::particles::ParticleInstance},
+    vfx::{SpreadType, VisualEffectAsset, emitter::ParticleEmitterKind, lerp},
 };
 
 pub struct VisualEffectNode {
-    pub effect: Rc<VisualEffectAsset>,
+    pub effect: Arc<VisualEffectAsset>,
     pub particles: Vec<Particle>,
     /// each item in this list corresponds to the same index in `effect.emitters`
     pub emit_timers: Box<[f32]>,
@@ -22,7 +22,7 @@ pub struct VisualEffectNode {
 }
 
 impl VisualEffectNode {
-    pub fn new(effect: Rc<VisualEffectAsset>) -> Self {
+    pub fn new(effect: Arc<VisualEffectAsset>) -> Self {
         VisualEffectNode {
             particles: Vec::new(),
             emit_timers: vec![0.0; effect.emitters.len()].into_boxed_slice(),
@@ -33,6 +33,20 @@ impl VisualEffectNode {
         }
     }
 
+    /// switches to a reloaded version of the effect. particles already emitted are kept
+    pub fn set_effect(&mut self, effect: Arc<VisualEffectAsset>) {
+        let mut emit_timers = vec![0.0; effect.emitters.len()];
+        for (new, old) in emit_timers.iter_mut().zip(self.emit_timers.iter()) {
+            *new = *old;
+        }
+        self.emit_timers = emit_timers.into_boxed_slice();
+        self.animation_fps = effect.keyframes_per_second;
+        if self.animation_timer >= effect.duration {
+            self.animation_timer = 0.0;
+        }
+        self.effect = effect;
+    }
+
     pub fn update(&mut self, dt: f32, transform: Mat4) {
         let mut rng = rand::rng();
 
@@ -388,10 +402,6 @@ impl Particle {
     }
 }
 
-pub fn lerp(a: f32, b: f32, f: f32) -> f32 {
-    a + ((b - a) * f)
-}
-
 pub fn wrap_radians(angle: f32) -> f32 {
     let a = angle.rem_euclid(TAU);
     if a > PI { a - TAU } else { a }
@@ -103,11 +103,22 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return out;
 }
 
+// missing textures are bound as neutral fallbacks, see `FallbackTextures`
 @group(3) @binding(0)
 var diffuse_texture_0: texture_2d<f32>;
 @group(3) @binding(1)
-var diffuse_texture_1: texture_2d<f32>;
+var material_texture_0: texture_2d<f32>;
 @group(3) @binding(2)
+var normal_texture_0: texture_2d<f32>;
+@group(3) @binding(3)
+var diffuse_texture_1: texture_2d<f32>;
+@group(3) @binding(4)
+var material_texture_1: texture_2d<f32>;
+@group(3) @binding(5)
+var normal_texture_1: texture_2d<f32>;
+@group(3) @binding(6)
+var reflection_map: texture_cube<f32>;
+@group(3) @binding(7)
 var texture_sampler: sampler;
 
 @fragment
@@ -190,7 +201,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
 
-    // // TODO: skipped normal and material map stuff
+    // TODO: normal_texture_* and material_texture_* are bound but not sampled yet, nothing is lit
 
     var output_0 = vec4<f32>(0.0);
     var output_1 = vec4<f32>(0.0);
@@ -0,0 +1,384 @@
+//! where content is read from. paths are relative to the root of a magicka install, use '/'
+//! separators, and have the exact casing `Vfs::files` lists them with
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::Context;
+
+pub trait Vfs: Send + Sync {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>>;
+
+    /// size of the file in bytes
+    fn size(&self, path: &Path) -> anyhow::Result<u64>;
+
+    /// when the file was last changed, `None` if the backend can't tell
+    fn modified(&self, path: &Path) -> anyhow::Result<Option<SystemTime>>;
+
+    /// every file, in no particular order
+    fn files(&self) -> anyhow::Result<Vec<PathBuf>>;
+
+    /// where the file is read from, such as the directory or archive holding it. tells the same
+    /// path apart between installs and overlays
+    fn origin(&self, path: &Path) -> anyhow::Result<String>;
+
+    fn read_to_string(&self, path: &Path) -> anyhow::Result<String> {
+        let data = self.read(path)?;
+        String::from_utf8(data).with_context(|| format!("{} is not utf-8", path.display()))
+    }
+}
+
+/// opens a zip archive if `path` is a file ending in `.zip`, and a directory otherwise
+pub fn open(path: &Path) -> anyhow::Result<Box<dyn Vfs>> {
+    let is_zip = path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    if is_zip {
+        Ok(Box::new(ZipVfs::open(path)?))
+    } else {
+        Ok(Box::new(DirectoryVfs::new(path)))
+    }
+}
+
+/// a directory on disk, such as the magicka install itself
+pub struct DirectoryVfs {
+    root: PathBuf,
+    /// `root` made absolute, so the same directory has the same origin however it was given
+    origin: String,
+}
+
+impl DirectoryVfs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let origin = std::fs::canonicalize(&root)
+            .unwrap_or_else(|_| root.clone())
+            .to_string_lossy()
+            .into_owned();
+        DirectoryVfs { root, origin }
+    }
+}
+
+impl Vfs for DirectoryVfs {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let full_path = self.root.join(path);
+        std::fs::read(&full_path)
+            .with_context(|| format!("failed to read file {}", full_path.display()))
+    }
+
+    fn size(&self, path: &Path) -> anyhow::Result<u64> {
+        let full_path = self.root.join(path);
+        let metadata = std::fs::metadata(&full_path)
+            .with_context(|| format!("failed to read metadata of {}", full_path.display()))?;
+        Ok(metadata.len())
+    }
+
+    fn modified(&self, path: &Path) -> anyhow::Result<Option<SystemTime>> {
+        let full_path = self.root.join(path);
+        let metadata = std::fs::metadata(&full_path)
+            .with_context(|| format!("failed to read metadata of {}", full_path.display()))?;
+        Ok(metadata.modified().ok())
+    }
+
+    fn files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut pending = vec![PathBuf::new()];
+        while let Some(relative_dir) = pending.pop() {
+            let dir = self.root.join(&relative_dir);
+            let entries = std::fs::read_dir(&dir)
+                .with_context(|| format!("failed to read directory {}", dir.display()))?;
+            for entry in entries {
+                let entry = entry?;
+                let relative = relative_dir.join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                    pending.push(relative);
+                } else {
+                    files.push(relative);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn origin(&self, _path: &Path) -> anyhow::Result<String> {
+        Ok(self.origin.clone())
+    }
+}
+
+/// a zip archive, with the install root at the root of the archive
+pub struct ZipVfs {
+    archive: Mutex<zip::ZipArchive<File>>,
+    /// entry timestamps have no time zone, so every file is treated as modified along with the
+    /// archive itself
+    modified: Option<SystemTime>,
+    origin: String,
+}
+
+impl ZipVfs {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open archive {}", path.display()))?;
+        let modified = file.metadata().and_then(|m| m.modified()).ok();
+        let archive = zip::ZipArchive::new(file)
+            .with_context(|| format!("failed to read archive {}", path.display()))?;
+        let origin = std::fs::canonicalize(path)
+            .unwrap_or_else(|_| path.to_owned())
+            .to_string_lossy()
+            .into_owned();
+        Ok(ZipVfs {
+            archive: Mutex::new(archive),
+            modified,
+            origin,
+        })
+    }
+}
+
+/// zip entries always use '/'
+fn zip_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+impl Vfs for ZipVfs {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = archive
+            .by_name(&zip_name(path))
+            .with_context(|| format!("failed to find {} in archive", path.display()))?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn size(&self, path: &Path) -> anyhow::Result<u64> {
+        let mut archive = self.archive.lock().unwrap();
+        let file = archive
+            .by_name(&zip_name(path))
+            .with_context(|| format!("failed to find {} in archive", path.display()))?;
+        Ok(file.size())
+    }
+
+    fn modified(&self, _path: &Path) -> anyhow::Result<Option<SystemTime>> {
+        Ok(self.modified)
+    }
+
+    fn files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let archive = self.archive.lock().unwrap();
+        let files = archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(PathBuf::from)
+            .collect();
+        Ok(files)
+    }
+
+    fn origin(&self, _path: &Path) -> anyhow::Result<String> {
+        Ok(self.origin.clone())
+    }
+}
+
+/// files kept in memory
+#[derive(Default)]
+pub struct MemoryVfs {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryVfs {
+    pub fn insert(&mut self, path: impl Into<PathBuf>, data: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), data.into());
+    }
+}
+
+impl Vfs for MemoryVfs {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("file {} not found", path.display()))
+    }
+
+    fn size(&self, path: &Path) -> anyhow::Result<u64> {
+        self.files
+            .get(path)
+            .map(|data| data.len() as u64)
+            .ok_or_else(|| anyhow::anyhow!("file {} not found", path.display()))
+    }
+
+    fn modified(&self, path: &Path) -> anyhow::Result<Option<SystemTime>> {
+        if !self.files.contains_key(path) {
+            anyhow::bail!("file {} not found", path.display());
+        }
+        Ok(None)
+    }
+
+    fn files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn origin(&self, _path: &Path) -> anyhow::Result<String> {
+        Ok("memory".to_owned())
+    }
+}
+
+/// a stack of layers where later layers override files of earlier ones, so a mod only has to
+/// contain the files it changes. files are matched case-insensitively between layers, since
+/// mods rarely match the casing of the game's files
+pub struct OverlayVfs {
+    layers: Vec<Box<dyn Vfs>>,
+    /// lowercase path -> layer index and the path within that layer
+    files: HashMap<String, (usize, PathBuf)>,
+}
+
+impl OverlayVfs {
+    /// `layers` are ordered from the base game to the highest priority override
+    pub fn new(layers: Vec<Box<dyn Vfs>>) -> anyhow::Result<Self> {
+        let mut files: HashMap<String, (usize, PathBuf)> = HashMap::new();
+        for (i, layer) in layers.iter().enumerate() {
+            let mut layer_files = layer.files()?;
+            // sorted so the same path wins every time when a layer has paths differing only in
+            // case, like `ContentIndex::build`
+            layer_files.sort();
+
+            let mut collisions: HashMap<String, Vec<PathBuf>> = HashMap::new();
+            for path in layer_files {
+                let key = overlay_key(&path);
+                match files.get(&key) {
+                    Some((layer, existing)) if *layer == i => {
+                        collisions
+                            .entry(key)
+                            .or_insert_with(|| vec![existing.clone()])
+                            .push(path);
+                    }
+                    _ => {
+                        files.insert(key, (i, path));
+                    }
+                }
+            }
+
+            let mut collisions: Vec<Vec<PathBuf>> = collisions.into_values().collect();
+            collisions.sort();
+            for paths in collisions {
+                log::warn!(
+                    "ambiguous paths differing only in case in overlay layer {i}, using the first: {}",
+                    paths
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        Ok(OverlayVfs { layers, files })
+    }
+
+    fn find(&self, path: &Path) -> anyhow::Result<(&dyn Vfs, &Path)> {
+        let (layer, layer_path) = self
+            .files
+            .get(&overlay_key(path))
+            .ok_or_else(|| anyhow::anyhow!("file {} not found in any layer", path.display()))?;
+        Ok((self.layers[*layer].as_ref(), layer_path))
+    }
+}
+
+fn overlay_key(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .to_ascii_lowercase()
+}
+
+impl Vfs for OverlayVfs {
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let (layer, path) = self.find(path)?;
+        layer.read(path)
+    }
+
+    fn size(&self, path: &Path) -> anyhow::Result<u64> {
+        let (layer, path) = self.find(path)?;
+        layer.size(path)
+    }
+
+    fn modified(&self, path: &Path) -> anyhow::Result<Option<SystemTime>> {
+        let (layer, path) = self.find(path)?;
+        layer.modified(path)
+    }
+
+    fn files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        Ok(self.files.values().map(|(_, path)| path.clone()).collect())
+    }
+
+    /// the origin of the layer the file comes from, so adding a layer only changes the origin of
+    /// the files it overrides
+    fn origin(&self, path: &Path) -> anyhow::Result<String> {
+        let (layer, path) = self.find(path)?;
+        layer.origin(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(files: &[(&str, &str)]) -> Box<dyn Vfs> {
+        let mut vfs = MemoryVfs::default();
+        for (path, data) in files {
+            vfs.insert(*path, *data);
+        }
+        Box::new(vfs)
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let overlay = OverlayVfs::new(vec![
+            memory(&[
+                ("Content/Levels/a.xml", "base a"),
+                ("Content/Levels/b.xml", "base b"),
+            ]),
+            memory(&[("content/levels/A.xml", "mod a")]),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            overlay
+                .read_to_string(Path::new("Content/Levels/a.xml"))
+                .unwrap(),
+            "mod a"
+        );
+        assert_eq!(
+            overlay
+                .read_to_string(Path::new("content/levels/B.XML"))
+                .unwrap(),
+            "base b"
+        );
+
+        let mut files = overlay.files().unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("Content/Levels/b.xml"),
+                PathBuf::from("content/levels/A.xml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn case_collisions_within_a_layer_keep_the_first_path() {
+        // inserted in both orders, `MemoryVfs` lists its files in hash order
+        for files in [
+            [("Content/a.xml", "upper"), ("content/a.xml", "lower")],
+            [("content/a.xml", "lower"), ("Content/a.xml", "upper")],
+        ] {
+            let overlay = OverlayVfs::new(vec![memory(&files)]).unwrap();
+            assert_eq!(overlay.files().unwrap(), [PathBuf::from("Content/a.xml")]);
+            assert_eq!(
+                overlay.read_to_string(Path::new("CONTENT/A.XML")).unwrap(),
+                "upper"
+            );
+        }
+    }
+}
@@ -1,10 +1,17 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
 use anyhow::Context;
 use roxmltree::{Document, Node};
 
-use crate::{asset_manager::vfx::emitter::ParticleEmitter, scene::vfx::lerp};
+use crate::{vfs::Vfs, vfx::emitter::ParticleEmitter};
 
 pub mod emitter;
 
+#[derive(Debug, PartialEq)]
 pub struct VisualEffectAsset {
     pub kind: VisualEffectKind,
     pub duration: f32,
@@ -21,9 +28,9 @@ impl VisualEffectAsset {
         let doc = match Document::parse(xml) {
             Ok(v) => v,
             Err(roxmltree::Error::MalformedEntityReference(_)) if allow_retry => {
-                log::warn!("found malformed entity reference in xml, stripping and trying again");
-                let stripped = strip_malformed_reference(xml);
-                return VisualEffectAsset::read_xml_inner(&stripped, false);
+                log::warn!("found malformed entity reference in xml, escaping and trying again");
+                let escaped = escape_malformed_references(xml);
+                return VisualEffectAsset::read_xml_inner(&escaped, false);
             }
             Err(e) => Err(e)?,
         };
@@ -79,16 +86,39 @@ impl VisualEffectAsset {
             emitters,
         })
     }
+
+    /// writes the effect back in the schema `read_xml` accepts. every emitter property is written
+    /// out explicitly, defaults included, so the output doesn't depend on what the original left out
+    pub fn write_xml(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        let kind = match self.kind {
+            VisualEffectKind::Single => "Single",
+            VisualEffectKind::Looping => "Looping",
+            VisualEffectKind::Infinite => "Infinite",
+        };
+        writeln!(
+            out,
+            "<Effect type=\"{kind}\" duration=\"{}\" keyFramesPerSecond=\"{}\">",
+            self.duration, self.keyframes_per_second
+        )
+        .unwrap();
+        for emitter in &self.emitters {
+            emitter.write_xml(&mut out);
+        }
+        out.push_str("</Effect>\n");
+        out
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum VisualEffectKind {
     Single,
     Looping,
     Infinite,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct VisualEffectPropertyKeyframe {
     pub time: i32, // TODO: apparently times can be negative? what am i supposed to do with those?
     pub value: f32,
@@ -116,7 +146,7 @@ impl VisualEffectPropertyKeyframe {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum VisualEffectProperty {
     Constant(f32),
     Animated(Vec<VisualEffectPropertyKeyframe>),
@@ -159,6 +189,26 @@ impl VisualEffectProperty {
         Ok(VisualEffectProperty::Animated(keyframes))
     }
 
+    pub fn write_xml(&self, out: &mut String, name: &str, indent: &str) {
+        match self {
+            VisualEffectProperty::Constant(value) => {
+                writeln!(out, "{indent}<{name} value=\"{value}\" />").unwrap();
+            }
+            VisualEffectProperty::Animated(keyframes) => {
+                writeln!(out, "{indent}<{name}>").unwrap();
+                for keyframe in keyframes {
+                    writeln!(
+                        out,
+                        "{indent}  <Key time=\"{}\" value=\"{}\" />",
+                        keyframe.time, keyframe.value
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "{indent}</{name}>").unwrap();
+            }
+        }
+    }
+
     /// assumes keyframes are sorted by time
     pub fn interpolate(&self, current_time: f32, fps: u32) -> f32 {
         match self {
@@ -193,26 +243,34 @@ impl VisualEffectProperty {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum SpreadType {
     Arc,
     Cone,
 }
 
-// technically stripping all references but close enough unless it causes problems later
-fn strip_malformed_reference(xml: &str) -> String {
+/// escapes a string for use inside a double quoted xml attribute
+pub fn escape_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// escapes every `&` that doesn't start a valid reference, a few effects use a bare `&` in names
+pub fn escape_malformed_references(xml: &str) -> String {
     let mut out = String::with_capacity(xml.len());
 
-    let mut chars = xml.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c == '&' {
-            while let Some(&next) = chars.peek() {
-                if next.is_whitespace() || next == ';' {
-                    chars.next();
-                    break;
-                }
-                chars.next();
-            }
+    for (i, c) in xml.char_indices() {
+        if c == '&' && !starts_with_reference(&xml[i + 1..]) {
+            out.push_str("&amp;");
         } else {
             out.push(c);
         }
@@ -220,3 +278,114 @@ fn strip_malformed_reference(xml: &str) -> String {
 
     out
 }
+
+/// whether `s` (the text after an `&`) is a predefined entity or character reference
+fn starts_with_reference(s: &str) -> bool {
+    let Some(end) = s.find(';') else {
+        return false;
+    };
+    let name = &s[..end];
+
+    if let Some(hex) = name.strip_prefix("#x") {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit())
+    } else {
+        matches!(name, "amp" | "lt" | "gt" | "quot" | "apos")
+    }
+}
+
+pub fn lerp(a: f32, b: f32, f: f32) -> f32 {
+    a + ((b - a) * f)
+}
+
+/// maps effect names to the files under Content/Effects they are read from. effects are
+/// referenced by file stem, but can be in any subdirectory, so every file has to be listed once
+/// up front. parsing is left to whoever looks an effect up
+#[derive(Default)]
+pub struct VisualEffectIndex {
+    /// lowercase file stem -> path
+    paths: HashMap<String, PathBuf>,
+    duplicates: Vec<Vec<PathBuf>>,
+}
+
+impl VisualEffectIndex {
+    pub fn build(vfs: &dyn Vfs) -> anyhow::Result<Self> {
+        let mut files: Vec<PathBuf> = vfs
+            .files()?
+            .into_iter()
+            .filter(|path| is_effect_path(path))
+            .collect();
+        // sorted so the same file wins every time
+        files.sort();
+
+        let mut index = VisualEffectIndex::default();
+        let mut duplicates: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in files {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                log::warn!(
+                    "skipping visual effect with invalid name {}",
+                    path.display()
+                );
+                continue;
+            };
+            let name = name.to_ascii_lowercase();
+
+            if let Some(existing) = index.paths.get(&name) {
+                duplicates
+                    .entry(name)
+                    .or_insert_with(|| vec![existing.clone()])
+                    .push(path);
+            } else {
+                index.paths.insert(name, path);
+            }
+        }
+
+        let mut duplicates: Vec<Vec<PathBuf>> = duplicates.into_values().collect();
+        duplicates.sort();
+        for paths in &duplicates {
+            log::warn!(
+                "visual effects with the same name, using the first: {}",
+                paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        index.duplicates = duplicates;
+
+        log::debug!("indexed {} visual effects", index.paths.len());
+
+        Ok(index)
+    }
+
+    /// `name` is matched case-insensitively
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.paths
+            .get(&name.to_ascii_lowercase())
+            .map(PathBuf::as_path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// groups of effect files sharing a name. only the first of each can be looked up
+    pub fn duplicates(&self) -> &[Vec<PathBuf>] {
+        &self.duplicates
+    }
+}
+
+/// xml files anywhere under Content/Effects
+fn is_effect_path(path: &Path) -> bool {
+    let key = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .to_ascii_lowercase();
+    key.starts_with("content/effects/") && key.ends_with(".xml")
+}
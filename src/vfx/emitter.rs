@@ -1,7 +1,10 @@
+use std::fmt::Write;
+
 use roxmltree::Node;
 
-use crate::asset_manager::vfx::{SpreadType, VisualEffectProperty};
+use crate::vfx::{SpreadType, VisualEffectProperty, escape_attribute};
 
+#[derive(Debug, PartialEq)]
 pub struct ParticleEmitter {
     pub name: String, // names arent necessarily unique
     pub kind: ParticleEmitterKind,
@@ -60,15 +63,18 @@ pub struct ParticleEmitter {
     pub alpha_dist: VisualEffectProperty,
 }
 
+#[derive(Debug, PartialEq)]
 pub enum ParticleEmitterKind {
     Continuous(ContinuousEmitter),
     Pulse(PulseEmitter),
 }
 
+#[derive(Debug, PartialEq)]
 pub struct ContinuousEmitter {
     pub particles_per_second: VisualEffectProperty,
 }
 
+#[derive(Debug, PartialEq)]
 pub struct PulseEmitter {
     pub number_of_particles: u32,
 }
@@ -520,6 +526,123 @@ impl ParticleEmitter {
             sprite,
         })
     }
+
+    /// the animatable properties together with the tag they're read from, in the order they're written
+    pub fn properties(&self) -> [(&'static str, &VisualEffectProperty); 44] {
+        [
+            ("LifeTimeMin", &self.lifetime_min),
+            ("LifeTimeMax", &self.lifetime_max),
+            ("LifeTimeDistribution", &self.lifetime_dist),
+            (
+                "SpreadArcHorizontalAngle",
+                &self.spread_arc_horizontal_angle_degrees,
+            ),
+            (
+                "SpreadArcHorizontalDistribution",
+                &self.spread_arc_horizontal_angle_dist,
+            ),
+            (
+                "SpreadArcVerticalMin",
+                &self.spread_arc_vertical_angle_degrees_min,
+            ),
+            (
+                "SpreadArcVerticalMax",
+                &self.spread_arc_vertical_angle_degrees_max,
+            ),
+            (
+                "SpreadArcVerticalDistribution",
+                &self.spread_arc_vertical_angle_dist,
+            ),
+            ("SpreadConeAngle", &self.spread_cone_angle_degrees),
+            ("SpreadConeDistribution", &self.spread_cone_angle_dist),
+            ("PositionX", &self.position_x),
+            ("PositionY", &self.position_y),
+            ("PositionZ", &self.position_z),
+            ("PositionXOffset", &self.position_offset_x),
+            ("PositionYOffset", &self.position_offset_y),
+            ("PositionZOffset", &self.position_offset_z),
+            ("VelocityMin", &self.velocity_min),
+            ("VelocityMax", &self.velocity_max),
+            ("VelocityDistribution", &self.velocity_dist),
+            ("Drag", &self.drag),
+            ("Gravity", &self.gravity),
+            ("RotationMin", &self.rotation_degrees_min),
+            ("RotationMax", &self.rotation_degrees_max),
+            ("RotationSpeedMin", &self.rotation_speed_degrees_min),
+            ("RotationSpeedMax", &self.rotation_speed_degrees_max),
+            ("RotationPCCW", &self.rotation_ccw_chance),
+            ("SizeStartMin", &self.size_start_min),
+            ("SizeStartMax", &self.size_start_max),
+            ("SizeStartDistribution", &self.size_start_dist),
+            ("SizeEndMin", &self.size_end_min),
+            ("SizeEndMax", &self.size_end_max),
+            ("SizeEndDistribution", &self.size_end_dist),
+            ("HueMin", &self.hue_min),
+            ("HueMax", &self.hue_max),
+            ("HueDistribution", &self.hue_dist),
+            ("SatMin", &self.saturation_min),
+            ("SatMax", &self.saturation_max),
+            ("SatDistribution", &self.saturation_dist),
+            ("ValueMin", &self.value_min),
+            ("ValueMax", &self.value_max),
+            ("ValueDistribution", &self.value_dist),
+            ("AlphaMin", &self.alpha_min),
+            ("AlphaMax", &self.alpha_max),
+            ("AlphaDistribution", &self.alpha_dist),
+        ]
+    }
+
+    pub fn write_xml(&self, out: &mut String) {
+        let indent = "    ";
+        let tag_name = match &self.kind {
+            ParticleEmitterKind::Continuous(_) => "ContinuousEmitter",
+            ParticleEmitterKind::Pulse(_) => "PulseEmitter",
+        };
+        writeln!(
+            out,
+            "  <{tag_name} name=\"{}\">",
+            escape_attribute(&self.name)
+        )
+        .unwrap();
+
+        match &self.kind {
+            ParticleEmitterKind::Continuous(emitter) => {
+                emitter
+                    .particles_per_second
+                    .write_xml(out, "ParticlesPerSecond", indent);
+            }
+            ParticleEmitterKind::Pulse(emitter) => {
+                writeln!(
+                    out,
+                    "{indent}<NrOfParticles value=\"{}\" />",
+                    emitter.number_of_particles
+                )
+                .unwrap();
+            }
+        }
+
+        let blend_mode = if self.additive_blend {
+            "Additive"
+        } else {
+            "Alpha"
+        };
+        let spread_type = match self.spread_type {
+            SpreadType::Arc => "Arc",
+            SpreadType::Cone => "Cone",
+        };
+        writeln!(out, "{indent}<Particle value=\"{}\" />", self.sprite).unwrap();
+        writeln!(out, "{indent}<BlendMode value=\"{blend_mode}\" />").unwrap();
+        writeln!(out, "{indent}<SpreadType value=\"{spread_type}\" />").unwrap();
+        // normalized to <HSV>, the inverse <ColorControlAlpha> is only ever read
+        writeln!(out, "{indent}<HSV value=\"{}\" />", self.hsv).unwrap();
+        writeln!(out, "{indent}<Colorize value=\"{}\" />", self.colorize).unwrap();
+
+        for (name, property) in self.properties() {
+            property.write_xml(out, name, indent);
+        }
+
+        writeln!(out, "  </{tag_name}>").unwrap();
+    }
 }
 
 impl ContinuousEmitter {
@@ -0,0 +1,83 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+/// the inverse of `MyReadBytesExt`
+pub trait MyWriteBytesExt: WriteBytesExt {
+    fn write_bool(&mut self, value: bool) -> std::io::Result<()>;
+    fn write_7bit_encoded_i32(&mut self, value: i32) -> std::io::Result<()>;
+    fn write_7bit_length_string(&mut self, value: &str) -> std::io::Result<()>;
+    fn write_vec2(&mut self, value: Vec2) -> std::io::Result<()>;
+    fn write_vec3(&mut self, value: Vec3) -> std::io::Result<()>;
+    fn write_mat4(&mut self, value: Mat4) -> std::io::Result<()>;
+    fn write_quat(&mut self, value: Quat) -> std::io::Result<()>;
+}
+
+impl<W: WriteBytesExt> MyWriteBytesExt for W {
+    fn write_bool(&mut self, value: bool) -> std::io::Result<()> {
+        self.write_u8(value as u8)
+    }
+
+    fn write_7bit_encoded_i32(&mut self, value: i32) -> std::io::Result<()> {
+        let mut value = value as u32;
+
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.write_u8(byte)?;
+                break;
+            }
+
+            self.write_u8(byte | 0x80)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_7bit_length_string(&mut self, value: &str) -> std::io::Result<()> {
+        // strings are read one byte per char, so write them back the same way
+        let bytes = value
+            .chars()
+            .map(|c| {
+                u8::try_from(c).map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("'{c}' in {value:?} doesn't fit in one byte"),
+                    )
+                })
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        self.write_7bit_encoded_i32(bytes.len() as i32)?;
+        self.write_all(&bytes)
+    }
+
+    fn write_vec2(&mut self, value: Vec2) -> std::io::Result<()> {
+        self.write_f32::<LittleEndian>(value.x)?;
+        self.write_f32::<LittleEndian>(value.y)?;
+        Ok(())
+    }
+
+    fn write_vec3(&mut self, value: Vec3) -> std::io::Result<()> {
+        self.write_f32::<LittleEndian>(value.x)?;
+        self.write_f32::<LittleEndian>(value.y)?;
+        self.write_f32::<LittleEndian>(value.z)?;
+        Ok(())
+    }
+
+    fn write_mat4(&mut self, value: Mat4) -> std::io::Result<()> {
+        // xna matrices are row major
+        for v in value.transpose().to_cols_array() {
+            self.write_f32::<LittleEndian>(v)?;
+        }
+        Ok(())
+    }
+
+    fn write_quat(&mut self, value: Quat) -> std::io::Result<()> {
+        self.write_f32::<LittleEndian>(value.x)?;
+        self.write_f32::<LittleEndian>(value.y)?;
+        self.write_f32::<LittleEndian>(value.z)?;
+        self.write_f32::<LittleEndian>(value.w)?;
+        Ok(())
+    }
+}
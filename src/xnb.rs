@@ -1,34 +1,36 @@
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use lzxd::Lzxd;
 use std::{
     borrow::Cow,
-    io::{Cursor, Read, Seek},
+    io::{Cursor, Read, Seek, Write},
 };
 
-use crate::{read_ext::MyReadBytesExt, xnb::asset::XnbAsset};
+use crate::{read_ext::MyReadBytesExt, write_ext::MyWriteBytesExt, xnb::asset::XnbAsset};
 
 pub mod asset;
+pub mod trace;
 
 pub use asset::bi_tree_model::{BiTree, BiTreeNode};
 pub use asset::model::Model;
 pub use asset::render_deferred_effect::RenderDeferredEffect;
 pub use asset::texture_2d::Texture2D;
 pub use asset::texture_3d::Texture3D;
+pub use asset::texture_cube::TextureCube;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub enum Platform {
     Windows,
     WindowsPhone,
     Xbox360,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub enum Version {
     Xna31,
     Xna40,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Header {
     pub platform: Platform,
     pub version: Version,
@@ -38,17 +40,36 @@ pub struct Header {
     pub uncompressed_size: u32,
 }
 
+#[derive(Debug, serde::Serialize)]
 pub struct TypeReader {
     pub name: String,
     pub version: i32,
 }
 
+#[derive(serde::Serialize)]
 pub struct XnbContent {
     pub type_readers: Vec<TypeReader>,
     pub primary_asset: XnbAsset,
     pub shared_assets: Vec<XnbAsset>,
 }
 
+impl XnbContent {
+    /// writes an uncompressed content stream, the inverse of `Xnb::read_content`
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        Xnb::write_type_readers(writer, &self.type_readers)?;
+
+        writer.write_7bit_encoded_i32(self.shared_assets.len() as i32)?;
+
+        self.primary_asset.write(writer, &self.type_readers)?;
+
+        for asset in &self.shared_assets {
+            asset.write(writer, &self.type_readers)?;
+        }
+
+        Ok(())
+    }
+}
+
 pub struct Xnb {
     pub header: Header,
     pub data: Vec<u8>,
@@ -108,6 +129,54 @@ impl Xnb {
         Ok(xnb)
     }
 
+    /// builds an xnb around an already serialized content stream. content is never compressed
+    pub fn from_content(platform: Platform, hi_def: bool, data: Vec<u8>) -> Self {
+        Xnb {
+            header: Header {
+                platform,
+                version: Version::Xna31,
+                hi_def,
+                compressed: false,
+                compressed_size: data.len() as u32 + 10,
+                uncompressed_size: 0,
+            },
+            data,
+        }
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_all(b"XNB")?;
+
+        writer.write_u8(match self.header.platform {
+            Platform::Windows => b'w',
+            Platform::WindowsPhone => b'm',
+            Platform::Xbox360 => b'x',
+        })?;
+
+        writer.write_u8(match self.header.version {
+            Version::Xna31 => 4,
+            Version::Xna40 => 5,
+        })?;
+
+        let mut flags = 0;
+        if self.header.hi_def {
+            flags |= 0x01;
+        }
+        if self.header.compressed {
+            flags |= 0x80;
+        }
+        writer.write_u8(flags)?;
+
+        let header_size = if self.header.compressed { 14 } else { 10 };
+        writer.write_u32::<LittleEndian>(self.data.len() as u32 + header_size)?;
+        if self.header.compressed {
+            writer.write_u32::<LittleEndian>(self.header.uncompressed_size)?;
+        }
+
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
     pub fn decompress(&self) -> anyhow::Result<Cow<'_, [u8]>> {
         if !self.header.compressed {
             return Ok(Cow::from(&self.data));
@@ -154,23 +223,22 @@ impl Xnb {
 
     pub fn parse_content_from(decompressed: &[u8]) -> anyhow::Result<XnbContent> {
         let mut reader = Cursor::new(decompressed);
+        Xnb::read_content(&mut reader)
+    }
 
-        let type_reader_count = reader.read_7bit_encoded_i32()? as usize;
-        let mut type_readers = Vec::with_capacity(type_reader_count);
-        for _ in 0..type_reader_count {
-            let name = reader.read_7bit_length_string()?;
-            let version = reader.read_i32::<LittleEndian>()?;
-            let type_reader = TypeReader { name, version };
-            type_readers.push(type_reader);
-        }
+    /// reads the type readers and assets from a decompressed content stream
+    pub fn read_content(reader: &mut impl Read) -> anyhow::Result<XnbContent> {
+        let type_readers = trace::field("type_readers", || Xnb::read_type_readers(reader))?;
 
-        let shared_asset_count = reader.read_7bit_encoded_i32()?;
+        let shared_asset_count =
+            trace::field("shared_asset_count", || reader.read_7bit_encoded_i32())?;
 
-        let primary_asset = XnbAsset::read(&mut reader, &type_readers)?;
+        let primary_asset =
+            trace::field("primary_asset", || XnbAsset::read(reader, &type_readers))?;
 
         let mut shared_assets = Vec::with_capacity(shared_asset_count as usize);
         for _ in 0..shared_asset_count {
-            let asset = XnbAsset::read(&mut reader, &type_readers)?;
+            let asset = trace::field("shared_asset", || XnbAsset::read(reader, &type_readers))?;
             shared_assets.push(asset);
         }
 
@@ -181,4 +249,30 @@ impl Xnb {
         };
         Ok(content)
     }
+
+    pub fn write_type_readers(
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        writer.write_7bit_encoded_i32(type_readers.len() as i32)?;
+        for type_reader in type_readers {
+            writer.write_7bit_length_string(&type_reader.name)?;
+            writer.write_i32::<LittleEndian>(type_reader.version)?;
+        }
+        Ok(())
+    }
+
+    /// reads the type reader table at the start of a decompressed content stream
+    pub fn read_type_readers(reader: &mut impl Read) -> anyhow::Result<Vec<TypeReader>> {
+        let type_reader_count =
+            trace::field("type_reader_count", || reader.read_7bit_encoded_i32())? as usize;
+        let mut type_readers = Vec::with_capacity(type_reader_count);
+        for _ in 0..type_reader_count {
+            let name = trace::field("name", || reader.read_7bit_length_string())?;
+            let version = trace::field("version", || reader.read_i32::<LittleEndian>())?;
+            let type_reader = TypeReader { name, version };
+            type_readers.push(type_reader);
+        }
+        Ok(type_readers)
+    }
 }
@@ -1,15 +1,18 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
 use crate::{
     read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
     xnb::{
         TypeReader,
         asset::{
             additive_effect::AdditiveEffect, bi_tree_model::BiTreeModel, index_buffer::IndexBuffer,
             level_model::LevelModel, model::Model, render_deferred_effect::RenderDeferredEffect,
             render_deferred_liquid_effect::RenderDeferredLiquidEffect, texture_2d::Texture2D,
-            texture_3d::Texture3D, vertex_buffer::VertexBuffer, vertex_decl::VertexDeclaration,
+            texture_3d::Texture3D, texture_cube::TextureCube, vertex_buffer::VertexBuffer,
+            vertex_decl::VertexDeclaration,
         },
+        trace,
     },
 };
 
@@ -24,6 +27,7 @@ pub mod render_deferred_effect;
 pub mod render_deferred_liquid_effect;
 pub mod texture_2d;
 pub mod texture_3d;
+pub mod texture_cube;
 pub mod vertex_buffer;
 pub mod vertex_decl;
 
@@ -31,6 +35,7 @@ const STRING_READER_NAME: &str = "Microsoft.Xna.Framework.Content.StringReader";
 const LIST_READER_NAME: &str = "Microsoft.Xna.Framework.Content.ListReader";
 const TEXTURE_2D_READER_NAME: &str = "Microsoft.Xna.Framework.Content.Texture2DReader";
 const TEXTURE_3D_READER_NAME: &str = "Microsoft.Xna.Framework.Content.Texture3DReader";
+const TEXTURE_CUBE_READER_NAME: &str = "Microsoft.Xna.Framework.Content.TextureCubeReader";
 const MODEL_READER_NAME: &str = "Microsoft.Xna.Framework.Content.ModelReader";
 const VERTEX_DECL_READER_NAME: &str = "Microsoft.Xna.Framework.Content.VertexDeclarationReader";
 const VERTEX_BUFFER_READER_NAME: &str = "Microsoft.Xna.Framework.Content.VertexBufferReader";
@@ -44,12 +49,62 @@ const RENDER_DEFERRED_LIQUID_EFFECT_READER_NAME: &str =
 
 const LEVEL_MODEL_READER_NAME: &str = "Magicka.ContentReaders.LevelModelReader";
 
-#[derive(strum::AsRefStr, Debug)]
+const KNOWN_READER_NAMES: &[&str] = &[
+    STRING_READER_NAME,
+    LIST_READER_NAME,
+    TEXTURE_2D_READER_NAME,
+    TEXTURE_3D_READER_NAME,
+    TEXTURE_CUBE_READER_NAME,
+    MODEL_READER_NAME,
+    VERTEX_DECL_READER_NAME,
+    VERTEX_BUFFER_READER_NAME,
+    INDEX_BUFFER_READER_NAME,
+    BI_TREE_MODEL_READER_NAME,
+    ADDITIVE_EFFECT_READER_NAME,
+    RENDER_DEFERRED_EFFECT_READER_NAME,
+    RENDER_DEFERRED_LIQUID_EFFECT_READER_NAME,
+    LEVEL_MODEL_READER_NAME,
+];
+
+/// strips the assembly information (and generic arguments) from a type reader name
+pub fn type_reader_base_name(name: &str) -> &str {
+    let name = name.split(',').next().unwrap();
+    name.split('`').next().unwrap()
+}
+
+/// whether `name` is a type reader that can be handled by `XnbAsset::read`
+/// or is otherwise read inline by another asset (such as lists)
+pub fn is_known_type_reader(name: &str) -> bool {
+    KNOWN_READER_NAMES.contains(&type_reader_base_name(name))
+}
+
+/// serializes raw buffer data as a hex string instead of a (much larger) list of numbers
+pub fn serialize_hex<S: serde::Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_hex(data))
+}
+
+pub fn serialize_hex_list<S: serde::Serializer>(
+    data: &[Vec<u8>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(data.iter().map(|d| to_hex(d)))
+}
+
+fn to_hex(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+    for byte in data {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+#[derive(strum::AsRefStr, Debug, serde::Serialize)]
 pub enum XnbAsset {
     Null,
     String(String),
     Texture2D(Texture2D),
     Texture3D(Texture3D),
+    TextureCube(TextureCube),
     Model(Model),
     VertexDeclaration(VertexDeclaration),
     VertexBuffer(VertexBuffer),
@@ -63,7 +118,7 @@ pub enum XnbAsset {
 
 impl XnbAsset {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let type_id = reader.read_7bit_encoded_i32()? as usize;
+        let type_id = trace::field("type_id", || reader.read_7bit_encoded_i32())? as usize;
         if type_id == 0 {
             return Ok(XnbAsset::Null);
         }
@@ -72,51 +127,55 @@ impl XnbAsset {
         let name = type_reader.name.split(',').next().unwrap();
         match name {
             STRING_READER_NAME => {
-                let string = reader.read_7bit_length_string()?;
+                let string = trace::field("string", || reader.read_7bit_length_string())?;
                 Ok(XnbAsset::String(string))
             }
             TEXTURE_2D_READER_NAME => {
-                let texture = Texture2D::read(reader)?;
+                let texture = trace::field("texture", || Texture2D::read(reader))?;
                 Ok(XnbAsset::Texture2D(texture))
             }
             TEXTURE_3D_READER_NAME => {
-                let texture = Texture3D::read(reader)?;
+                let texture = trace::field("texture", || Texture3D::read(reader))?;
                 Ok(XnbAsset::Texture3D(texture))
             }
+            TEXTURE_CUBE_READER_NAME => {
+                let texture = trace::field("texture", || TextureCube::read(reader))?;
+                Ok(XnbAsset::TextureCube(texture))
+            }
             MODEL_READER_NAME => {
-                let model = Model::read(reader, type_readers)?;
+                let model = trace::field("model", || Model::read(reader, type_readers))?;
                 Ok(XnbAsset::Model(model))
             }
             VERTEX_DECL_READER_NAME => {
-                let decl = VertexDeclaration::read(reader)?;
+                let decl = trace::field("decl", || VertexDeclaration::read(reader))?;
                 Ok(XnbAsset::VertexDeclaration(decl))
             }
             VERTEX_BUFFER_READER_NAME => {
-                let buffer = VertexBuffer::read(reader)?;
+                let buffer = trace::field("buffer", || VertexBuffer::read(reader))?;
                 Ok(XnbAsset::VertexBuffer(buffer))
             }
             INDEX_BUFFER_READER_NAME => {
-                let buffer = IndexBuffer::read(reader)?;
+                let buffer = trace::field("buffer", || IndexBuffer::read(reader))?;
                 Ok(XnbAsset::IndexBuffer(buffer))
             }
             BI_TREE_MODEL_READER_NAME => {
-                let model = BiTreeModel::read(reader, type_readers)?;
+                let model = trace::field("model", || BiTreeModel::read(reader, type_readers))?;
                 Ok(XnbAsset::BiTreeModel(model))
             }
             ADDITIVE_EFFECT_READER_NAME => {
-                let effect = AdditiveEffect::read(reader)?;
+                let effect = trace::field("effect", || AdditiveEffect::read(reader))?;
                 Ok(XnbAsset::AdditiveEffect(effect))
             }
             RENDER_DEFERRED_EFFECT_READER_NAME => {
-                let effect = RenderDeferredEffect::read(reader)?;
+                let effect = trace::field("effect", || RenderDeferredEffect::read(reader))?;
                 Ok(XnbAsset::RenderDeferredEffect(effect))
             }
             RENDER_DEFERRED_LIQUID_EFFECT_READER_NAME => {
-                let effect = RenderDeferredLiquidEffect::read(reader)?;
+                let effect = trace::field("effect", || RenderDeferredLiquidEffect::read(reader))?;
                 Ok(XnbAsset::RenderDeferredLiquidEffect(effect))
             }
             LEVEL_MODEL_READER_NAME => {
-                let model = LevelModel::read(reader, type_readers)?;
+                let model = trace::field("model", || LevelModel::read(reader, type_readers))?;
                 Ok(XnbAsset::LevelModel(model))
             }
             _ => {
@@ -124,4 +183,67 @@ impl XnbAsset {
             }
         }
     }
+
+    /// writes the type id followed by the asset. `type_readers` must contain the reader for this asset
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        let reader_name = match self {
+            XnbAsset::Null => {
+                writer.write_7bit_encoded_i32(0)?;
+                return Ok(());
+            }
+            XnbAsset::String(_) => STRING_READER_NAME,
+            XnbAsset::Texture2D(_) => TEXTURE_2D_READER_NAME,
+            XnbAsset::Texture3D(_) => TEXTURE_3D_READER_NAME,
+            XnbAsset::TextureCube(_) => TEXTURE_CUBE_READER_NAME,
+            XnbAsset::Model(_) => MODEL_READER_NAME,
+            XnbAsset::VertexDeclaration(_) => VERTEX_DECL_READER_NAME,
+            XnbAsset::VertexBuffer(_) => VERTEX_BUFFER_READER_NAME,
+            XnbAsset::IndexBuffer(_) => INDEX_BUFFER_READER_NAME,
+            XnbAsset::BiTreeModel(_) => BI_TREE_MODEL_READER_NAME,
+            XnbAsset::AdditiveEffect(_) => ADDITIVE_EFFECT_READER_NAME,
+            XnbAsset::RenderDeferredEffect(_) => RENDER_DEFERRED_EFFECT_READER_NAME,
+            XnbAsset::RenderDeferredLiquidEffect(_) => RENDER_DEFERRED_LIQUID_EFFECT_READER_NAME,
+            XnbAsset::LevelModel(_) => LEVEL_MODEL_READER_NAME,
+        };
+        write_type_id(writer, type_readers, reader_name)?;
+
+        match self {
+            XnbAsset::Null => {}
+            XnbAsset::String(string) => writer.write_7bit_length_string(string)?,
+            XnbAsset::Texture2D(texture) => texture.write(writer)?,
+            XnbAsset::Texture3D(texture) => texture.write(writer)?,
+            XnbAsset::TextureCube(texture) => texture.write(writer)?,
+            XnbAsset::Model(model) => model.write(writer, type_readers)?,
+            XnbAsset::VertexDeclaration(decl) => decl.write(writer)?,
+            XnbAsset::VertexBuffer(buffer) => buffer.write(writer)?,
+            XnbAsset::IndexBuffer(buffer) => buffer.write(writer)?,
+            XnbAsset::BiTreeModel(model) => model.write(writer, type_readers)?,
+            XnbAsset::AdditiveEffect(effect) => effect.write(writer)?,
+            XnbAsset::RenderDeferredEffect(effect) => effect.write(writer)?,
+            XnbAsset::RenderDeferredLiquidEffect(effect) => effect.write(writer)?,
+            XnbAsset::LevelModel(model) => model.write(writer, type_readers)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// writes the 1-based index of the type reader named `reader_name`, for assets that are read
+/// inline by another asset instead of through `XnbAsset::read`
+fn write_type_id(
+    writer: &mut impl Write,
+    type_readers: &[TypeReader],
+    reader_name: &str,
+) -> anyhow::Result<()> {
+    let type_id = type_readers
+        .iter()
+        .position(|r| type_reader_base_name(&r.name) == reader_name)
+        .ok_or_else(|| anyhow::anyhow!("no type reader for {reader_name}"))?
+        + 1;
+    writer.write_7bit_encoded_i32(type_id as i32)?;
+    Ok(())
 }
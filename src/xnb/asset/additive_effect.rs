@@ -1,8 +1,12 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::{read_ext::MyReadBytesExt, xnb::asset::color::Color};
+use crate::{
+    read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
+    xnb::{asset::color::Color, trace},
+};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AdditiveEffect {
     pub color_tint: Color,
     pub vertex_color_enabled: bool,
@@ -12,10 +16,10 @@ pub struct AdditiveEffect {
 
 impl AdditiveEffect {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let color_tint = Color::read(reader)?;
-        let vertex_color_enabled = reader.read_bool()?;
-        let texture_enabled = reader.read_bool()?;
-        let texture = reader.read_7bit_length_string()?;
+        let color_tint = trace::field("color_tint", || Color::read(reader))?;
+        let vertex_color_enabled = trace::field("vertex_color_enabled", || reader.read_bool())?;
+        let texture_enabled = trace::field("texture_enabled", || reader.read_bool())?;
+        let texture = trace::field("texture", || reader.read_7bit_length_string())?;
         Ok(AdditiveEffect {
             color_tint,
             vertex_color_enabled,
@@ -23,4 +27,12 @@ impl AdditiveEffect {
             texture,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        self.color_tint.write(writer)?;
+        writer.write_bool(self.vertex_color_enabled)?;
+        writer.write_bool(self.texture_enabled)?;
+        writer.write_7bit_length_string(&self.texture)?;
+        Ok(())
+    }
 }
@@ -1,36 +1,47 @@
-use std::{collections::HashMap, io::Read};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use glam::{Quat, Vec3};
 
-use crate::read_ext::MyReadBytesExt;
+use crate::{read_ext::MyReadBytesExt, write_ext::MyWriteBytesExt, xnb::trace};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AnimationClip {
     pub name: String,
     pub duration: f32,
     pub channels: HashMap<String, AnimationChannel>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AnimationChannel {
     pub keyframes: Vec<AnimationKeyframe>,
 }
 
 impl AnimationChannel {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let num_frames = reader.read_i32::<LittleEndian>()?;
+        let num_frames = trace::field("num_frames", || reader.read_i32::<LittleEndian>())?;
         let mut keyframes = Vec::with_capacity(num_frames as usize);
         for _ in 0..num_frames {
-            let keyframe = AnimationKeyframe::read(reader)?;
+            let keyframe = trace::field("keyframe", || AnimationKeyframe::read(reader))?;
             keyframes.push(keyframe);
         }
 
         Ok(AnimationChannel { keyframes })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_i32::<LittleEndian>(self.keyframes.len() as i32)?;
+        for keyframe in &self.keyframes {
+            keyframe.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AnimationKeyframe {
     pub time: f32,
     pub pose: AnimationPose,
@@ -38,14 +49,20 @@ pub struct AnimationKeyframe {
 
 impl AnimationKeyframe {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let time = reader.read_f32::<LittleEndian>()?;
-        let pose = AnimationPose::read(reader)?;
+        let time = trace::field("time", || reader.read_f32::<LittleEndian>())?;
+        let pose = trace::field("pose", || AnimationPose::read(reader))?;
 
         Ok(AnimationKeyframe { time, pose })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_f32::<LittleEndian>(self.time)?;
+        self.pose.write(writer)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AnimationPose {
     pub translation: Vec3,
     pub orientation: Quat,
@@ -54,9 +71,9 @@ pub struct AnimationPose {
 
 impl AnimationPose {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let translation = reader.read_vec3()?;
-        let orientation = reader.read_quat()?;
-        let scale = reader.read_vec3()?;
+        let translation = trace::field("translation", || reader.read_vec3())?;
+        let orientation = trace::field("orientation", || reader.read_quat())?;
+        let scale = trace::field("scale", || reader.read_vec3())?;
 
         Ok(AnimationPose {
             translation,
@@ -64,4 +81,11 @@ impl AnimationPose {
             scale,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_vec3(self.translation)?;
+        writer.write_quat(self.orientation)?;
+        writer.write_vec3(self.scale)?;
+        Ok(())
+    }
 }
@@ -1,29 +1,32 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use crate::{
     read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
     xnb::{
         TypeReader,
         asset::{
-            XnbAsset, index_buffer::IndexBuffer, model::BoundingBox, vertex_buffer::VertexBuffer,
-            vertex_decl::VertexDeclaration,
+            INDEX_BUFFER_READER_NAME, VERTEX_BUFFER_READER_NAME, VERTEX_DECL_READER_NAME, XnbAsset,
+            index_buffer::IndexBuffer, model::BoundingBox, vertex_buffer::VertexBuffer,
+            vertex_decl::VertexDeclaration, write_type_id,
         },
+        trace,
     },
 };
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct BiTreeModel {
     pub trees: Vec<BiTree>,
 }
 
 impl BiTreeModel {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let num_trees = reader.read_i32::<LittleEndian>()?;
+        let num_trees = trace::field("num_trees", || reader.read_i32::<LittleEndian>())?;
         let mut trees = Vec::with_capacity(num_trees as usize);
         for _ in 0..num_trees {
-            let tree = BiTree::read(reader, type_readers)?;
+            let tree = trace::field("tree", || BiTree::read(reader, type_readers))?;
             trees.push(tree);
         }
 
@@ -33,9 +36,21 @@ impl BiTreeModel {
 
         Ok(BiTreeModel { trees })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        writer.write_i32::<LittleEndian>(self.trees.len() as i32)?;
+        for tree in &self.trees {
+            tree.write(writer, type_readers)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct BiTree {
     pub visible: bool,
     pub cast_shadows: bool,
@@ -53,33 +68,34 @@ pub struct BiTree {
 
 impl BiTree {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let visible = reader.read_bool()?;
-        let cast_shadows = reader.read_bool()?;
-        let sway = reader.read_f32::<LittleEndian>()?;
-        let entity_influence = reader.read_f32::<LittleEndian>()?;
-        let ground_level = reader.read_f32::<LittleEndian>()?;
-        let num_vertices = reader.read_i32::<LittleEndian>()?;
-        let vertex_stride = reader.read_i32::<LittleEndian>()?;
-
-        let vertex_decl = XnbAsset::read(reader, type_readers)?;
+        let visible = trace::field("visible", || reader.read_bool())?;
+        let cast_shadows = trace::field("cast_shadows", || reader.read_bool())?;
+        let sway = trace::field("sway", || reader.read_f32::<LittleEndian>())?;
+        let entity_influence =
+            trace::field("entity_influence", || reader.read_f32::<LittleEndian>())?;
+        let ground_level = trace::field("ground_level", || reader.read_f32::<LittleEndian>())?;
+        let num_vertices = trace::field("num_vertices", || reader.read_i32::<LittleEndian>())?;
+        let vertex_stride = trace::field("vertex_stride", || reader.read_i32::<LittleEndian>())?;
+
+        let vertex_decl = trace::field("vertex_decl", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::VertexDeclaration(vertex_decl) = vertex_decl else {
             anyhow::bail!("expected vertex declaration");
         };
 
-        let vertex_buffer = XnbAsset::read(reader, type_readers)?;
+        let vertex_buffer = trace::field("vertex_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::VertexBuffer(vertex_buffer) = vertex_buffer else {
             anyhow::bail!("expected vertex buffer");
         };
 
-        let index_buffer = XnbAsset::read(reader, type_readers)?;
+        let index_buffer = trace::field("index_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::IndexBuffer(index_buffer) = index_buffer else {
             anyhow::bail!("expected index buffer");
         };
 
-        let effect = XnbAsset::read(reader, type_readers)?;
+        let effect = trace::field("effect", || XnbAsset::read(reader, type_readers))?;
         // TODO: validate that it's actually an effect
 
-        let node = BiTreeNode::read(reader)?;
+        let node = trace::field("node", || BiTreeNode::read(reader))?;
 
         Ok(BiTree {
             visible,
@@ -96,9 +112,34 @@ impl BiTree {
             node,
         })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        writer.write_bool(self.visible)?;
+        writer.write_bool(self.cast_shadows)?;
+        writer.write_f32::<LittleEndian>(self.sway)?;
+        writer.write_f32::<LittleEndian>(self.entity_influence)?;
+        writer.write_f32::<LittleEndian>(self.ground_level)?;
+        writer.write_i32::<LittleEndian>(self.num_vertices)?;
+        writer.write_i32::<LittleEndian>(self.vertex_stride)?;
+
+        write_type_id(writer, type_readers, VERTEX_DECL_READER_NAME)?;
+        self.vertex_decl.write(writer)?;
+        write_type_id(writer, type_readers, VERTEX_BUFFER_READER_NAME)?;
+        self.vertex_buffer.write(writer)?;
+        write_type_id(writer, type_readers, INDEX_BUFFER_READER_NAME)?;
+        self.index_buffer.write(writer)?;
+
+        self.effect.write(writer, type_readers)?;
+        self.node.write(writer)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct BiTreeNode {
     pub primitive_count: i32,
     pub start_index: i32,
@@ -109,19 +150,20 @@ pub struct BiTreeNode {
 
 impl BiTreeNode {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let primitive_count = reader.read_i32::<LittleEndian>()?;
-        let start_index = reader.read_i32::<LittleEndian>()?;
-        let bounding_box = BoundingBox::read(reader)?;
+        let primitive_count =
+            trace::field("primitive_count", || reader.read_i32::<LittleEndian>())?;
+        let start_index = trace::field("start_index", || reader.read_i32::<LittleEndian>())?;
+        let bounding_box = trace::field("bounding_box", || BoundingBox::read(reader))?;
 
-        let child_a = if reader.read_bool()? {
-            let node = BiTreeNode::read(reader)?;
+        let child_a = if trace::field("has_child_a", || reader.read_bool())? {
+            let node = trace::field("child_a", || BiTreeNode::read(reader))?;
             Some(Box::new(node))
         } else {
             None
         };
 
-        let child_b = if reader.read_bool()? {
-            let node = BiTreeNode::read(reader)?;
+        let child_b = if trace::field("has_child_b", || reader.read_bool())? {
+            let node = trace::field("child_b", || BiTreeNode::read(reader))?;
             Some(Box::new(node))
         } else {
             None
@@ -136,6 +178,19 @@ impl BiTreeNode {
         })
     }
 
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_i32::<LittleEndian>(self.primitive_count)?;
+        writer.write_i32::<LittleEndian>(self.start_index)?;
+        self.bounding_box.write(writer)?;
+        for child in [&self.child_a, &self.child_b] {
+            writer.write_bool(child.is_some())?;
+            if let Some(child) = child {
+                child.write(writer)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn iter_children(&self) -> BiTreeNodeChildrenIter<'_> {
         BiTreeNodeChildrenIter {
             node: self,
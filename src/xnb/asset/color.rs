@@ -1,8 +1,10 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-#[derive(Clone, Copy, Debug)]
+use crate::xnb::trace;
+
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -11,9 +13,16 @@ pub struct Color {
 
 impl Color {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let r = reader.read_f32::<LittleEndian>()?;
-        let g = reader.read_f32::<LittleEndian>()?;
-        let b = reader.read_f32::<LittleEndian>()?;
+        let r = trace::field("r", || reader.read_f32::<LittleEndian>())?;
+        let g = trace::field("g", || reader.read_f32::<LittleEndian>())?;
+        let b = trace::field("b", || reader.read_f32::<LittleEndian>())?;
         Ok(Color { r, g, b })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_f32::<LittleEndian>(self.r)?;
+        writer.write_f32::<LittleEndian>(self.g)?;
+        writer.write_f32::<LittleEndian>(self.b)?;
+        Ok(())
+    }
 }
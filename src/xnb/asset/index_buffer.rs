@@ -1,29 +1,38 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::read_ext::MyReadBytesExt;
+use crate::{read_ext::MyReadBytesExt, write_ext::MyWriteBytesExt, xnb::trace};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct IndexBuffer {
     pub is_16_bit: bool,
+    #[serde(serialize_with = "crate::xnb::asset::serialize_hex")]
     pub data: Vec<u8>,
 }
 
 impl IndexBuffer {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let is_16_bit = reader.read_bool()?;
-        let size = reader.read_u32::<LittleEndian>()? as usize;
+        let is_16_bit = trace::field("is_16_bit", || reader.read_bool())?;
+        let size = trace::field("size", || reader.read_u32::<LittleEndian>())? as usize;
         let mut data = vec![0; size];
-        reader.read_exact(&mut data)?;
+        trace::bytes("data", || reader.read_exact(&mut data))?;
         Ok(IndexBuffer { is_16_bit, data })
     }
 
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_bool(self.is_16_bit)?;
+        writer.write_u32::<LittleEndian>(self.data.len() as u32)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
     pub fn index_count(&self) -> usize {
         let index_size = if self.is_16_bit { 2 } else { 4 };
         self.data.len() / index_size
     }
 
+    #[cfg(feature = "renderer")]
     pub fn wgpu_format(&self) -> wgpu::IndexFormat {
         if self.is_16_bit {
             wgpu::IndexFormat::Uint16
@@ -1,22 +1,27 @@
-use std::{collections::HashMap, io::Read};
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use glam::{Mat4, Quat, Vec3};
 
 use crate::{
     read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
     xnb::{
         TypeReader,
         asset::{
-            LIST_READER_NAME, XnbAsset, animation::AnimationChannel, bi_tree_model::BiTreeModel,
-            color::Color, index_buffer::IndexBuffer, model::Model,
+            BI_TREE_MODEL_READER_NAME, INDEX_BUFFER_READER_NAME, LIST_READER_NAME,
+            MODEL_READER_NAME, RENDER_DEFERRED_LIQUID_EFFECT_READER_NAME,
+            VERTEX_BUFFER_READER_NAME, VERTEX_DECL_READER_NAME, XnbAsset,
+            animation::AnimationChannel, bi_tree_model::BiTreeModel, color::Color,
+            index_buffer::IndexBuffer, model::Model,
             render_deferred_liquid_effect::RenderDeferredLiquidEffect, vertex_buffer::VertexBuffer,
-            vertex_decl::VertexDeclaration,
+            vertex_decl::VertexDeclaration, write_type_id,
         },
+        trace,
     },
 };
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct LevelModel {
     pub model: BiTreeModel,
     pub animated_parts: Vec<AnimatedLevelPart>,
@@ -25,7 +30,8 @@ pub struct LevelModel {
     pub physics_entity_storages: Vec<PhysicsEntityStorage>,
     pub liquids: Vec<Liquid>,
     pub force_fields: Vec<ForceField>,
-    pub collision_meshes: Vec<TriangleMesh>,
+    /// always has all 10 slots, empty ones included, so the model can be written back unchanged
+    pub collision_meshes: Vec<Option<TriangleMesh>>,
     pub camera_mesh: Option<TriangleMesh>,
     pub trigger_areas: Vec<TriggerArea>,
     pub locators: Vec<Locator>,
@@ -34,86 +40,97 @@ pub struct LevelModel {
 
 impl LevelModel {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let model = XnbAsset::read(reader, type_readers)?;
+        let model = trace::field("model", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::BiTreeModel(model) = model else {
             anyhow::bail!("expected bi tree model");
         };
 
-        let num_animated_parts = reader.read_i32::<LittleEndian>()?;
+        let num_animated_parts =
+            trace::field("num_animated_parts", || reader.read_i32::<LittleEndian>())?;
         let mut animated_parts = Vec::with_capacity(num_animated_parts as usize);
         for _ in 0..num_animated_parts {
-            let part = AnimatedLevelPart::read(reader, type_readers)?;
+            let part = trace::field("part", || AnimatedLevelPart::read(reader, type_readers))?;
             animated_parts.push(part);
         }
 
-        let num_lights = reader.read_i32::<LittleEndian>()?;
+        let num_lights = trace::field("num_lights", || reader.read_i32::<LittleEndian>())?;
         let mut lights = Vec::with_capacity(num_lights as usize);
         for _ in 0..num_lights {
-            let light = LevelModelLight::read(reader)?;
+            let light = trace::field("light", || LevelModelLight::read(reader))?;
             lights.push(light);
         }
 
-        let num_effect_storages = reader.read_i32::<LittleEndian>()?;
+        let num_effect_storages =
+            trace::field("num_effect_storages", || reader.read_i32::<LittleEndian>())?;
         let mut effect_storages = Vec::with_capacity(num_effect_storages as usize);
         for _ in 0..num_effect_storages {
-            let effect = EffectStorage::read(reader)?;
+            let effect = trace::field("effect", || EffectStorage::read(reader))?;
             effect_storages.push(effect);
         }
 
-        let num_physics_entity_storages = reader.read_i32::<LittleEndian>()?;
+        let num_physics_entity_storages = trace::field("num_physics_entity_storages", || {
+            reader.read_i32::<LittleEndian>()
+        })?;
         let mut physics_entity_storages = Vec::with_capacity(num_physics_entity_storages as usize);
         for _ in 0..num_physics_entity_storages {
-            let entity = PhysicsEntityStorage::read(reader)?;
+            let entity = trace::field("entity", || PhysicsEntityStorage::read(reader))?;
             physics_entity_storages.push(entity);
         }
 
-        let num_liquids = reader.read_i32::<LittleEndian>()?;
+        let num_liquids = trace::field("num_liquids", || reader.read_i32::<LittleEndian>())?;
         let mut liquids = Vec::with_capacity(num_liquids as usize);
         for _ in 0..num_liquids {
-            let liquid = Liquid::read(reader, type_readers)?;
+            let liquid = trace::field("liquid", || Liquid::read(reader, type_readers))?;
             liquids.push(liquid);
         }
 
-        let num_force_fields = reader.read_i32::<LittleEndian>()?;
+        let num_force_fields =
+            trace::field("num_force_fields", || reader.read_i32::<LittleEndian>())?;
         let mut force_fields = Vec::with_capacity(num_force_fields as usize);
         for _ in 0..num_force_fields {
-            let force_field = ForceField::read(reader, type_readers)?;
+            let force_field =
+                trace::field("force_field", || ForceField::read(reader, type_readers))?;
             force_fields.push(force_field);
         }
 
         let max_collision_meshes = 10;
         let mut collision_meshes = Vec::with_capacity(max_collision_meshes);
         for _ in 0..max_collision_meshes {
-            let exists = reader.read_bool()?;
-            if !exists {
-                continue;
-            }
-
-            let mesh = TriangleMesh::read(reader, type_readers)?;
+            let exists = trace::field("exists", || reader.read_bool())?;
+            let mesh = if exists {
+                Some(trace::field("mesh", || {
+                    TriangleMesh::read(reader, type_readers)
+                })?)
+            } else {
+                None
+            };
             collision_meshes.push(mesh);
         }
 
-        let camera_mesh = if reader.read_bool()? {
-            Some(TriangleMesh::read(reader, type_readers)?)
+        let camera_mesh = if trace::field("has_camera_mesh", || reader.read_bool())? {
+            Some(trace::field("camera_mesh", || {
+                TriangleMesh::read(reader, type_readers)
+            })?)
         } else {
             None
         };
 
-        let num_trigger_areas = reader.read_i32::<LittleEndian>()?;
+        let num_trigger_areas =
+            trace::field("num_trigger_areas", || reader.read_i32::<LittleEndian>())?;
         let mut trigger_areas = Vec::with_capacity(num_trigger_areas as usize);
         for _ in 0..num_trigger_areas {
-            let area = TriggerArea::read(reader)?;
+            let area = trace::field("area", || TriggerArea::read(reader))?;
             trigger_areas.push(area);
         }
 
-        let num_locators = reader.read_i32::<LittleEndian>()?;
+        let num_locators = trace::field("num_locators", || reader.read_i32::<LittleEndian>())?;
         let mut locators = Vec::with_capacity(num_locators as usize);
         for _ in 0..num_locators {
-            let locator = Locator::read(reader)?;
+            let locator = trace::field("locator", || Locator::read(reader))?;
             locators.push(locator);
         }
 
-        let nav_mesh = NavMesh::read(reader)?;
+        let nav_mesh = trace::field("nav_mesh", || NavMesh::read(reader))?;
 
         Ok(LevelModel {
             model,
@@ -130,14 +147,74 @@ impl LevelModel {
             nav_mesh,
         })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        write_type_id(writer, type_readers, BI_TREE_MODEL_READER_NAME)?;
+        self.model.write(writer, type_readers)?;
+
+        writer.write_i32::<LittleEndian>(self.animated_parts.len() as i32)?;
+        for part in &self.animated_parts {
+            part.write(writer, type_readers)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.lights.len() as i32)?;
+        for light in &self.lights {
+            light.write(writer)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.effect_storages.len() as i32)?;
+        for effect in &self.effect_storages {
+            effect.write(writer)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.physics_entity_storages.len() as i32)?;
+        for entity in &self.physics_entity_storages {
+            entity.write(writer)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.liquids.len() as i32)?;
+        for liquid in &self.liquids {
+            liquid.write(writer, type_readers)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.force_fields.len() as i32)?;
+        for force_field in &self.force_fields {
+            force_field.write(writer, type_readers)?;
+        }
+
+        for mesh in self.collision_meshes.iter().chain(Some(&self.camera_mesh)) {
+            writer.write_bool(mesh.is_some())?;
+            if let Some(mesh) = mesh {
+                mesh.write(writer, type_readers)?;
+            }
+        }
+
+        writer.write_i32::<LittleEndian>(self.trigger_areas.len() as i32)?;
+        for area in &self.trigger_areas {
+            area.write(writer)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.locators.len() as i32)?;
+        for locator in &self.locators {
+            locator.write(writer)?;
+        }
+
+        self.nav_mesh.write(writer)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AnimatedLevelPart {
     pub name: String,
     pub affect_shields: bool,
     pub model: Model,
-    pub mesh_settings: HashMap<String, (bool, bool)>,
+    /// kept in file order so the part can be written back unchanged
+    pub mesh_settings: Vec<(String, (bool, bool))>,
     pub liquids: Vec<Liquid>,
     pub locators: Vec<Locator>,
     pub animation_duration: f32,
@@ -151,72 +228,74 @@ pub struct AnimatedLevelPart {
 
 impl AnimatedLevelPart {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let name = reader.read_7bit_length_string()?;
-        let affect_shields = reader.read_bool()?;
+        let name = trace::field("name", || reader.read_7bit_length_string())?;
+        let affect_shields = trace::field("affect_shields", || reader.read_bool())?;
 
-        let model = XnbAsset::read(reader, type_readers)?;
+        let model = trace::field("model", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::Model(model) = model else {
             anyhow::bail!("expected model");
         };
 
-        let num_settings = reader.read_i32::<LittleEndian>()?;
-        let mut mesh_settings = HashMap::with_capacity(num_settings as usize);
+        let num_settings = trace::field("num_settings", || reader.read_i32::<LittleEndian>())?;
+        let mut mesh_settings = Vec::with_capacity(num_settings as usize);
         for _ in 0..num_settings {
-            let setting = reader.read_7bit_length_string()?;
-            let flag1 = reader.read_bool()?;
-            let flag2 = reader.read_bool()?;
-            mesh_settings.insert(setting, (flag1, flag2));
+            let setting = trace::field("setting", || reader.read_7bit_length_string())?;
+            let flag1 = trace::field("flag1", || reader.read_bool())?;
+            let flag2 = trace::field("flag2", || reader.read_bool())?;
+            mesh_settings.push((setting, (flag1, flag2)));
         }
 
-        let num_liquids = reader.read_i32::<LittleEndian>()?;
+        let num_liquids = trace::field("num_liquids", || reader.read_i32::<LittleEndian>())?;
         let mut liquids = Vec::with_capacity(num_liquids as usize);
         for _ in 0..num_liquids {
-            let liquid = Liquid::read(reader, type_readers)?;
+            let liquid = trace::field("liquid", || Liquid::read(reader, type_readers))?;
             liquids.push(liquid);
         }
 
-        let num_locators = reader.read_i32::<LittleEndian>()?;
+        let num_locators = trace::field("num_locators", || reader.read_i32::<LittleEndian>())?;
         let mut locators = Vec::with_capacity(num_locators as usize);
         for _ in 0..num_locators {
-            let locator = Locator::read(reader)?;
+            let locator = trace::field("locator", || Locator::read(reader))?;
             locators.push(locator);
         }
 
-        let animation_duration = reader.read_f32::<LittleEndian>()?;
-        let animation = AnimationChannel::read(reader)?;
+        let animation_duration =
+            trace::field("animation_duration", || reader.read_f32::<LittleEndian>())?;
+        let animation = trace::field("animation", || AnimationChannel::read(reader))?;
 
-        let num_effect_storages = reader.read_i32::<LittleEndian>()?;
+        let num_effect_storages =
+            trace::field("num_effect_storages", || reader.read_i32::<LittleEndian>())?;
         let mut effect_storages = Vec::with_capacity(num_effect_storages as usize);
         for _ in 0..num_effect_storages {
-            let effect = EffectStorage::read(reader)?;
+            let effect = trace::field("effect", || EffectStorage::read(reader))?;
             effect_storages.push(effect);
         }
 
-        let num_lights = reader.read_i32::<LittleEndian>()?;
+        let num_lights = trace::field("num_lights", || reader.read_i32::<LittleEndian>())?;
         let mut light_refs = Vec::with_capacity(num_lights as usize);
         for _ in 0..num_lights {
-            let light = LevelModelLightRef::read(reader)?;
+            let light = trace::field("light", || LevelModelLightRef::read(reader))?;
             light_refs.push(light);
         }
 
-        let collision = if reader.read_bool()? {
-            let material = CollisionMaterial::read(reader)?;
-            let mesh = TriangleMesh::read(reader, type_readers)?;
+        let collision = if trace::field("has_collision", || reader.read_bool())? {
+            let material = trace::field("material", || CollisionMaterial::read(reader))?;
+            let mesh = trace::field("mesh", || TriangleMesh::read(reader, type_readers))?;
             Some(AnimatedLevelPartCollision { material, mesh })
         } else {
             None
         };
 
-        let nav_mesh = if reader.read_bool()? {
-            Some(NavMesh::read(reader)?)
+        let nav_mesh = if trace::field("has_nav_mesh", || reader.read_bool())? {
+            Some(trace::field("nav_mesh", || NavMesh::read(reader))?)
         } else {
             None
         };
 
-        let num_children = reader.read_i32::<LittleEndian>()?;
+        let num_children = trace::field("num_children", || reader.read_i32::<LittleEndian>())?;
         let mut children = Vec::with_capacity(num_children as usize);
         for _ in 0..num_children {
-            let child = AnimatedLevelPart::read(reader, type_readers)?;
+            let child = trace::field("child", || AnimatedLevelPart::read(reader, type_readers))?;
             children.push(child);
         }
 
@@ -236,16 +315,75 @@ impl AnimatedLevelPart {
             children,
         })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_bool(self.affect_shields)?;
+
+        write_type_id(writer, type_readers, MODEL_READER_NAME)?;
+        self.model.write(writer, type_readers)?;
+
+        writer.write_i32::<LittleEndian>(self.mesh_settings.len() as i32)?;
+        for (setting, (flag1, flag2)) in &self.mesh_settings {
+            writer.write_7bit_length_string(setting)?;
+            writer.write_bool(*flag1)?;
+            writer.write_bool(*flag2)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.liquids.len() as i32)?;
+        for liquid in &self.liquids {
+            liquid.write(writer, type_readers)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.locators.len() as i32)?;
+        for locator in &self.locators {
+            locator.write(writer)?;
+        }
+
+        writer.write_f32::<LittleEndian>(self.animation_duration)?;
+        self.animation.write(writer)?;
+
+        writer.write_i32::<LittleEndian>(self.effect_storages.len() as i32)?;
+        for effect in &self.effect_storages {
+            effect.write(writer)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.light_refs.len() as i32)?;
+        for light in &self.light_refs {
+            light.write(writer)?;
+        }
+
+        writer.write_bool(self.collision.is_some())?;
+        if let Some(collision) = &self.collision {
+            writer.write_u8(collision.material as u8)?;
+            collision.mesh.write(writer, type_readers)?;
+        }
+
+        writer.write_bool(self.nav_mesh.is_some())?;
+        if let Some(nav_mesh) = &self.nav_mesh {
+            nav_mesh.write(writer)?;
+        }
+
+        writer.write_i32::<LittleEndian>(self.children.len() as i32)?;
+        for child in &self.children {
+            child.write(writer, type_readers)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct AnimatedLevelPartCollision {
     pub material: CollisionMaterial,
     pub mesh: TriangleMesh,
 }
 
 #[repr(u8)]
-#[derive(strum::FromRepr, Debug)]
+#[derive(strum::FromRepr, Debug, Clone, Copy, serde::Serialize)]
 pub enum CollisionMaterial {
     Generic,
     Gravel,
@@ -261,14 +399,14 @@ pub enum CollisionMaterial {
 
 impl CollisionMaterial {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u8()?;
+        let value = trace::field("value", || reader.read_u8())?;
         let kind = CollisionMaterial::from_repr(value)
             .ok_or_else(|| anyhow::anyhow!("unknown collision material: {value}"))?;
         Ok(kind)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct LevelModelLight {
     pub name: String,
     pub position: Vec3,
@@ -290,22 +428,26 @@ pub struct LevelModelLight {
 
 impl LevelModelLight {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let name = reader.read_7bit_length_string()?;
-        let position = reader.read_vec3()?;
-        let direction = reader.read_vec3()?;
-        let kind = LevelModelLightKind::read(reader)?;
-        let variation = LevelModelLightVariation::read(reader)?;
-        let reach = reader.read_f32::<LittleEndian>()?;
-        let use_attenuation = reader.read_bool()?;
-        let cutoff_angle = reader.read_f32::<LittleEndian>()?;
-        let sharpness = reader.read_f32::<LittleEndian>()?;
-        let diffuse_color = Color::read(reader)?;
-        let ambient_color = Color::read(reader)?;
-        let specular_amount = reader.read_f32::<LittleEndian>()?;
-        let variation_speed = reader.read_f32::<LittleEndian>()?;
-        let variation_amount = reader.read_f32::<LittleEndian>()?;
-        let shadow_map_size = reader.read_i32::<LittleEndian>()?;
-        let casts_shadows = reader.read_bool()?;
+        let name = trace::field("name", || reader.read_7bit_length_string())?;
+        let position = trace::field("position", || reader.read_vec3())?;
+        let direction = trace::field("direction", || reader.read_vec3())?;
+        let kind = trace::field("kind", || LevelModelLightKind::read(reader))?;
+        let variation = trace::field("variation", || LevelModelLightVariation::read(reader))?;
+        let reach = trace::field("reach", || reader.read_f32::<LittleEndian>())?;
+        let use_attenuation = trace::field("use_attenuation", || reader.read_bool())?;
+        let cutoff_angle = trace::field("cutoff_angle", || reader.read_f32::<LittleEndian>())?;
+        let sharpness = trace::field("sharpness", || reader.read_f32::<LittleEndian>())?;
+        let diffuse_color = trace::field("diffuse_color", || Color::read(reader))?;
+        let ambient_color = trace::field("ambient_color", || Color::read(reader))?;
+        let specular_amount =
+            trace::field("specular_amount", || reader.read_f32::<LittleEndian>())?;
+        let variation_speed =
+            trace::field("variation_speed", || reader.read_f32::<LittleEndian>())?;
+        let variation_amount =
+            trace::field("variation_amount", || reader.read_f32::<LittleEndian>())?;
+        let shadow_map_size =
+            trace::field("shadow_map_size", || reader.read_i32::<LittleEndian>())?;
+        let casts_shadows = trace::field("casts_shadows", || reader.read_bool())?;
 
         Ok(LevelModelLight {
             name,
@@ -326,10 +468,30 @@ impl LevelModelLight {
             casts_shadows,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_vec3(self.position)?;
+        writer.write_vec3(self.direction)?;
+        writer.write_u32::<LittleEndian>(self.kind as u32)?;
+        writer.write_u32::<LittleEndian>(self.variation as u32)?;
+        writer.write_f32::<LittleEndian>(self.reach)?;
+        writer.write_bool(self.use_attenuation)?;
+        writer.write_f32::<LittleEndian>(self.cutoff_angle)?;
+        writer.write_f32::<LittleEndian>(self.sharpness)?;
+        self.diffuse_color.write(writer)?;
+        self.ambient_color.write(writer)?;
+        writer.write_f32::<LittleEndian>(self.specular_amount)?;
+        writer.write_f32::<LittleEndian>(self.variation_speed)?;
+        writer.write_f32::<LittleEndian>(self.variation_amount)?;
+        writer.write_i32::<LittleEndian>(self.shadow_map_size)?;
+        writer.write_bool(self.casts_shadows)?;
+        Ok(())
+    }
 }
 
 #[repr(u8)]
-#[derive(strum::FromRepr, Debug)]
+#[derive(strum::FromRepr, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum LevelModelLightKind {
     Point,
     Directional,
@@ -339,7 +501,7 @@ pub enum LevelModelLightKind {
 
 impl LevelModelLightKind {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u32::<LittleEndian>()?;
+        let value = trace::field("value", || reader.read_u32::<LittleEndian>())?;
         let kind = LevelModelLightKind::from_repr(value as u8)
             .ok_or_else(|| anyhow::anyhow!("unknown level model light kind: {value}"))?;
         Ok(kind)
@@ -347,7 +509,7 @@ impl LevelModelLightKind {
 }
 
 #[repr(u8)]
-#[derive(strum::FromRepr, Debug)]
+#[derive(strum::FromRepr, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum LevelModelLightVariation {
     None = 0,
     Sine,
@@ -358,29 +520,35 @@ pub enum LevelModelLightVariation {
 
 impl LevelModelLightVariation {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u32::<LittleEndian>()?;
+        let value = trace::field("value", || reader.read_u32::<LittleEndian>())?;
         let kind = LevelModelLightVariation::from_repr(value as u8)
             .ok_or_else(|| anyhow::anyhow!("unknown level model light variation: {value}"))?;
         Ok(kind)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct LevelModelLightRef {
-    name: String,
-    transform: Mat4,
+    pub name: String,
+    pub transform: Mat4,
 }
 
 impl LevelModelLightRef {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let name = reader.read_7bit_length_string()?;
-        let transform = reader.read_mat4()?;
+        let name = trace::field("name", || reader.read_7bit_length_string())?;
+        let transform = trace::field("transform", || reader.read_mat4())?;
 
         Ok(LevelModelLightRef { name, transform })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_mat4(self.transform)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct EffectStorage {
     pub name: String,
     pub position: Vec3,
@@ -391,11 +559,11 @@ pub struct EffectStorage {
 
 impl EffectStorage {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let name = reader.read_7bit_length_string()?;
-        let position = reader.read_vec3()?;
-        let forward = reader.read_vec3()?;
-        let range = reader.read_f32::<LittleEndian>()?;
-        let effect = reader.read_7bit_length_string()?;
+        let name = trace::field("name", || reader.read_7bit_length_string())?;
+        let position = trace::field("position", || reader.read_vec3())?;
+        let forward = trace::field("forward", || reader.read_vec3())?;
+        let range = trace::field("range", || reader.read_f32::<LittleEndian>())?;
+        let effect = trace::field("effect", || reader.read_7bit_length_string())?;
 
         Ok(EffectStorage {
             name,
@@ -405,9 +573,18 @@ impl EffectStorage {
             effect,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_vec3(self.position)?;
+        writer.write_vec3(self.forward)?;
+        writer.write_f32::<LittleEndian>(self.range)?;
+        writer.write_7bit_length_string(&self.effect)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct PhysicsEntityStorage {
     pub transform: Mat4,
     pub template: String,
@@ -415,17 +592,23 @@ pub struct PhysicsEntityStorage {
 
 impl PhysicsEntityStorage {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let transform = reader.read_mat4()?;
-        let template = reader.read_7bit_length_string()?;
+        let transform = trace::field("transform", || reader.read_mat4())?;
+        let template = trace::field("template", || reader.read_7bit_length_string())?;
 
         Ok(PhysicsEntityStorage {
             transform,
             template,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_mat4(self.transform)?;
+        writer.write_7bit_length_string(&self.template)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub enum Liquid {
     Water(Water),
     Lava(Lava),
@@ -433,19 +616,38 @@ pub enum Liquid {
 
 impl Liquid {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let effect = XnbAsset::read(reader, type_readers)?;
+        let effect = trace::field("effect", || XnbAsset::read(reader, type_readers))?;
 
         match effect {
             XnbAsset::RenderDeferredLiquidEffect(effect) => {
-                let water = Water::read(reader, type_readers, effect)?;
+                let water = trace::field("water", || Water::read(reader, type_readers, effect))?;
                 Ok(Liquid::Water(water))
             }
             _ => anyhow::bail!("expected DeferredLiquidEffect in LeveLModel"),
         }
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        match self {
+            Liquid::Water(water) => {
+                write_type_id(
+                    writer,
+                    type_readers,
+                    RENDER_DEFERRED_LIQUID_EFFECT_READER_NAME,
+                )?;
+                water.effect.write(writer)?;
+                water.write(writer, type_readers)
+            }
+            Liquid::Lava(_) => anyhow::bail!("writing lava is not supported"),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Water {
     pub effect: RenderDeferredLiquidEffect,
     pub vertex_buffer: VertexBuffer,
@@ -465,27 +667,30 @@ impl Water {
         type_readers: &[TypeReader],
         effect: RenderDeferredLiquidEffect,
     ) -> anyhow::Result<Self> {
-        let vertex_buffer = XnbAsset::read(reader, type_readers)?;
+        let vertex_buffer = trace::field("vertex_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::VertexBuffer(vertex_buffer) = vertex_buffer else {
             anyhow::bail!("expected vertex buffer");
         };
 
-        let index_buffer = XnbAsset::read(reader, type_readers)?;
+        let index_buffer = trace::field("index_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::IndexBuffer(index_buffer) = index_buffer else {
             anyhow::bail!("expected index buffer");
         };
 
-        let vertex_declaration = XnbAsset::read(reader, type_readers)?;
+        let vertex_declaration = trace::field("vertex_declaration", || {
+            XnbAsset::read(reader, type_readers)
+        })?;
         let XnbAsset::VertexDeclaration(vertex_declaration) = vertex_declaration else {
             anyhow::bail!("expected vertex declaration");
         };
 
-        let vertex_stride = reader.read_i32::<LittleEndian>()?;
-        let num_vertices = reader.read_i32::<LittleEndian>()?;
-        let primitive_count = reader.read_i32::<LittleEndian>()?;
-        let entities_can_drown = reader.read_bool()?;
-        let freezable = reader.read_bool()?;
-        let auto_freeze = reader.read_bool()?;
+        let vertex_stride = trace::field("vertex_stride", || reader.read_i32::<LittleEndian>())?;
+        let num_vertices = trace::field("num_vertices", || reader.read_i32::<LittleEndian>())?;
+        let primitive_count =
+            trace::field("primitive_count", || reader.read_i32::<LittleEndian>())?;
+        let entities_can_drown = trace::field("entities_can_drown", || reader.read_bool())?;
+        let freezable = trace::field("freezable", || reader.read_bool())?;
+        let auto_freeze = trace::field("auto_freeze", || reader.read_bool())?;
 
         Ok(Water {
             effect,
@@ -500,12 +705,34 @@ impl Water {
             auto_freeze,
         })
     }
+
+    /// writes everything after the effect, which is written by `Liquid::write`
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        write_type_id(writer, type_readers, VERTEX_BUFFER_READER_NAME)?;
+        self.vertex_buffer.write(writer)?;
+        write_type_id(writer, type_readers, INDEX_BUFFER_READER_NAME)?;
+        self.index_buffer.write(writer)?;
+        write_type_id(writer, type_readers, VERTEX_DECL_READER_NAME)?;
+        self.vertex_declaration.write(writer)?;
+
+        writer.write_i32::<LittleEndian>(self.vertex_stride)?;
+        writer.write_i32::<LittleEndian>(self.num_vertices)?;
+        writer.write_i32::<LittleEndian>(self.primitive_count)?;
+        writer.write_bool(self.entities_can_drown)?;
+        writer.write_bool(self.freezable)?;
+        writer.write_bool(self.auto_freeze)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Lava {}
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct ForceField {
     pub color: Color,
     pub width: f32,
@@ -527,35 +754,41 @@ pub struct ForceField {
 
 impl ForceField {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let color = Color::read(reader)?;
-        let width = reader.read_f32::<LittleEndian>()?;
-        let alpha_power = reader.read_f32::<LittleEndian>()?;
-        let alpha_falloff_power = reader.read_f32::<LittleEndian>()?;
-        let max_radius = reader.read_f32::<LittleEndian>()?;
-        let ripple_distortion = reader.read_f32::<LittleEndian>()?;
-        let map_distortion = reader.read_f32::<LittleEndian>()?;
-        let vertex_color_enabled = reader.read_bool()?;
-        let displacement_map = reader.read_7bit_length_string()?;
-        let ttl = reader.read_f32::<LittleEndian>()?;
-
-        let vertex_buffer = XnbAsset::read(reader, type_readers)?;
+        let color = trace::field("color", || Color::read(reader))?;
+        let width = trace::field("width", || reader.read_f32::<LittleEndian>())?;
+        let alpha_power = trace::field("alpha_power", || reader.read_f32::<LittleEndian>())?;
+        let alpha_falloff_power =
+            trace::field("alpha_falloff_power", || reader.read_f32::<LittleEndian>())?;
+        let max_radius = trace::field("max_radius", || reader.read_f32::<LittleEndian>())?;
+        let ripple_distortion =
+            trace::field("ripple_distortion", || reader.read_f32::<LittleEndian>())?;
+        let map_distortion = trace::field("map_distortion", || reader.read_f32::<LittleEndian>())?;
+        let vertex_color_enabled = trace::field("vertex_color_enabled", || reader.read_bool())?;
+        let displacement_map =
+            trace::field("displacement_map", || reader.read_7bit_length_string())?;
+        let ttl = trace::field("ttl", || reader.read_f32::<LittleEndian>())?;
+
+        let vertex_buffer = trace::field("vertex_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::VertexBuffer(vertex_buffer) = vertex_buffer else {
             anyhow::bail!("expected vertex buffer");
         };
 
-        let index_buffer = XnbAsset::read(reader, type_readers)?;
+        let index_buffer = trace::field("index_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::IndexBuffer(index_buffer) = index_buffer else {
             anyhow::bail!("expected index buffer");
         };
 
-        let vertex_declaration = XnbAsset::read(reader, type_readers)?;
+        let vertex_declaration = trace::field("vertex_declaration", || {
+            XnbAsset::read(reader, type_readers)
+        })?;
         let XnbAsset::VertexDeclaration(vertex_declaration) = vertex_declaration else {
             anyhow::bail!("expected vertex declaration");
         };
 
-        let vertex_stride = reader.read_i32::<LittleEndian>()?;
-        let num_vertices = reader.read_i32::<LittleEndian>()?;
-        let primitive_count = reader.read_i32::<LittleEndian>()?;
+        let vertex_stride = trace::field("vertex_stride", || reader.read_i32::<LittleEndian>())?;
+        let num_vertices = trace::field("num_vertices", || reader.read_i32::<LittleEndian>())?;
+        let primitive_count =
+            trace::field("primitive_count", || reader.read_i32::<LittleEndian>())?;
 
         Ok(ForceField {
             color,
@@ -576,56 +809,109 @@ impl ForceField {
             primitive_count,
         })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        self.color.write(writer)?;
+        writer.write_f32::<LittleEndian>(self.width)?;
+        writer.write_f32::<LittleEndian>(self.alpha_power)?;
+        writer.write_f32::<LittleEndian>(self.alpha_falloff_power)?;
+        writer.write_f32::<LittleEndian>(self.max_radius)?;
+        writer.write_f32::<LittleEndian>(self.ripple_distortion)?;
+        writer.write_f32::<LittleEndian>(self.map_distortion)?;
+        writer.write_bool(self.vertex_color_enabled)?;
+        writer.write_7bit_length_string(&self.displacement_map)?;
+        writer.write_f32::<LittleEndian>(self.ttl)?;
+
+        write_type_id(writer, type_readers, VERTEX_BUFFER_READER_NAME)?;
+        self.vertex_buffer.write(writer)?;
+        write_type_id(writer, type_readers, INDEX_BUFFER_READER_NAME)?;
+        self.index_buffer.write(writer)?;
+        write_type_id(writer, type_readers, VERTEX_DECL_READER_NAME)?;
+        self.vertex_declaration.write(writer)?;
+
+        writer.write_i32::<LittleEndian>(self.vertex_stride)?;
+        writer.write_i32::<LittleEndian>(self.num_vertices)?;
+        writer.write_i32::<LittleEndian>(self.primitive_count)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct TriangleMesh {
-    vertices: Vec<Vec3>,
-    indices: Vec<[u32; 3]>,
+    pub vertices: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
 }
 
 impl TriangleMesh {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let reader_index = reader.read_7bit_encoded_i32()? as usize;
+        let reader_index =
+            trace::field("reader_index", || reader.read_7bit_encoded_i32())? as usize;
         let reader_name = &type_readers[reader_index - 1].name;
         if !reader_name.starts_with(LIST_READER_NAME) {
             anyhow::bail!("expected list");
         }
 
-        let num_vertices = reader.read_u32::<LittleEndian>()? as usize;
+        let num_vertices =
+            trace::field("num_vertices", || reader.read_u32::<LittleEndian>())? as usize;
         let mut vertices = Vec::with_capacity(num_vertices);
         for _ in 0..num_vertices {
-            let vertex = reader.read_vec3()?;
+            let vertex = trace::field("vertex", || reader.read_vec3())?;
             vertices.push(vertex);
         }
 
-        let num_indices = reader.read_u32::<LittleEndian>()? as usize;
+        let num_indices =
+            trace::field("num_indices", || reader.read_u32::<LittleEndian>())? as usize;
         let mut indices = Vec::with_capacity(num_indices);
         for _ in 0..num_indices {
-            let i0 = reader.read_u32::<LittleEndian>()?;
-            let i1 = reader.read_u32::<LittleEndian>()?;
-            let i2 = reader.read_u32::<LittleEndian>()?;
+            let i0 = trace::field("i0", || reader.read_u32::<LittleEndian>())?;
+            let i1 = trace::field("i1", || reader.read_u32::<LittleEndian>())?;
+            let i2 = trace::field("i2", || reader.read_u32::<LittleEndian>())?;
             indices.push([i0, i1, i2]);
         }
 
         Ok(TriangleMesh { vertices, indices })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        write_type_id(writer, type_readers, LIST_READER_NAME)?;
+
+        writer.write_u32::<LittleEndian>(self.vertices.len() as u32)?;
+        for vertex in &self.vertices {
+            writer.write_vec3(*vertex)?;
+        }
+
+        writer.write_u32::<LittleEndian>(self.indices.len() as u32)?;
+        for triangle in &self.indices {
+            for index in triangle {
+                writer.write_u32::<LittleEndian>(*index)?;
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct TriggerArea {
-    name: String,
-    position: Vec3,
-    side_lengths: Vec3,
-    orientation: Quat,
+    pub name: String,
+    pub position: Vec3,
+    pub side_lengths: Vec3,
+    pub orientation: Quat,
 }
 
 impl TriggerArea {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let name = reader.read_7bit_length_string()?;
-        let position = reader.read_vec3()?;
-        let side_lengths = reader.read_vec3()?;
-        let orientation = reader.read_quat()?;
+        let name = trace::field("name", || reader.read_7bit_length_string())?;
+        let position = trace::field("position", || reader.read_vec3())?;
+        let side_lengths = trace::field("side_lengths", || reader.read_vec3())?;
+        let orientation = trace::field("orientation", || reader.read_quat())?;
 
         Ok(TriggerArea {
             name,
@@ -634,9 +920,17 @@ impl TriggerArea {
             orientation,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_vec3(self.position)?;
+        writer.write_vec3(self.side_lengths)?;
+        writer.write_quat(self.orientation)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Locator {
     pub name: String,
     pub transform: Mat4,
@@ -645,9 +939,9 @@ pub struct Locator {
 
 impl Locator {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let name = reader.read_7bit_length_string()?;
-        let transform = reader.read_mat4()?;
-        let radius = reader.read_f32::<LittleEndian>()?;
+        let name = trace::field("name", || reader.read_7bit_length_string())?;
+        let transform = trace::field("transform", || reader.read_mat4())?;
+        let radius = trace::field("radius", || reader.read_f32::<LittleEndian>())?;
 
         Ok(Locator {
             name,
@@ -655,9 +949,16 @@ impl Locator {
             radius,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_mat4(self.transform)?;
+        writer.write_f32::<LittleEndian>(self.radius)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct NavMesh {
     pub vertices: Vec<Vec3>,
     pub triangles: Vec<NavMeshTriangle>,
@@ -665,17 +966,17 @@ pub struct NavMesh {
 
 impl NavMesh {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let num_vertices = reader.read_u16::<LittleEndian>()?;
+        let num_vertices = trace::field("num_vertices", || reader.read_u16::<LittleEndian>())?;
         let mut vertices = Vec::with_capacity(num_vertices as usize);
         for _ in 0..num_vertices {
-            let vertex = reader.read_vec3()?;
+            let vertex = trace::field("vertex", || reader.read_vec3())?;
             vertices.push(vertex);
         }
 
-        let num_triangles = reader.read_u16::<LittleEndian>()?;
+        let num_triangles = trace::field("num_triangles", || reader.read_u16::<LittleEndian>())?;
         let mut triangles = Vec::with_capacity(num_triangles as usize);
         for _ in 0..num_triangles {
-            let triangle = NavMeshTriangle::read(reader)?;
+            let triangle = trace::field("triangle", || NavMeshTriangle::read(reader))?;
             triangles.push(triangle);
         }
 
@@ -684,9 +985,22 @@ impl NavMesh {
             triangles,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u16::<LittleEndian>(self.vertices.len() as u16)?;
+        for vertex in &self.vertices {
+            writer.write_vec3(*vertex)?;
+        }
+
+        writer.write_u16::<LittleEndian>(self.triangles.len() as u16)?;
+        for triangle in &self.triangles {
+            triangle.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct NavMeshTriangle {
     pub vertex_a: u16,
     pub vertex_b: u16,
@@ -702,16 +1016,16 @@ pub struct NavMeshTriangle {
 
 impl NavMeshTriangle {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let vertex_a = reader.read_u16::<LittleEndian>()?;
-        let vertex_b = reader.read_u16::<LittleEndian>()?;
-        let vertex_c = reader.read_u16::<LittleEndian>()?;
-        let neighbor_a = reader.read_u16::<LittleEndian>()?;
-        let neighbor_b = reader.read_u16::<LittleEndian>()?;
-        let neighbor_c = reader.read_u16::<LittleEndian>()?;
-        let cost_ab = reader.read_f32::<LittleEndian>()?;
-        let cost_bc = reader.read_f32::<LittleEndian>()?;
-        let cost_ca = reader.read_f32::<LittleEndian>()?;
-        let properties = MovementProperties::read(reader)?;
+        let vertex_a = trace::field("vertex_a", || reader.read_u16::<LittleEndian>())?;
+        let vertex_b = trace::field("vertex_b", || reader.read_u16::<LittleEndian>())?;
+        let vertex_c = trace::field("vertex_c", || reader.read_u16::<LittleEndian>())?;
+        let neighbor_a = trace::field("neighbor_a", || reader.read_u16::<LittleEndian>())?;
+        let neighbor_b = trace::field("neighbor_b", || reader.read_u16::<LittleEndian>())?;
+        let neighbor_c = trace::field("neighbor_c", || reader.read_u16::<LittleEndian>())?;
+        let cost_ab = trace::field("cost_ab", || reader.read_f32::<LittleEndian>())?;
+        let cost_bc = trace::field("cost_bc", || reader.read_f32::<LittleEndian>())?;
+        let cost_ca = trace::field("cost_ca", || reader.read_f32::<LittleEndian>())?;
+        let properties = trace::field("properties", || MovementProperties::read(reader))?;
         Ok(NavMeshTriangle {
             vertex_a,
             vertex_b,
@@ -725,10 +1039,24 @@ impl NavMeshTriangle {
             properties,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u16::<LittleEndian>(self.vertex_a)?;
+        writer.write_u16::<LittleEndian>(self.vertex_b)?;
+        writer.write_u16::<LittleEndian>(self.vertex_c)?;
+        writer.write_u16::<LittleEndian>(self.neighbor_a)?;
+        writer.write_u16::<LittleEndian>(self.neighbor_b)?;
+        writer.write_u16::<LittleEndian>(self.neighbor_c)?;
+        writer.write_f32::<LittleEndian>(self.cost_ab)?;
+        writer.write_f32::<LittleEndian>(self.cost_bc)?;
+        writer.write_f32::<LittleEndian>(self.cost_ca)?;
+        writer.write_u8(self.properties.bits())?;
+        Ok(())
+    }
 }
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, serde::Serialize)]
     pub struct MovementProperties: u8 {
         const DEFAULT = 0;
         const WATER = 1;
@@ -741,7 +1069,7 @@ bitflags::bitflags! {
 
 impl MovementProperties {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u8()?;
+        let value = trace::field("value", || reader.read_u8())?;
         let properties = MovementProperties::from_bits(value)
             .ok_or_else(|| anyhow::anyhow!("unknown movement properties: {value}"))?;
         Ok(properties)
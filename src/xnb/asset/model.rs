@@ -1,20 +1,23 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use glam::{Mat4, Vec3};
 
 use crate::{
     read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
     xnb::{
         TypeReader,
         asset::{
-            XnbAsset, index_buffer::IndexBuffer, vertex_buffer::VertexBuffer,
-            vertex_decl::VertexDeclaration,
+            INDEX_BUFFER_READER_NAME, STRING_READER_NAME, VERTEX_BUFFER_READER_NAME,
+            VERTEX_DECL_READER_NAME, XnbAsset, index_buffer::IndexBuffer,
+            vertex_buffer::VertexBuffer, vertex_decl::VertexDeclaration, write_type_id,
         },
+        trace,
     },
 };
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Model {
     pub bones: Vec<Bone>,
     pub bones_hierarchy: Vec<BoneHierarchy>,
@@ -26,39 +29,40 @@ pub struct Model {
 
 impl Model {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let num_bones = reader.read_u32::<LittleEndian>()?;
+        let num_bones = trace::field("num_bones", || reader.read_u32::<LittleEndian>())?;
 
         let mut bones = Vec::with_capacity(num_bones as usize);
         for _ in 0..num_bones {
-            let bone = Bone::read(reader, type_readers)?;
+            let bone = trace::field("bone", || Bone::read(reader, type_readers))?;
             bones.push(bone);
         }
 
         let mut bones_hierarchy = Vec::with_capacity(num_bones as usize);
         for _ in 0..num_bones {
-            let hierarchy = BoneHierarchy::read(reader, num_bones)?;
+            let hierarchy = trace::field("hierarchy", || BoneHierarchy::read(reader, num_bones))?;
             bones_hierarchy.push(hierarchy);
         }
 
-        let num_vertex_decls = reader.read_u32::<LittleEndian>()?;
+        let num_vertex_decls =
+            trace::field("num_vertex_decls", || reader.read_u32::<LittleEndian>())?;
         let mut vertex_decls = Vec::with_capacity(num_vertex_decls as usize);
         for _ in 0..num_vertex_decls {
-            let content = XnbAsset::read(reader, type_readers)?;
+            let content = trace::field("content", || XnbAsset::read(reader, type_readers))?;
             let XnbAsset::VertexDeclaration(decl) = content else {
                 anyhow::bail!("expected vertex declaration");
             };
             vertex_decls.push(decl);
         }
 
-        let num_meshes = reader.read_u32::<LittleEndian>()?;
+        let num_meshes = trace::field("num_meshes", || reader.read_u32::<LittleEndian>())?;
         let mut meshes = Vec::with_capacity(num_meshes as usize);
         for _ in 0..num_meshes {
-            let mesh = Mesh::read(reader, type_readers)?;
+            let mesh = trace::field("mesh", || Mesh::read(reader, type_readers))?;
             meshes.push(mesh);
         }
 
-        let root_bone_ref = read_bone_ref(reader, num_bones)?;
-        let tag = reader.read_u8()?;
+        let root_bone_ref = trace::field("root_bone_ref", || read_bone_ref(reader, num_bones))?;
+        let tag = trace::field("tag", || reader.read_u8())?;
 
         Ok(Model {
             bones,
@@ -69,9 +73,39 @@ impl Model {
             tag,
         })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        let num_bones = self.bones.len() as u32;
+        writer.write_u32::<LittleEndian>(num_bones)?;
+        for bone in &self.bones {
+            bone.write(writer, type_readers)?;
+        }
+        for hierarchy in &self.bones_hierarchy {
+            hierarchy.write(writer, num_bones)?;
+        }
+
+        writer.write_u32::<LittleEndian>(self.vertex_decls.len() as u32)?;
+        for decl in &self.vertex_decls {
+            write_type_id(writer, type_readers, VERTEX_DECL_READER_NAME)?;
+            decl.write(writer)?;
+        }
+
+        writer.write_u32::<LittleEndian>(self.meshes.len() as u32)?;
+        for mesh in &self.meshes {
+            mesh.write(writer, type_readers)?;
+        }
+
+        write_bone_ref(writer, self.root_bone_ref, num_bones)?;
+        writer.write_u8(self.tag)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Bone {
     pub name: String,
     pub transform: Mat4,
@@ -79,16 +113,27 @@ pub struct Bone {
 
 impl Bone {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let name = XnbAsset::read(reader, type_readers)?;
+        let name = trace::field("name", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::String(name) = name else {
             anyhow::bail!("expected bone name to be a string");
         };
-        let transform = reader.read_mat4()?;
+        let transform = trace::field("transform", || reader.read_mat4())?;
         Ok(Bone { name, transform })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        write_type_id(writer, type_readers, STRING_READER_NAME)?;
+        writer.write_7bit_length_string(&self.name)?;
+        writer.write_mat4(self.transform)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct BoneHierarchy {
     pub parent_ref: u32,
     pub children_refs: Vec<u32>,
@@ -96,11 +141,12 @@ pub struct BoneHierarchy {
 
 impl BoneHierarchy {
     pub fn read(reader: &mut impl Read, num_bones: u32) -> anyhow::Result<Self> {
-        let parent_ref = read_bone_ref(reader, num_bones)?;
-        let num_children = reader.read_u32::<LittleEndian>()? as usize;
+        let parent_ref = trace::field("parent_ref", || read_bone_ref(reader, num_bones))?;
+        let num_children =
+            trace::field("num_children", || reader.read_u32::<LittleEndian>())? as usize;
         let mut children_refs = Vec::with_capacity(num_children);
         for _ in 0..num_children {
-            let child_ref = read_bone_ref(reader, num_bones)?;
+            let child_ref = trace::field("child_ref", || read_bone_ref(reader, num_bones))?;
             children_refs.push(child_ref);
         }
         Ok(BoneHierarchy {
@@ -108,9 +154,18 @@ impl BoneHierarchy {
             children_refs,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write, num_bones: u32) -> anyhow::Result<()> {
+        write_bone_ref(writer, self.parent_ref, num_bones)?;
+        writer.write_u32::<LittleEndian>(self.children_refs.len() as u32)?;
+        for child_ref in &self.children_refs {
+            write_bone_ref(writer, *child_ref, num_bones)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Mesh {
     pub name: String,
     pub parent_bone_ref: u32,
@@ -123,30 +178,30 @@ pub struct Mesh {
 
 impl Mesh {
     pub fn read(reader: &mut impl Read, type_readers: &[TypeReader]) -> anyhow::Result<Self> {
-        let name = XnbAsset::read(reader, type_readers)?;
+        let name = trace::field("name", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::String(name) = name else {
             anyhow::bail!("expected bone name to be a string");
         };
 
-        let parent_bone_ref = read_bone_ref(reader, 0)?;
-        let bounds = BoundingSphere::read(reader)?;
+        let parent_bone_ref = trace::field("parent_bone_ref", || read_bone_ref(reader, 0))?;
+        let bounds = trace::field("bounds", || BoundingSphere::read(reader))?;
 
-        let vertex_buffer = XnbAsset::read(reader, type_readers)?;
+        let vertex_buffer = trace::field("vertex_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::VertexBuffer(vertex_buffer) = vertex_buffer else {
             anyhow::bail!("expected vertex buffer");
         };
 
-        let index_buffer = XnbAsset::read(reader, type_readers)?;
+        let index_buffer = trace::field("index_buffer", || XnbAsset::read(reader, type_readers))?;
         let XnbAsset::IndexBuffer(index_buffer) = index_buffer else {
             anyhow::bail!("expected index buffer");
         };
 
-        let tag = reader.read_u8()?;
+        let tag = trace::field("tag", || reader.read_u8())?;
 
-        let num_parts = reader.read_u32::<LittleEndian>()? as usize;
+        let num_parts = trace::field("num_parts", || reader.read_u32::<LittleEndian>())? as usize;
         let mut parts = Vec::with_capacity(num_parts);
         for _ in 0..num_parts {
-            let part = MeshPart::read(reader)?;
+            let part = trace::field("part", || MeshPart::read(reader))?;
             parts.push(part);
         }
 
@@ -160,9 +215,33 @@ impl Mesh {
             tag,
         })
     }
+
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        type_readers: &[TypeReader],
+    ) -> anyhow::Result<()> {
+        write_type_id(writer, type_readers, STRING_READER_NAME)?;
+        writer.write_7bit_length_string(&self.name)?;
+        write_bone_ref(writer, self.parent_bone_ref, 0)?;
+        self.bounds.write(writer)?;
+
+        write_type_id(writer, type_readers, VERTEX_BUFFER_READER_NAME)?;
+        self.vertex_buffer.write(writer)?;
+        write_type_id(writer, type_readers, INDEX_BUFFER_READER_NAME)?;
+        self.index_buffer.write(writer)?;
+
+        writer.write_u8(self.tag)?;
+
+        writer.write_u32::<LittleEndian>(self.parts.len() as u32)?;
+        for part in &self.parts {
+            part.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct MeshPart {
     pub stream_offset: u32,
     pub base_vertex: u32,
@@ -176,14 +255,18 @@ pub struct MeshPart {
 
 impl MeshPart {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let stream_offset = reader.read_u32::<LittleEndian>()?;
-        let base_vertex = reader.read_u32::<LittleEndian>()?;
-        let vertex_count = reader.read_u32::<LittleEndian>()?;
-        let start_index = reader.read_u32::<LittleEndian>()?;
-        let primitive_count = reader.read_u32::<LittleEndian>()?;
-        let vertex_decl_index = reader.read_u32::<LittleEndian>()?;
-        let tag = reader.read_u8()?;
-        let shared_content_material_index = reader.read_7bit_encoded_i32()?;
+        let stream_offset = trace::field("stream_offset", || reader.read_u32::<LittleEndian>())?;
+        let base_vertex = trace::field("base_vertex", || reader.read_u32::<LittleEndian>())?;
+        let vertex_count = trace::field("vertex_count", || reader.read_u32::<LittleEndian>())?;
+        let start_index = trace::field("start_index", || reader.read_u32::<LittleEndian>())?;
+        let primitive_count =
+            trace::field("primitive_count", || reader.read_u32::<LittleEndian>())?;
+        let vertex_decl_index =
+            trace::field("vertex_decl_index", || reader.read_u32::<LittleEndian>())?;
+        let tag = trace::field("tag", || reader.read_u8())?;
+        let shared_content_material_index = trace::field("shared_content_material_index", || {
+            reader.read_7bit_encoded_i32()
+        })?;
         Ok(MeshPart {
             stream_offset,
             base_vertex,
@@ -195,9 +278,21 @@ impl MeshPart {
             shared_content_material_index,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.stream_offset)?;
+        writer.write_u32::<LittleEndian>(self.base_vertex)?;
+        writer.write_u32::<LittleEndian>(self.vertex_count)?;
+        writer.write_u32::<LittleEndian>(self.start_index)?;
+        writer.write_u32::<LittleEndian>(self.primitive_count)?;
+        writer.write_u32::<LittleEndian>(self.vertex_decl_index)?;
+        writer.write_u8(self.tag)?;
+        writer.write_7bit_encoded_i32(self.shared_content_material_index)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct BoundingSphere {
     pub center: Vec3,
     pub radius: f32,
@@ -205,13 +300,19 @@ pub struct BoundingSphere {
 
 impl BoundingSphere {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let center = reader.read_vec3()?;
-        let radius = reader.read_f32::<LittleEndian>()?;
+        let center = trace::field("center", || reader.read_vec3())?;
+        let radius = trace::field("radius", || reader.read_f32::<LittleEndian>())?;
         Ok(BoundingSphere { center, radius })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_vec3(self.center)?;
+        writer.write_f32::<LittleEndian>(self.radius)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct BoundingBox {
     pub min: Vec3,
     pub max: Vec3,
@@ -219,10 +320,16 @@ pub struct BoundingBox {
 
 impl BoundingBox {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let min = reader.read_vec3()?;
-        let max = reader.read_vec3()?;
+        let min = trace::field("min", || reader.read_vec3())?;
+        let max = trace::field("max", || reader.read_vec3())?;
         Ok(BoundingBox { min, max })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_vec3(self.min)?;
+        writer.write_vec3(self.max)?;
+        Ok(())
+    }
 }
 
 fn read_bone_ref(reader: &mut impl Read, num_bones: u32) -> std::io::Result<u32> {
@@ -233,3 +340,11 @@ fn read_bone_ref(reader: &mut impl Read, num_bones: u32) -> std::io::Result<u32>
     };
     Ok(bone_ref)
 }
+
+fn write_bone_ref(writer: &mut impl Write, bone_ref: u32, num_bones: u32) -> std::io::Result<()> {
+    if num_bones <= 255 {
+        writer.write_u8(bone_ref as u8)
+    } else {
+        writer.write_u32::<LittleEndian>(bone_ref)
+    }
+}
@@ -1,10 +1,14 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::{read_ext::MyReadBytesExt, xnb::asset::color::Color};
+use crate::{
+    read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
+    xnb::{asset::color::Color, trace},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct RenderDeferredEffect {
     pub alpha: f32,
     pub sharpness: f32,
@@ -17,15 +21,20 @@ pub struct RenderDeferredEffect {
 
 impl RenderDeferredEffect {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let alpha = reader.read_f32::<LittleEndian>()?;
-        let sharpness = reader.read_f32::<LittleEndian>()?;
-        let vertex_color_enabled = reader.read_bool()?;
-        let use_material_texture_for_reflectiveness = reader.read_bool()?;
-        let reflection_map = reader.read_7bit_length_string()?;
-        let material_0 = RenderDeferredEffectMaterial::read(reader)?;
-        let has_material_1 = reader.read_bool()?;
+        let alpha = trace::field("alpha", || reader.read_f32::<LittleEndian>())?;
+        let sharpness = trace::field("sharpness", || reader.read_f32::<LittleEndian>())?;
+        let vertex_color_enabled = trace::field("vertex_color_enabled", || reader.read_bool())?;
+        let use_material_texture_for_reflectiveness =
+            trace::field("use_material_texture_for_reflectiveness", || {
+                reader.read_bool()
+            })?;
+        let reflection_map = trace::field("reflection_map", || reader.read_7bit_length_string())?;
+        let material_0 = trace::field("material_0", || RenderDeferredEffectMaterial::read(reader))?;
+        let has_material_1 = trace::field("has_material_1", || reader.read_bool())?;
         let material_1 = if has_material_1 {
-            Some(RenderDeferredEffectMaterial::read(reader)?)
+            Some(trace::field("material_1", || {
+                RenderDeferredEffectMaterial::read(reader)
+            })?)
         } else {
             None
         };
@@ -43,9 +52,23 @@ impl RenderDeferredEffect {
             material_1,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_f32::<LittleEndian>(self.alpha)?;
+        writer.write_f32::<LittleEndian>(self.sharpness)?;
+        writer.write_bool(self.vertex_color_enabled)?;
+        writer.write_bool(self.use_material_texture_for_reflectiveness)?;
+        writer.write_7bit_length_string(&self.reflection_map)?;
+        self.material_0.write(writer)?;
+        writer.write_bool(self.material_1.is_some())?;
+        if let Some(material_1) = &self.material_1 {
+            material_1.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct RenderDeferredEffectMaterial {
     pub diffuse_texture_alpha_disabled: bool,
     pub alpha_mask_enabled: bool,
@@ -62,17 +85,20 @@ pub struct RenderDeferredEffectMaterial {
 
 impl RenderDeferredEffectMaterial {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let diffuse_texture_alpha_disabled = reader.read_bool()?;
-        let alpha_mask_enabled = reader.read_bool()?;
-        let diffuse_color = Color::read(reader)?;
-        let spec_amount = reader.read_f32::<LittleEndian>()?;
-        let spec_power = reader.read_f32::<LittleEndian>()?;
-        let emissive_amount = reader.read_f32::<LittleEndian>()?;
-        let normal_power = reader.read_f32::<LittleEndian>()?;
-        let reflectiveness = reader.read_f32::<LittleEndian>()?;
-        let diffuse_texture = reader.read_7bit_length_string()?;
-        let material_texture = reader.read_7bit_length_string()?;
-        let normal_texture = reader.read_7bit_length_string()?;
+        let diffuse_texture_alpha_disabled =
+            trace::field("diffuse_texture_alpha_disabled", || reader.read_bool())?;
+        let alpha_mask_enabled = trace::field("alpha_mask_enabled", || reader.read_bool())?;
+        let diffuse_color = trace::field("diffuse_color", || Color::read(reader))?;
+        let spec_amount = trace::field("spec_amount", || reader.read_f32::<LittleEndian>())?;
+        let spec_power = trace::field("spec_power", || reader.read_f32::<LittleEndian>())?;
+        let emissive_amount =
+            trace::field("emissive_amount", || reader.read_f32::<LittleEndian>())?;
+        let normal_power = trace::field("normal_power", || reader.read_f32::<LittleEndian>())?;
+        let reflectiveness = trace::field("reflectiveness", || reader.read_f32::<LittleEndian>())?;
+        let diffuse_texture = trace::field("diffuse_texture", || reader.read_7bit_length_string())?;
+        let material_texture =
+            trace::field("material_texture", || reader.read_7bit_length_string())?;
+        let normal_texture = trace::field("normal_texture", || reader.read_7bit_length_string())?;
         Ok(RenderDeferredEffectMaterial {
             diffuse_texture_alpha_disabled,
             alpha_mask_enabled,
@@ -87,4 +113,19 @@ impl RenderDeferredEffectMaterial {
             normal_texture,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_bool(self.diffuse_texture_alpha_disabled)?;
+        writer.write_bool(self.alpha_mask_enabled)?;
+        self.diffuse_color.write(writer)?;
+        writer.write_f32::<LittleEndian>(self.spec_amount)?;
+        writer.write_f32::<LittleEndian>(self.spec_power)?;
+        writer.write_f32::<LittleEndian>(self.emissive_amount)?;
+        writer.write_f32::<LittleEndian>(self.normal_power)?;
+        writer.write_f32::<LittleEndian>(self.reflectiveness)?;
+        writer.write_7bit_length_string(&self.diffuse_texture)?;
+        writer.write_7bit_length_string(&self.material_texture)?;
+        writer.write_7bit_length_string(&self.normal_texture)?;
+        Ok(())
+    }
 }
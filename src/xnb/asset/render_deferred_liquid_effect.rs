@@ -1,11 +1,15 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use glam::Vec2;
 
-use crate::{read_ext::MyReadBytesExt, xnb::asset::color::Color};
+use crate::{
+    read_ext::MyReadBytesExt,
+    write_ext::MyWriteBytesExt,
+    xnb::{asset::color::Color, trace},
+};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct RenderDeferredLiquidEffect {
     pub reflection_map: String,
     pub wave_height: f32,
@@ -30,25 +34,34 @@ pub struct RenderDeferredLiquidEffect {
 
 impl RenderDeferredLiquidEffect {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let reflection_map = reader.read_7bit_length_string()?;
-        let wave_height = reader.read_f32::<LittleEndian>()?;
-        let wave_speed_0 = reader.read_vec2()?;
-        let wave_speed_1 = reader.read_vec2()?;
-        let water_reflectiveness = reader.read_f32::<LittleEndian>()?;
-        let bottom_color = Color::read(reader)?;
-        let deep_bottom_color = Color::read(reader)?;
-        let water_emissive_amount = reader.read_f32::<LittleEndian>()?;
-        let water_spec_amount = reader.read_f32::<LittleEndian>()?;
-        let water_spec_power = reader.read_f32::<LittleEndian>()?;
-        let bottom_texture = reader.read_7bit_length_string()?;
-        let water_normal_map = reader.read_7bit_length_string()?;
-        let ice_reflectiveness = reader.read_f32::<LittleEndian>()?;
-        let ice_color = Color::read(reader)?;
-        let ice_emissive_amount = reader.read_f32::<LittleEndian>()?;
-        let ice_spec_amount = reader.read_f32::<LittleEndian>()?;
-        let ice_spec_power = reader.read_f32::<LittleEndian>()?;
-        let ice_diffuse_map = reader.read_7bit_length_string()?;
-        let ice_normal_map = reader.read_7bit_length_string()?;
+        let reflection_map = trace::field("reflection_map", || reader.read_7bit_length_string())?;
+        let wave_height = trace::field("wave_height", || reader.read_f32::<LittleEndian>())?;
+        let wave_speed_0 = trace::field("wave_speed_0", || reader.read_vec2())?;
+        let wave_speed_1 = trace::field("wave_speed_1", || reader.read_vec2())?;
+        let water_reflectiveness =
+            trace::field("water_reflectiveness", || reader.read_f32::<LittleEndian>())?;
+        let bottom_color = trace::field("bottom_color", || Color::read(reader))?;
+        let deep_bottom_color = trace::field("deep_bottom_color", || Color::read(reader))?;
+        let water_emissive_amount = trace::field("water_emissive_amount", || {
+            reader.read_f32::<LittleEndian>()
+        })?;
+        let water_spec_amount =
+            trace::field("water_spec_amount", || reader.read_f32::<LittleEndian>())?;
+        let water_spec_power =
+            trace::field("water_spec_power", || reader.read_f32::<LittleEndian>())?;
+        let bottom_texture = trace::field("bottom_texture", || reader.read_7bit_length_string())?;
+        let water_normal_map =
+            trace::field("water_normal_map", || reader.read_7bit_length_string())?;
+        let ice_reflectiveness =
+            trace::field("ice_reflectiveness", || reader.read_f32::<LittleEndian>())?;
+        let ice_color = trace::field("ice_color", || Color::read(reader))?;
+        let ice_emissive_amount =
+            trace::field("ice_emissive_amount", || reader.read_f32::<LittleEndian>())?;
+        let ice_spec_amount =
+            trace::field("ice_spec_amount", || reader.read_f32::<LittleEndian>())?;
+        let ice_spec_power = trace::field("ice_spec_power", || reader.read_f32::<LittleEndian>())?;
+        let ice_diffuse_map = trace::field("ice_diffuse_map", || reader.read_7bit_length_string())?;
+        let ice_normal_map = trace::field("ice_normal_map", || reader.read_7bit_length_string())?;
 
         Ok(RenderDeferredLiquidEffect {
             reflection_map,
@@ -72,4 +85,27 @@ impl RenderDeferredLiquidEffect {
             ice_normal_map,
         })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_7bit_length_string(&self.reflection_map)?;
+        writer.write_f32::<LittleEndian>(self.wave_height)?;
+        writer.write_vec2(self.wave_speed_0)?;
+        writer.write_vec2(self.wave_speed_1)?;
+        writer.write_f32::<LittleEndian>(self.water_reflectiveness)?;
+        self.bottom_color.write(writer)?;
+        self.deep_bottom_color.write(writer)?;
+        writer.write_f32::<LittleEndian>(self.water_emissive_amount)?;
+        writer.write_f32::<LittleEndian>(self.water_spec_amount)?;
+        writer.write_f32::<LittleEndian>(self.water_spec_power)?;
+        writer.write_7bit_length_string(&self.bottom_texture)?;
+        writer.write_7bit_length_string(&self.water_normal_map)?;
+        writer.write_f32::<LittleEndian>(self.ice_reflectiveness)?;
+        self.ice_color.write(writer)?;
+        writer.write_f32::<LittleEndian>(self.ice_emissive_amount)?;
+        writer.write_f32::<LittleEndian>(self.ice_spec_amount)?;
+        writer.write_f32::<LittleEndian>(self.ice_spec_power)?;
+        writer.write_7bit_length_string(&self.ice_diffuse_map)?;
+        writer.write_7bit_length_string(&self.ice_normal_map)?;
+        Ok(())
+    }
 }
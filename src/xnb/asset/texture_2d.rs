@@ -1,30 +1,36 @@
-use std::{borrow::Cow, io::Read};
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+};
 
 use bcndecode::{BcnDecoderFormat, BcnEncoding};
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use strum::FromRepr;
 
-#[derive(Debug)]
+use crate::xnb::trace;
+
+#[derive(Debug, serde::Serialize)]
 pub struct Texture2D {
     pub format: PixelFormat,
     pub width: u32,
     pub height: u32,
+    #[serde(serialize_with = "crate::xnb::asset::serialize_hex_list")]
     pub mips: Vec<Vec<u8>>,
 }
 
 impl Texture2D {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let format = reader.read_u32::<LittleEndian>()?;
+        let format = trace::field("format", || reader.read_u32::<LittleEndian>())?;
         let format = PixelFormat::from_repr(format)
             .ok_or_else(|| anyhow::anyhow!("unknown texture format: {}", format))?;
-        let width = reader.read_u32::<LittleEndian>()?;
-        let height = reader.read_u32::<LittleEndian>()?;
-        let mip_count = reader.read_u32::<LittleEndian>()?;
+        let width = trace::field("width", || reader.read_u32::<LittleEndian>())?;
+        let height = trace::field("height", || reader.read_u32::<LittleEndian>())?;
+        let mip_count = trace::field("mip_count", || reader.read_u32::<LittleEndian>())?;
         let mut mips = Vec::with_capacity(mip_count as usize);
         for _ in 0..mip_count {
-            let size = reader.read_u32::<LittleEndian>()?;
+            let size = trace::field("size", || reader.read_u32::<LittleEndian>())?;
             let mut mip = vec![0u8; size as usize];
-            reader.read_exact(&mut mip)?;
+            trace::bytes("mip", || reader.read_exact(&mut mip))?;
             mips.push(mip);
         }
         Ok(Texture2D {
@@ -35,6 +41,18 @@ impl Texture2D {
         })
     }
 
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.format as u32)?;
+        writer.write_u32::<LittleEndian>(self.width)?;
+        writer.write_u32::<LittleEndian>(self.height)?;
+        writer.write_u32::<LittleEndian>(self.mips.len() as u32)?;
+        for mip in &self.mips {
+            writer.write_u32::<LittleEndian>(mip.len() as u32)?;
+            writer.write_all(mip)?;
+        }
+        Ok(())
+    }
+
     pub fn bytes_per_row(&self, mip_index: usize) -> anyhow::Result<u32> {
         let bytes = bytes_per_row(self.width, mip_index, self.format)?;
         Ok(bytes)
@@ -104,6 +122,11 @@ pub fn bgra8_to_rgba8(bgra8: &[u8]) -> Vec<u8> {
     rgba8
 }
 
+/// the swizzle is symmetric, this only exists so call sites read correctly
+pub fn rgba8_to_bgra8(rgba8: &[u8]) -> Vec<u8> {
+    bgra8_to_rgba8(rgba8)
+}
+
 pub fn bytes_per_row(width: u32, mip_index: usize, format: PixelFormat) -> anyhow::Result<u32> {
     let block_dim = format.block_dim();
     let block_size = format.block_size();
@@ -120,8 +143,17 @@ pub fn rows_per_image(height: u32, mip_index: usize, format: PixelFormat) -> any
     Ok(blocks_y)
 }
 
+/// how a texture's values are meant to be read. this isn't stored in the xnb, it depends on what
+/// the texture is used for: colors such as diffuse maps are srgb, while data such as normal maps
+/// and material ("SPG") maps are linear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
 #[repr(u32)]
-#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub enum PixelFormat {
     /// bgra8?
     Color = 1,
@@ -130,19 +162,15 @@ pub enum PixelFormat {
 }
 
 impl PixelFormat {
-    pub fn to_wgpu(self) -> wgpu::TextureFormat {
-        // TODO: i do not understand color spaces at all, but i'm pretty sure
-        // color data is stored as SRGB, and other data like normals or "SPG" maps
-        // are likely linear. this information is not stored in the XNB, so the gpu
-        // format would have to be determined with additional info about what this
-        // texture will be used for. this might complicate the asset loader, but unless
-        // magicka devs were insane and sometimes used a single texture in both linear
-        // and srgb contexts, i can probably just get away with an assert on the expected
-        // format when loading a cached texture
-        match self {
-            PixelFormat::Color => wgpu::TextureFormat::Bgra8UnormSrgb,
-            PixelFormat::Bc1 => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
-            PixelFormat::Bc3 => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+    #[cfg(feature = "renderer")]
+    pub fn to_wgpu(self, color_space: ColorSpace) -> wgpu::TextureFormat {
+        match (self, color_space) {
+            (PixelFormat::Color, ColorSpace::Srgb) => wgpu::TextureFormat::Bgra8UnormSrgb,
+            (PixelFormat::Color, ColorSpace::Linear) => wgpu::TextureFormat::Bgra8Unorm,
+            (PixelFormat::Bc1, ColorSpace::Srgb) => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+            (PixelFormat::Bc1, ColorSpace::Linear) => wgpu::TextureFormat::Bc1RgbaUnorm,
+            (PixelFormat::Bc3, ColorSpace::Srgb) => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+            (PixelFormat::Bc3, ColorSpace::Linear) => wgpu::TextureFormat::Bc3RgbaUnorm,
         }
     }
 
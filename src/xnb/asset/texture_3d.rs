@@ -1,32 +1,36 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::xnb::asset::texture_2d::{PixelFormat, bytes_per_row, rows_per_image};
+use crate::xnb::{
+    asset::texture_2d::{PixelFormat, bytes_per_row, rows_per_image},
+    trace,
+};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Texture3D {
     pub format: PixelFormat,
     pub width: u32,
     pub height: u32,
     pub depth: u32,
+    #[serde(serialize_with = "crate::xnb::asset::serialize_hex_list")]
     pub mips: Vec<Vec<u8>>,
 }
 
 impl Texture3D {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let format = reader.read_u32::<LittleEndian>()?;
+        let format = trace::field("format", || reader.read_u32::<LittleEndian>())?;
         let format = PixelFormat::from_repr(format)
             .ok_or_else(|| anyhow::anyhow!("unknown texture format: {}", format))?;
-        let width = reader.read_u32::<LittleEndian>()?;
-        let height = reader.read_u32::<LittleEndian>()?;
-        let depth = reader.read_u32::<LittleEndian>()?;
-        let mip_count = reader.read_u32::<LittleEndian>()?;
+        let width = trace::field("width", || reader.read_u32::<LittleEndian>())?;
+        let height = trace::field("height", || reader.read_u32::<LittleEndian>())?;
+        let depth = trace::field("depth", || reader.read_u32::<LittleEndian>())?;
+        let mip_count = trace::field("mip_count", || reader.read_u32::<LittleEndian>())?;
         let mut mips = Vec::with_capacity(mip_count as usize);
         for _ in 0..mip_count {
-            let size = reader.read_u32::<LittleEndian>()?;
+            let size = trace::field("size", || reader.read_u32::<LittleEndian>())?;
             let mut mip = vec![0u8; size as usize];
-            reader.read_exact(&mut mip)?;
+            trace::bytes("mip", || reader.read_exact(&mut mip))?;
             mips.push(mip);
         }
         Ok(Texture3D {
@@ -38,6 +42,19 @@ impl Texture3D {
         })
     }
 
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.format as u32)?;
+        writer.write_u32::<LittleEndian>(self.width)?;
+        writer.write_u32::<LittleEndian>(self.height)?;
+        writer.write_u32::<LittleEndian>(self.depth)?;
+        writer.write_u32::<LittleEndian>(self.mips.len() as u32)?;
+        for mip in &self.mips {
+            writer.write_u32::<LittleEndian>(mip.len() as u32)?;
+            writer.write_all(mip)?;
+        }
+        Ok(())
+    }
+
     pub fn bytes_per_row(&self, mip_index: usize) -> anyhow::Result<u32> {
         let bytes = bytes_per_row(self.width, mip_index, self.format)?;
         Ok(bytes)
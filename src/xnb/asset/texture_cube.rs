@@ -0,0 +1,52 @@
+use std::io::{Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::xnb::{asset::texture_2d::PixelFormat, trace};
+
+pub const FACE_COUNT: usize = 6;
+
+#[derive(Debug, serde::Serialize)]
+pub struct TextureCube {
+    pub format: PixelFormat,
+    /// width and height of every face
+    pub size: u32,
+    /// each mip holds all faces of its level back to back, in the order +x, -x, +y, -y, +z, -z.
+    /// the xnb stores every mip of a face before moving on to the next face instead
+    #[serde(serialize_with = "crate::xnb::asset::serialize_hex_list")]
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl TextureCube {
+    pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
+        let format = trace::field("format", || reader.read_u32::<LittleEndian>())?;
+        let format = PixelFormat::from_repr(format)
+            .ok_or_else(|| anyhow::anyhow!("unknown texture format: {}", format))?;
+        let size = trace::field("size", || reader.read_u32::<LittleEndian>())?;
+        let mip_count = trace::field("mip_count", || reader.read_u32::<LittleEndian>())?;
+        let mut mips = vec![Vec::new(); mip_count as usize];
+        for _ in 0..FACE_COUNT {
+            for mip in mips.iter_mut() {
+                let size = trace::field("size", || reader.read_u32::<LittleEndian>())?;
+                let start = mip.len();
+                mip.resize(start + size as usize, 0);
+                trace::bytes("mip", || reader.read_exact(&mut mip[start..]))?;
+            }
+        }
+        Ok(TextureCube { format, size, mips })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.format as u32)?;
+        writer.write_u32::<LittleEndian>(self.size)?;
+        writer.write_u32::<LittleEndian>(self.mips.len() as u32)?;
+        for face in 0..FACE_COUNT {
+            for mip in &self.mips {
+                let face_size = mip.len() / FACE_COUNT;
+                writer.write_u32::<LittleEndian>(face_size as u32)?;
+                writer.write_all(&mip[face * face_size..(face + 1) * face_size])?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,26 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-#[derive(Debug)]
+use crate::xnb::trace;
+
+#[derive(Debug, serde::Serialize)]
 pub struct VertexBuffer {
+    #[serde(serialize_with = "crate::xnb::asset::serialize_hex")]
     pub data: Vec<u8>,
 }
 
 impl VertexBuffer {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let size = reader.read_u32::<LittleEndian>()? as usize;
+        let size = trace::field("size", || reader.read_u32::<LittleEndian>())? as usize;
         let mut data = vec![0; size];
-        reader.read_exact(&mut data)?;
+        trace::bytes("data", || reader.read_exact(&mut data))?;
         Ok(VertexBuffer { data })
     }
+
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.data.len() as u32)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
 }
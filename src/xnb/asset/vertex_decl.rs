@@ -1,23 +1,34 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+use crate::xnb::trace;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, serde::Serialize)]
 pub struct VertexDeclaration {
     pub elements: Vec<VertexElement>,
 }
 
 impl VertexDeclaration {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let num_elements = reader.read_u32::<LittleEndian>()? as usize;
+        let num_elements =
+            trace::field("num_elements", || reader.read_u32::<LittleEndian>())? as usize;
         let mut elements = Vec::with_capacity(num_elements);
         for _ in 0..num_elements {
-            let element = VertexElement::read(reader)?;
+            let element = trace::field("element", || VertexElement::read(reader))?;
             elements.push(element);
         }
         Ok(VertexDeclaration { elements })
     }
 
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u32::<LittleEndian>(self.elements.len() as u32)?;
+        for element in &self.elements {
+            element.write(writer)?;
+        }
+        Ok(())
+    }
+
     pub fn stride(&self) -> usize {
         self.elements
             .iter()
@@ -26,6 +37,7 @@ impl VertexDeclaration {
             .unwrap_or(0)
     }
 
+    #[cfg(feature = "renderer")]
     pub fn to_wgpu(&self) -> Vec<wgpu::VertexAttribute> {
         self.elements
             .iter()
@@ -39,7 +51,7 @@ impl VertexDeclaration {
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, serde::Serialize)]
 pub struct VertexElement {
     pub stream: u16,
     pub offset: u16,
@@ -51,12 +63,12 @@ pub struct VertexElement {
 
 impl VertexElement {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let stream = reader.read_u16::<LittleEndian>()?;
-        let offset = reader.read_u16::<LittleEndian>()?;
-        let format = ElementFormat::read(reader)?;
-        let method = ElementMethod::read(reader)?;
-        let usage = ElementUsage::read(reader)?;
-        let usage_index = reader.read_u8()?;
+        let stream = trace::field("stream", || reader.read_u16::<LittleEndian>())?;
+        let offset = trace::field("offset", || reader.read_u16::<LittleEndian>())?;
+        let format = trace::field("format", || ElementFormat::read(reader))?;
+        let method = trace::field("method", || ElementMethod::read(reader))?;
+        let usage = trace::field("usage", || ElementUsage::read(reader))?;
+        let usage_index = trace::field("usage_index", || reader.read_u8())?;
         Ok(VertexElement {
             stream,
             offset,
@@ -67,13 +79,23 @@ impl VertexElement {
         })
     }
 
+    pub fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        writer.write_u16::<LittleEndian>(self.stream)?;
+        writer.write_u16::<LittleEndian>(self.offset)?;
+        writer.write_u8(self.format as u8)?;
+        writer.write_u8(self.method as u8)?;
+        writer.write_u8(self.usage as u8)?;
+        writer.write_u8(self.usage_index)?;
+        Ok(())
+    }
+
     pub fn debug_string(&self) -> String {
         format!("{:?}-{:?}", self.format, self.usage)
     }
 }
 
 #[repr(u8)]
-#[derive(strum::FromRepr, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(strum::FromRepr, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum ElementFormat {
     Single,
     Vector2,
@@ -96,7 +118,7 @@ pub enum ElementFormat {
 
 impl ElementFormat {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u8()?;
+        let value = trace::field("value", || reader.read_u8())?;
         let format = ElementFormat::from_repr(value)
             .ok_or_else(|| anyhow::anyhow!("unknown element format: {value}"))?;
         Ok(format)
@@ -113,6 +135,7 @@ impl ElementFormat {
         }
     }
 
+    #[cfg(feature = "renderer")]
     pub fn to_wgpu(self) -> wgpu::VertexFormat {
         match self {
             ElementFormat::Single => wgpu::VertexFormat::Float32,
@@ -126,7 +149,7 @@ impl ElementFormat {
 }
 
 #[repr(u8)]
-#[derive(strum::FromRepr, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(strum::FromRepr, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum ElementMethod {
     Default,
     UV = 4,
@@ -136,7 +159,7 @@ pub enum ElementMethod {
 
 impl ElementMethod {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u8()?;
+        let value = trace::field("value", || reader.read_u8())?;
         let method = ElementMethod::from_repr(value)
             .ok_or_else(|| anyhow::anyhow!("unknown element method: {value}"))?;
         Ok(method)
@@ -144,7 +167,7 @@ impl ElementMethod {
 }
 
 #[repr(u8)]
-#[derive(strum::FromRepr, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(strum::FromRepr, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum ElementUsage {
     Position,
     BlendWeight,
@@ -163,7 +186,7 @@ pub enum ElementUsage {
 
 impl ElementUsage {
     pub fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
-        let value = reader.read_u8()?;
+        let value = trace::field("value", || reader.read_u8())?;
         let usage = ElementUsage::from_repr(value)
             .ok_or_else(|| anyhow::anyhow!("unknown element usage: {value}"))?;
         Ok(usage)
@@ -0,0 +1,175 @@
+//! opt-in recording of where each parsed field lives in a content stream, used by `dev inspect`.
+//! parsers wrap their reads in [`field`], which does nothing unless a trace is being recorded
+//! with [`record`] on the current thread.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    io::Read,
+};
+
+/// leaf values with longer debug representations are cut off
+const MAX_VALUE_LEN: usize = 48;
+
+thread_local! {
+    static TRACE: RefCell<Option<Trace>> = const { RefCell::new(None) };
+    /// whether `TRACE` is set, checked first so untraced parsing skips borrowing it for every
+    /// field and read
+    static TRACING: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Default)]
+pub struct Trace {
+    /// spans in the order they were started, so parents come before their children
+    pub spans: Vec<Span>,
+    /// number of bytes read through a [`TraceReader`] so far
+    pub position: u64,
+    depth: usize,
+}
+
+pub struct Span {
+    pub name: String,
+    pub type_name: String,
+    pub depth: usize,
+    pub start: u64,
+    /// `None` if the field failed to parse
+    pub end: Option<u64>,
+    /// only set for fields that didn't contain other traced fields
+    pub value: Option<String>,
+}
+
+impl Span {
+    pub fn is_leaf(&self, next: Option<&Span>) -> bool {
+        next.is_none_or(|next| next.depth <= self.depth)
+    }
+}
+
+/// wraps a reader to keep track of the position of the current trace
+pub struct TraceReader<R> {
+    inner: R,
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(inner: R) -> Self {
+        TraceReader { inner }
+    }
+}
+
+impl<R: Read> Read for TraceReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if !TRACING.get() {
+            return Ok(n);
+        }
+        TRACE.with_borrow_mut(|trace| {
+            if let Some(trace) = trace {
+                trace.position += n as u64;
+            }
+        });
+        Ok(n)
+    }
+}
+
+/// runs `f` while recording every traced field. reads must go through a [`TraceReader`]
+/// for the recorded offsets to mean anything
+pub fn record<T>(f: impl FnOnce() -> T) -> (T, Trace) {
+    TRACE.set(Some(Trace::default()));
+    TRACING.set(true);
+    let result = f();
+    TRACING.set(false);
+    let trace = TRACE.take().unwrap_or_default();
+    (result, trace)
+}
+
+/// labels the bytes consumed by `f` as the field `name`
+pub fn field<T: Debug, E>(name: &str, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    if !TRACING.get() {
+        return f();
+    }
+    traced(name, f, std::any::type_name::<T>(), |value| {
+        format!("{value:?}")
+    })
+}
+
+/// like [`field`], for raw buffers filled in place by `read_exact`
+pub fn bytes<E>(name: &str, f: impl FnOnce() -> Result<(), E>) -> Result<(), E> {
+    if !TRACING.get() {
+        return f();
+    }
+    traced(name, f, "bytes", |_| String::new())
+}
+
+fn traced<T, E>(
+    name: &str,
+    f: impl FnOnce() -> Result<T, E>,
+    type_name: &str,
+    describe: impl FnOnce(&T) -> String,
+) -> Result<T, E> {
+    let Some(index) = begin(name) else {
+        return f();
+    };
+
+    let result = f();
+
+    TRACE.with_borrow_mut(|trace| {
+        let Some(trace) = trace else {
+            return;
+        };
+        trace.depth -= 1;
+
+        let is_leaf = index + 1 == trace.spans.len();
+        let position = trace.position;
+        let span = &mut trace.spans[index];
+        span.type_name = short_type_name(type_name);
+        if let Ok(value) = &result {
+            span.end = Some(position);
+            if is_leaf {
+                let value = describe(value);
+                span.value = (!value.is_empty()).then(|| truncate(value));
+            }
+        }
+    });
+
+    result
+}
+
+fn begin(name: &str) -> Option<usize> {
+    TRACE.with_borrow_mut(|trace| {
+        let trace = trace.as_mut()?;
+        trace.spans.push(Span {
+            name: name.to_string(),
+            type_name: String::new(),
+            depth: trace.depth,
+            start: trace.position,
+            end: None,
+            value: None,
+        });
+        trace.depth += 1;
+        Some(trace.spans.len() - 1)
+    })
+}
+
+/// strips module paths, `alloc::vec::Vec<aldrheim::xnb::asset::model::Bone>` becomes `Vec<Bone>`
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            short.push_str(segment.rsplit("::").next().unwrap_or_default());
+            segment.clear();
+            short.push(c);
+        }
+    }
+    short.push_str(segment.rsplit("::").next().unwrap_or_default());
+    short
+}
+
+fn truncate(mut value: String) -> String {
+    if value.chars().count() > MAX_VALUE_LEN {
+        value = value.chars().take(MAX_VALUE_LEN).collect();
+        value.push_str("...");
+    }
+    value
+}